@@ -0,0 +1,172 @@
+use crate::error::{Result, TranscriptError};
+use crate::languages::match_rank;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Record of transcripts that were already processed, so interrupted or
+/// repeated batch runs can skip them.
+///
+/// The archive is a plain text file with one `video_id language format` entry
+/// per line. Entries are appended as soon as a video succeeds, so a crash never
+/// loses more than the video that was in progress.
+#[derive(Debug)]
+pub struct DownloadArchive {
+    path: PathBuf,
+    /// `(language, format)` entries by video ID
+    entries: HashMap<String, HashSet<(String, String)>>,
+    file: Option<File>,
+}
+
+impl DownloadArchive {
+    /// Load an archive, starting empty if the file does not exist yet.
+    /// Malformed lines are ignored.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(TranscriptError::io(
+                    format!("Failed to read archive {}", path.display()),
+                    e,
+                ))
+            }
+        };
+
+        let mut entries: HashMap<String, HashSet<(String, String)>> = HashMap::new();
+        for line in content.lines() {
+            let mut parts = line.split_whitespace();
+            if let (Some(video_id), Some(language), Some(format), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            {
+                entries
+                    .entry(video_id.to_string())
+                    .or_default()
+                    .insert((language.to_string(), format.to_string()));
+            }
+        }
+
+        Ok(Self {
+            path,
+            entries,
+            file: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.values().map(HashSet::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, video_id: &str, language: &str, format: &str) -> bool {
+        self.entries
+            .get(video_id)
+            .is_some_and(|entries| entries.contains(&(language.to_string(), format.to_string())))
+    }
+
+    /// The archived language of the video in `format` that best matches
+    /// `languages`, if any. Codes are compared the way
+    /// [`LanguagePolicy`](crate::languages::LanguagePolicy) compares them with
+    /// track codes, so `iw` finds an `he` entry, and with `match_region` `en`
+    /// finds `en-US`.
+    pub fn find(&self, video_id: &str, languages: &[&str], format: &str, match_region: bool) -> Option<&str> {
+        let entries = self.entries.get(video_id)?;
+        languages.iter().find_map(|requested| {
+            entries
+                .iter()
+                .filter(|(_, entry_format)| entry_format == format)
+                .filter_map(|(language, _)| Some((match_rank(requested, language, match_region)?, language)))
+                .min()
+                .map(|(_, language)| language.as_str())
+        })
+    }
+
+    /// Add an entry and append it to the archive file immediately.
+    pub fn record(&mut self, video_id: &str, language: &str, format: &str) -> Result<()> {
+        if self.contains(video_id, language, format) {
+            return Ok(());
+        }
+
+        if self.file.is_none() {
+            if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| TranscriptError::io(format!("Failed to open archive {}", self.path.display()), e))?;
+            self.file = Some(file);
+        }
+
+        if let Some(file) = self.file.as_mut() {
+            writeln!(file, "{} {} {}", video_id, language, format)?;
+            file.flush()?;
+        }
+        self.entries
+            .entry(video_id.to_string())
+            .or_default()
+            .insert((language.to_string(), format.to_string()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_reload() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.txt");
+
+        let mut archive = DownloadArchive::open(&path).unwrap();
+        assert!(archive.is_empty());
+        archive.record("dQw4w9WgXcQ", "en", "txt").unwrap();
+        archive.record("dQw4w9WgXcQ", "en", "txt").unwrap();
+        archive.record("_NuH3D4SN-c", "de", "md").unwrap();
+
+        let reloaded = DownloadArchive::open(&path).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert!(reloaded.contains("dQw4w9WgXcQ", "en", "txt"));
+        assert!(!reloaded.contains("dQw4w9WgXcQ", "en", "md"));
+        assert_eq!(reloaded.find("_NuH3D4SN-c", &["en", "de"], "md", false), Some("de"));
+        assert_eq!(reloaded.find("_NuH3D4SN-c", &["en"], "md", false), None);
+    }
+
+    #[test]
+    fn test_find_matches_like_the_language_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut archive = DownloadArchive::open(temp_dir.path().join("archive.txt")).unwrap();
+        archive.record("dQw4w9WgXcQ", "en-US", "txt").unwrap();
+        archive.record("dQw4w9WgXcQ", "en", "srt").unwrap();
+        archive.record("dQw4w9WgXcQ", "en-GB", "srt").unwrap();
+        archive.record("_NuH3D4SN-c", "iw", "txt").unwrap();
+
+        assert_eq!(archive.find("dQw4w9WgXcQ", &["en"], "txt", false), None);
+        assert_eq!(archive.find("dQw4w9WgXcQ", &["en"], "txt", true), Some("en-US"));
+        assert_eq!(archive.find("dQw4w9WgXcQ", &["en"], "srt", true), Some("en"));
+        assert_eq!(archive.find("dQw4w9WgXcQ", &["de", "en-gb"], "srt", false), Some("en-GB"));
+        assert_eq!(archive.find("_NuH3D4SN-c", &["he"], "txt", false), Some("iw"));
+    }
+
+    #[test]
+    fn test_open_ignores_malformed_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive.txt");
+        fs::write(&path, "dQw4w9WgXcQ en txt\ngarbage\n\na b c d\n").unwrap();
+
+        let archive = DownloadArchive::open(&path).unwrap();
+        assert_eq!(archive.len(), 1);
+    }
+}
@@ -0,0 +1,282 @@
+//! Synchronous versions of the [`YouTubeTranscript`](crate::YouTubeTranscript) API,
+//! for callers that do not run an async runtime themselves.
+//!
+//! Requires the `blocking` feature. Each client drives the async API on its own
+//! single-threaded tokio runtime, so its methods must not be called from async
+//! code (tokio panics when a runtime is blocked on inside another one).
+//!
+//! ```no_run
+//! let api = ytt::blocking::YouTubeTranscript::new();
+//! let transcript = api.fetch_transcript("dQw4w9WgXcQ", Some(vec!["en"]))?;
+//! println!("{} lines", transcript.transcript.len());
+//! # Ok::<(), ytt::TranscriptError>(())
+//! ```
+
+use crate::chatgpt::ChatGPT;
+use crate::error::Result;
+use crate::languages::LanguagePolicy;
+use crate::{TranscriptFilter, TranscriptInfo, TranscriptItem, TranscriptList, TranscriptResponse};
+use futures_util::StreamExt;
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+pub struct YouTubeTranscript {
+    inner: crate::YouTubeTranscript,
+    runtime: Runtime,
+}
+
+impl Default for YouTubeTranscript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl YouTubeTranscript {
+    /// # Panics
+    ///
+    /// If the tokio runtime cannot be started.
+    pub fn new() -> Self {
+        Self::from_async(crate::YouTubeTranscript::new())
+    }
+
+    /// # Panics
+    ///
+    /// If the tokio runtime cannot be started.
+    pub fn with_delay(delay_ms: u64) -> Self {
+        Self::from_async(crate::YouTubeTranscript::with_delay(delay_ms))
+    }
+
+    /// Wrap an async client
+    ///
+    /// # Panics
+    ///
+    /// If the tokio runtime cannot be started.
+    pub fn from_async(inner: crate::YouTubeTranscript) -> Self {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start the tokio runtime for ytt::blocking");
+        Self { inner, runtime }
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn extract_video_id(url_or_id: &str) -> Result<String> {
+        crate::YouTubeTranscript::extract_video_id(url_or_id)
+    }
+
+    pub fn extract_playlist_id(url_or_id: &str) -> Result<String> {
+        crate::YouTubeTranscript::extract_playlist_id(url_or_id)
+    }
+
+    pub fn extract_channel_url(url_or_handle: &str) -> Result<String> {
+        crate::YouTubeTranscript::extract_channel_url(url_or_handle)
+    }
+
+    pub fn get_playlist_video_ids(&self, playlist_id: &str) -> Result<Vec<String>> {
+        self.block_on(self.inner.get_playlist_video_ids(playlist_id))
+    }
+
+    pub fn get_channel_video_ids(&self, channel_url: &str) -> Result<Vec<String>> {
+        self.block_on(self.inner.get_channel_video_ids(channel_url))
+    }
+
+    pub fn get_video_title(&self, video_id: &str) -> Result<String> {
+        self.block_on(self.inner.get_video_title(video_id))
+    }
+
+    /// List all available transcripts for a video
+    pub fn list_transcripts(&self, video_id: &str) -> Result<TranscriptList> {
+        self.block_on(self.inner.list_transcripts(video_id))
+    }
+
+    /// Fetch transcript for a specific language
+    pub fn fetch_transcript(&self, video_id: &str, languages: Option<Vec<&str>>) -> Result<TranscriptResponse> {
+        self.block_on(self.inner.fetch_transcript(video_id, languages))
+    }
+
+    /// The lines of a transcript as they are parsed, see
+    /// [`crate::YouTubeTranscript::fetch_transcript_stream`]
+    pub fn fetch_transcript_iter(
+        &self,
+        video_id: &str,
+        languages: Option<Vec<&str>>,
+    ) -> impl Iterator<Item = Result<TranscriptItem>> + '_ {
+        let mut stream = Box::pin(self.inner.fetch_transcript_stream(video_id, languages));
+        std::iter::from_fn(move || self.block_on(stream.next()))
+    }
+
+    /// The transcripts of a playlist's videos, fetched as the iterator advances,
+    /// see [`crate::YouTubeTranscript::fetch_playlist_stream`]
+    pub fn fetch_playlist_iter(
+        &self,
+        playlist_id: &str,
+        languages: &[&str],
+    ) -> Result<impl Iterator<Item = (String, Result<TranscriptResponse>)> + '_> {
+        let mut stream = Box::pin(self.block_on(self.inner.fetch_playlist_stream(playlist_id, languages))?);
+        Ok(std::iter::from_fn(move || self.block_on(stream.next())))
+    }
+
+    /// See [`crate::YouTubeTranscript::fetch_transcript_with_policy`]
+    pub fn fetch_transcript_with_policy(
+        &self,
+        video_id: &str,
+        languages: &[&str],
+        policy: &LanguagePolicy,
+    ) -> Result<TranscriptResponse> {
+        self.block_on(self.inner.fetch_transcript_with_policy(video_id, languages, policy))
+    }
+
+    /// See [`crate::YouTubeTranscript::fetch_all_transcripts`]
+    pub fn fetch_all_transcripts(
+        &self,
+        video_id: &str,
+        filter: &TranscriptFilter,
+    ) -> Result<Vec<Result<TranscriptResponse>>> {
+        self.block_on(self.inner.fetch_all_transcripts(video_id, filter))
+    }
+
+    /// Fetch a track of `transcript_list`, see [`TranscriptList::fetch`]
+    pub fn fetch_from_list(
+        &self,
+        transcript_list: &TranscriptList,
+        transcript_info: &TranscriptInfo,
+    ) -> Result<TranscriptResponse> {
+        self.block_on(transcript_list.fetch(&self.inner, transcript_info))
+    }
+
+    /// Fetch the tracks of `transcript_list` that `filter` selects, see [`TranscriptList::fetch_all`]
+    pub fn fetch_all_from_list(
+        &self,
+        transcript_list: &TranscriptList,
+        filter: &TranscriptFilter,
+    ) -> Vec<Result<TranscriptResponse>> {
+        self.block_on(transcript_list.fetch_all(&self.inner, filter))
+    }
+
+    /// Translate a transcript to another language
+    pub fn translate_transcript(
+        &self,
+        video_id: &str,
+        source_languages: &[&str],
+        target_language: &str,
+    ) -> Result<TranscriptResponse> {
+        self.block_on(
+            self.inner
+                .translate_transcript(video_id, source_languages, target_language),
+        )
+    }
+
+    /// See [`crate::YouTubeTranscript::translate_transcript_with_fallback`]
+    pub fn translate_transcript_with_fallback(
+        &self,
+        video_id: &str,
+        source_languages: &[&str],
+        target_language: &str,
+        chatgpt: &ChatGPT,
+    ) -> Result<TranscriptResponse> {
+        self.block_on(self.inner.translate_transcript_with_fallback(
+            video_id,
+            source_languages,
+            target_language,
+            chatgpt,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_client() {
+        let api = YouTubeTranscript::with_delay(0);
+        assert_eq!(
+            YouTubeTranscript::extract_video_id("https://youtu.be/dQw4w9WgXcQ").unwrap(),
+            "dQw4w9WgXcQ"
+        );
+        // The runtime can drive the async client's futures
+        assert!(api.get_channel_video_ids("not a channel url").is_err());
+    }
+
+    /// A client whose requests are answered from recordings of a playlist with
+    /// one video, whose watch page also lists a video that was not recorded
+    fn replay_client() -> YouTubeTranscript {
+        use crate::transport::{Method, RecordedExchange, ReplayTransport};
+
+        let exchange = |method, url: &str, body: String| RecordedExchange {
+            method,
+            url: url.to_string(),
+            status: 200,
+            body: Some(body),
+            body_file: None,
+        };
+        let player = serde_json::json!({
+            "playabilityStatus": { "status": "OK" },
+            "videoDetails": { "title": "Replayed", "author": "Channel" },
+            "captions": { "playerCaptionsTracklistRenderer": { "captionTracks": [{
+                "languageCode": "en",
+                "baseUrl": "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en",
+                "name": { "runs": [{ "text": "English" }] },
+                "isTranslatable": false
+            }]}}
+        });
+        let transport = ReplayTransport::new(vec![
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/playlist?list=PLreplay",
+                r#"{"videoId":"dQw4w9WgXcQ"},{"videoId":"xxxxxxxxxxx"}"#.to_string(),
+            ),
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                r#"<script>{"INNERTUBE_API_KEY": "test-key"}</script>"#.to_string(),
+            ),
+            exchange(
+                Method::Post,
+                "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                player.to_string(),
+            ),
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en",
+                r#"<transcript><text start="0.5" dur="1.5">Hello</text><text start="2" dur="1">world</text></transcript>"#
+                    .to_string(),
+            ),
+        ]);
+        YouTubeTranscript::from_async(crate::YouTubeTranscript::with_transport(transport, 0))
+    }
+
+    #[test]
+    fn test_blocking_fetch_transcript() {
+        let api = replay_client();
+        let transcript = api.fetch_transcript("dQw4w9WgXcQ", None).unwrap();
+        assert_eq!(transcript.title.as_deref(), Some("Replayed"));
+        assert_eq!(transcript.transcript.len(), 2);
+
+        let lines: Vec<String> = api
+            .fetch_transcript_iter("dQw4w9WgXcQ", None)
+            .map(|item| item.unwrap().text)
+            .collect();
+        assert_eq!(lines, vec!["Hello", "world"]);
+
+        let mut items = api.fetch_transcript_iter("xxxxxxxxxxx", None);
+        assert!(items.next().unwrap().is_err());
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn test_blocking_fetch_playlist() {
+        let api = replay_client();
+        let results: Vec<_> = api.fetch_playlist_iter("PLreplay", &["en"]).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "dQw4w9WgXcQ");
+        assert_eq!(results[0].1.as_ref().unwrap().transcript[1].text, "world");
+        assert_eq!(results[1].0, "xxxxxxxxxxx");
+        assert!(results[1].1.is_err());
+
+        assert!(api.fetch_playlist_iter("PLmissing", &["en"]).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use crate::error::{Result, TranscriptError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// On-disk cache of LLM responses, so identical requests are only billed once.
+///
+/// Entries are JSON files named after a hash of the provider, model, prompt and
+/// input, which means any change to the prompt or the transcript is a miss.
+#[derive(Debug, Clone)]
+pub struct LlmCache {
+    dir: PathBuf,
+}
+
+/// A cached LLM completion together with the usage it was originally billed for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCompletion {
+    pub content: String,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// Summary of the cache contents, see [`LlmCache::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl LlmCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default cache location: `<user cache dir>/ytt/llm`
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("ytt").join("llm"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Build the cache key for a request.
+    ///
+    /// `prompt` is everything sent to the model apart from the transcript
+    /// (system prompt and instructions), `input` is the transcript itself.
+    pub fn key(provider: &str, model: &str, prompt: &str, input: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(provider.as_bytes());
+        hasher.update([0]);
+        hasher.update(model.as_bytes());
+        hasher.update([0]);
+        hasher.update(Sha256::digest(prompt.as_bytes()));
+        hasher.update(Sha256::digest(input.as_bytes()));
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Look up a cached completion. Unreadable entries are treated as misses.
+    pub fn get(&self, key: &str) -> Option<CachedCompletion> {
+        let content = fs::read_to_string(self.entry_path(key)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn put(&self, key: &str, completion: &CachedCompletion) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            TranscriptError::io(format!("Failed to create cache directory {}", self.dir.display()), e)
+        })?;
+        let json = serde_json::to_string(completion)?;
+        fs::write(self.entry_path(key), json)?;
+        Ok(())
+    }
+
+    /// Number and total size of the cached entries. A missing cache directory is empty.
+    pub fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for path in self.entry_paths()? {
+            stats.entries += 1;
+            stats.bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        }
+        Ok(stats)
+    }
+
+    /// Remove every cached entry, returning how many were removed.
+    pub fn clear(&self) -> Result<usize> {
+        let paths = self.entry_paths()?;
+        for path in &paths {
+            fs::remove_file(path)?;
+        }
+        Ok(paths.len())
+    }
+
+    fn entry_paths(&self) -> Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(TranscriptError::io(
+                    format!("Failed to read cache directory {}", self.dir.display()),
+                    e,
+                ))
+            }
+        };
+
+        Ok(entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_key_depends_on_every_part() {
+        let key = LlmCache::key("openai", "gpt-4o-mini", "prompt", "input");
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, LlmCache::key("openai", "gpt-4o-mini", "prompt", "input"));
+        assert_ne!(key, LlmCache::key("openai", "gpt-4o", "prompt", "input"));
+        assert_ne!(key, LlmCache::key("openai", "gpt-4o-mini", "other prompt", "input"));
+        assert_ne!(key, LlmCache::key("openai", "gpt-4o-mini", "prompt", "other input"));
+    }
+
+    #[test]
+    fn test_put_and_get() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = LlmCache::new(temp_dir.path().join("llm"));
+        let key = LlmCache::key("openai", "gpt-4o-mini", "prompt", "input");
+
+        assert!(cache.get(&key).is_none());
+
+        let completion = CachedCompletion {
+            content: "Cleaned".to_string(),
+            prompt_tokens: 100,
+            completion_tokens: 20,
+        };
+        cache.put(&key, &completion).unwrap();
+
+        let cached = cache.get(&key).unwrap();
+        assert_eq!(cached.content, "Cleaned");
+        assert_eq!(cached.prompt_tokens, 100);
+        assert_eq!(cached.completion_tokens, 20);
+    }
+
+    #[test]
+    fn test_stats_and_clear() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache = LlmCache::new(temp_dir.path().join("llm"));
+        assert_eq!(cache.stats().unwrap(), CacheStats::default());
+
+        let completion = CachedCompletion {
+            content: "Cleaned".to_string(),
+            prompt_tokens: 1,
+            completion_tokens: 1,
+        };
+        cache.put("a", &completion).unwrap();
+        cache.put("b", &completion).unwrap();
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.entries, 2);
+        assert!(stats.bytes > 0);
+
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert_eq!(cache.stats().unwrap().entries, 0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::{Result, TranscriptError};
+use crate::TranscriptItem;
+use serde::{Deserialize, Serialize};
+
+/// A titled section of a video, starting at `start` seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chapter {
+    pub start: f64,
+    pub title: String,
+}
+
+/// Check chapters returned by an LLM against the transcript they were generated from.
+///
+/// Every chapter must start within the time range covered by `items` and the
+/// start times must be strictly increasing. Each start is snapped to the start
+/// of the transcript item it falls in, so chapters always point at real captions.
+pub fn validate_chapters(chapters: Vec<Chapter>, items: &[TranscriptItem]) -> Result<Vec<Chapter>> {
+    let (first, last) = match (items.first(), items.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            return Err(TranscriptError::LlmResponseInvalid(
+                "Cannot create chapters for an empty transcript".to_string(),
+            ))
+        }
+    };
+
+    if chapters.is_empty() {
+        return Err(TranscriptError::LlmResponseInvalid(
+            "No chapters returned".to_string(),
+        ));
+    }
+
+    let range_end = last.start + last.duration;
+    let mut validated: Vec<Chapter> = Vec::with_capacity(chapters.len());
+
+    for chapter in chapters {
+        let title = chapter.title.trim().to_string();
+        if title.is_empty() {
+            return Err(TranscriptError::LlmResponseInvalid(format!(
+                "Chapter at {} has an empty title",
+                format_timestamp(chapter.start)
+            )));
+        }
+
+        if !chapter.start.is_finite() || chapter.start < 0.0 || chapter.start > range_end {
+            return Err(TranscriptError::LlmResponseInvalid(format!(
+                "Chapter '{}' starts at {}, outside the transcript ({} - {})",
+                title,
+                format_timestamp(chapter.start),
+                format_timestamp(first.start),
+                format_timestamp(range_end)
+            )));
+        }
+
+        // Snap to the last item starting at or before the chapter start
+        let start = items
+            .iter()
+            .rev()
+            .find(|item| item.start <= chapter.start)
+            .map(|item| item.start)
+            .unwrap_or(first.start);
+
+        if let Some(previous) = validated.last() {
+            if start <= previous.start {
+                return Err(TranscriptError::LlmResponseInvalid(format!(
+                    "Chapter '{}' at {} does not come after '{}' at {}",
+                    title,
+                    format_timestamp(chapter.start),
+                    previous.title,
+                    format_timestamp(previous.start)
+                )));
+            }
+        }
+
+        validated.push(Chapter { start, title });
+    }
+
+    Ok(validated)
+}
+
+/// Format seconds as `MM:SS`, or `H:MM:SS` for videos longer than an hour.
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let hours = total / 3600;
+    let minutes = (total % 3600) / 60;
+    let secs = total % 60;
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, secs)
+    } else {
+        format!("{:02}:{:02}", minutes, secs)
+    }
+}
+
+/// Parse a `MM:SS` or `H:MM:SS` timestamp back into seconds.
+pub fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let parts: Vec<u64> = timestamp
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match parts.as_slice() {
+        [minutes, seconds] if *seconds < 60 => Some((minutes * 60 + seconds) as f64),
+        [hours, minutes, seconds] if *minutes < 60 && *seconds < 60 => {
+            Some((hours * 3600 + minutes * 60 + seconds) as f64)
+        }
+        _ => None,
+    }
+}
+
+/// Render chapters the way YouTube expects them in a video description.
+///
+/// YouTube only recognises chapters when the first one starts at `00:00`, so
+/// an `Intro` chapter is added in front of a first chapter that starts later.
+pub fn to_youtube_description(chapters: &[Chapter]) -> String {
+    let mut lines = Vec::new();
+    if chapters.first().is_some_and(|chapter| format_timestamp(chapter.start) != format_timestamp(0.0)) {
+        lines.push(format!("{} Intro", format_timestamp(0.0)));
+    }
+    lines.extend(
+        chapters
+            .iter()
+            .map(|chapter| format!("{} {}", format_timestamp(chapter.start), chapter.title)),
+    );
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<TranscriptItem> {
+        [0.0, 4.5, 62.0, 130.0]
+            .iter()
+            .map(|&start| TranscriptItem {
+                text: format!("line at {}", start),
+                start,
+                duration: 5.0,
+            })
+            .collect()
+    }
+
+    fn chapter(start: f64, title: &str) -> Chapter {
+        Chapter {
+            start,
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0.0), "00:00");
+        assert_eq!(format_timestamp(65.9), "01:05");
+        assert_eq!(format_timestamp(3725.0), "1:02:05");
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("00:00"), Some(0.0));
+        assert_eq!(parse_timestamp("01:05"), Some(65.0));
+        assert_eq!(parse_timestamp("1:02:05"), Some(3725.0));
+        assert_eq!(parse_timestamp("01:75"), None);
+        assert_eq!(parse_timestamp("abc"), None);
+    }
+
+    #[test]
+    fn test_validate_chapters_snaps_to_items() {
+        let chapters = validate_chapters(
+            vec![chapter(0.0, "Intro"), chapter(63.0, " Setup "), chapter(131.0, "Wrap up")],
+            &items(),
+        )
+        .unwrap();
+
+        assert_eq!(chapters[1], chapter(62.0, "Setup"));
+        assert_eq!(chapters[2].start, 130.0);
+    }
+
+    #[test]
+    fn test_validate_chapters_rejects_out_of_range() {
+        assert!(validate_chapters(vec![chapter(0.0, "Intro"), chapter(500.0, "Later")], &items()).is_err());
+        assert!(validate_chapters(vec![chapter(-1.0, "Intro")], &items()).is_err());
+    }
+
+    #[test]
+    fn test_validate_chapters_rejects_non_monotonic() {
+        assert!(validate_chapters(vec![chapter(62.0, "Setup"), chapter(4.5, "Intro")], &items()).is_err());
+        // Two chapters snapping to the same item are not monotonic either
+        assert!(validate_chapters(vec![chapter(62.0, "A"), chapter(63.0, "B")], &items()).is_err());
+    }
+
+    #[test]
+    fn test_validate_chapters_rejects_empty() {
+        assert!(validate_chapters(vec![], &items()).is_err());
+        assert!(validate_chapters(vec![chapter(0.0, "Intro")], &[]).is_err());
+        assert!(validate_chapters(vec![chapter(0.0, "  ")], &items()).is_err());
+    }
+
+    #[test]
+    fn test_to_youtube_description() {
+        let chapters = vec![chapter(0.5, "Intro"), chapter(62.0, "Setup"), chapter(3725.0, "Q&A")];
+        assert_eq!(
+            to_youtube_description(&chapters),
+            "00:00 Intro\n01:02 Setup\n1:02:05 Q&A"
+        );
+
+        // The first chapter keeps its own start time
+        let chapters = vec![chapter(30.0, "Setup"), chapter(62.0, "Demo")];
+        assert_eq!(
+            to_youtube_description(&chapters),
+            "00:00 Intro\n00:30 Setup\n01:02 Demo"
+        );
+        assert_eq!(to_youtube_description(&[]), "");
+    }
+}
@@ -1,13 +1,43 @@
-use crate::error::{Result, TranscriptError};
+use crate::cache::{CachedCompletion, LlmCache};
+use crate::chapters::{validate_chapters, Chapter};
+use crate::error::{Result, Stage, TranscriptError};
+use crate::search::{format_window, TranscriptWindow};
+use crate::{TranscriptItem, TranscriptResponse, TranslationOrigin};
+use futures_util::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Mutex;
+use tracing::debug;
 
 const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+const PROVIDER: &str = "openai";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+/// Number of transcript items translated per request
+const TRANSLATION_BATCH_SIZE: usize = 50;
 
 #[derive(Debug, Serialize)]
 struct ChatRequest {
     model: String,
     messages: Vec<Message>,
     temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    format_type: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -19,6 +49,13 @@ struct Message {
 #[derive(Debug, Deserialize)]
 struct ChatResponse {
     choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -31,102 +68,748 @@ struct MessageResponse {
     content: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    delta: Delta,
+}
+
+#[derive(Debug, Deserialize)]
+struct Delta {
+    content: Option<String>,
+}
+
+/// Text of an LLM response, delivered in pieces as it is generated.
+pub type TextStream<'a> = Pin<Box<dyn Stream<Item = Result<String>> + Send + 'a>>;
+
+struct StreamState {
+    bytes: Pin<Box<dyn Stream<Item = reqwest::Result<Vec<u8>>> + Send>>,
+    /// Received bytes that do not form a complete line yet
+    buffer: Vec<u8>,
+    /// Everything yielded so far, cached once the stream completes
+    content: String,
+    usage: Option<Usage>,
+    cache_key: String,
+    /// `[DONE]` was received, so `content` is the complete response
+    done: bool,
+    /// The response is complete (or failed); nothing more will be yielded
+    finished: bool,
+    /// The connection has been closed by the server
+    closed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslationLines {
+    lines: Vec<TranslationLine>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslationLine {
+    id: usize,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChaptersResponse {
+    chapters: Vec<Chapter>,
+}
+
+/// Token usage and cost of the requests made by a [`ChatGPT`] client.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageSummary {
+    pub requests: u64,
+    /// Requests answered from the response cache, which are not billed
+    pub cached_requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Cost in US dollars, based on the published price of the model
+    pub cost: f64,
+}
+
+impl UsageSummary {
+    pub fn add(&mut self, other: &UsageSummary) {
+        self.requests += other.requests;
+        self.cached_requests += other.cached_requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost += other.cost;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests == 0
+    }
+}
+
+impl fmt::Display for UsageSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} request(s) ({} cached), {} prompt + {} completion tokens, ${:.4}",
+            self.requests, self.cached_requests, self.prompt_tokens, self.completion_tokens, self.cost
+        )
+    }
+}
+
+/// Price in US dollars per million (prompt, completion) tokens.
+fn model_pricing(model: &str) -> Option<(f64, f64)> {
+    match model {
+        "gpt-4o-mini" => Some((0.15, 0.60)),
+        "gpt-4o" => Some((2.50, 10.00)),
+        "gpt-4.1-mini" => Some((0.40, 1.60)),
+        "gpt-4.1" => Some((2.00, 8.00)),
+        _ => None,
+    }
+}
+
+/// Cost in US dollars of a request, or zero for models without known pricing.
+pub fn estimate_cost(model: &str, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+    model_pricing(model)
+        .map(|(prompt_price, completion_price)| {
+            (prompt_tokens as f64 * prompt_price + completion_tokens as f64 * completion_price)
+                / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// Rough token count of `text` (about four characters per token for English).
+fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+#[derive(Debug, Default)]
+struct Accounting {
+    /// Usage since the last call to `take_usage`
+    pending: UsageSummary,
+    /// Cost of every request made by this client, checked against `max_cost`
+    total_cost: f64,
+}
+
 pub struct ChatGPT {
     client: reqwest::Client,
     api_key: String,
+    model: String,
+    cache: Option<LlmCache>,
+    max_cost: Option<f64>,
+    accounting: Mutex<Accounting>,
 }
 
 impl ChatGPT {
     pub fn new(api_key: Option<String>) -> Result<Self> {
         let api_key = api_key
             .or_else(|| std::env::var("OPENAI_API_KEY").ok())
-            .ok_or_else(|| TranscriptError::HttpError(
+            .ok_or_else(|| TranscriptError::ConfigError(
                 "OpenAI API key not found. Set OPENAI_API_KEY environment variable or use --openai-key flag".to_string()
             ))?;
 
         Ok(Self {
             client: reqwest::Client::new(),
             api_key,
+            model: DEFAULT_MODEL.to_string(),
+            cache: None,
+            max_cost: None,
+            accounting: Mutex::new(Accounting::default()),
         })
     }
 
+    /// Serve repeated requests from `cache` instead of calling the API again.
+    pub fn with_cache(mut self, cache: LlmCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Refuse to send a request whose estimated cost would take the total
+    /// spent by this client above `max_cost` US dollars. With a model whose
+    /// price is not known, every request is refused, as its cost can't be estimated.
+    pub fn with_max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    /// Return the usage accumulated since the previous call and start over.
+    pub fn take_usage(&self) -> UsageSummary {
+        let mut accounting = self.accounting.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut accounting.pending)
+    }
+
     pub async fn cleanup_transcript(
         &self,
         transcript_text: &str,
         format_markdown: bool,
     ) -> Result<String> {
-        let format_instruction = if format_markdown {
-            "Format the cleaned transcript using Markdown syntax. Use appropriate markdown elements like:\n\
-            - **Bold** for emphasis on important points\n\
-            - *Italics* for subtle emphasis\n\
-            - Headings (##, ###) to organize sections if the transcript has clear topics\n\
-            - Bullet points (-) or numbered lists (1.) for lists\n\
-            - Blockquotes (>) for notable quotes\n\
-            - Line breaks between paragraphs\n\
-            Make it well-structured and readable with proper markdown formatting.\n\n"
-        } else {
-            ""
-        };
+        let cleaned_text = self
+            .complete(
+                CLEANUP_SYSTEM_PROMPT,
+                &cleanup_instructions(format_markdown),
+                transcript_text,
+                false,
+            )
+            .await?;
+
+        Ok(cleaned_text.trim().to_string())
+    }
+
+    /// Like [`ChatGPT::cleanup_transcript`], but yields the cleaned text while
+    /// it is being generated instead of waiting for the whole response.
+    pub async fn cleanup_transcript_stream(
+        &self,
+        transcript_text: &str,
+        format_markdown: bool,
+    ) -> Result<TextStream<'_>> {
+        self.stream_completion(
+            CLEANUP_SYSTEM_PROMPT,
+            &cleanup_instructions(format_markdown),
+            transcript_text,
+        )
+        .await
+    }
+
+    /// Ask the model to segment a timestamped transcript into titled chapters.
+    ///
+    /// The returned chapters are validated against `items` (see
+    /// [`crate::chapters::validate_chapters`]) before being handed back.
+    pub async fn generate_chapters(&self, items: &[TranscriptItem]) -> Result<Vec<Chapter>> {
+        let timestamped_text: String = items
+            .iter()
+            .map(|item| format!("[{:.1}] {}", item.start, item.text))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let instructions = "Split the following transcript into chapters, the way a creator would for a YouTube video description. \
+            Each line of the transcript starts with its start time in seconds in square brackets.\n\n\
+            Rules:\n\
+            - The first chapter must start at the first line of the transcript\n\
+            - Chapters must be in chronological order and use start times that appear in the transcript\n\
+            - Use between 3 and 15 chapters depending on the length of the transcript\n\
+            - Titles must be short (at most 8 words) and describe the topic of the chapter\n\n\
+            Respond only with a JSON object of the form \
+            {\"chapters\": [{\"start\": <seconds>, \"title\": \"<title>\"}]}.\n\n\
+            Transcript:\n\n";
+
+        let content = self
+            .complete(
+                "You are a helpful assistant that divides video transcripts into well-titled chapters.",
+                instructions,
+                &timestamped_text,
+                true,
+            )
+            .await?;
+
+        let response: ChaptersResponse = serde_json::from_str(strip_code_fence(&content))
+            .map_err(|e| {
+                TranscriptError::LlmResponseInvalid(format!("Failed to parse chapters: {}", e))
+            })?;
+
+        validate_chapters(response.chapters, items)
+    }
+
+    /// Answer a question using only the given transcript excerpts.
+    ///
+    /// The answer cites the `[mm:ss]` timestamps of the lines it relies on;
+    /// use [`crate::search::verify_citations`] to check them.
+    pub async fn answer_question(&self, question: &str, windows: &[TranscriptWindow]) -> Result<String> {
+        let excerpts = windows
+            .iter()
+            .map(format_window)
+            .collect::<Vec<_>>()
+            .join("\n\n...\n\n");
+
+        let instructions = format!(
+            "Answer the question below using only the transcript excerpts that follow. \
+            Each line of the excerpts starts with its timestamp in square brackets.\n\n\
+            Rules:\n\
+            - Support every statement by citing the timestamp of the line it comes from, \
+            copied exactly in square brackets, e.g. [01:23]\n\
+            - Only cite timestamps that appear in the excerpts\n\
+            - If the excerpts do not contain the answer, say so instead of guessing\n\n\
+            Question: {}\n\n\
+            Transcript excerpts:\n\n",
+            question
+        );
+
+        let answer = self
+            .complete(
+                "You are a helpful assistant that answers questions about videos from their transcripts, citing timestamps for every claim.",
+                &instructions,
+                &excerpts,
+                false,
+            )
+            .await?;
+
+        Ok(answer.trim().to_string())
+    }
+
+    /// Summarize a transcript as Markdown: a short overview followed by the key points.
+    pub async fn summarize_transcript(&self, transcript_text: &str) -> Result<String> {
+        let instructions = "Summarize the following video transcript. \
+            Start with a short paragraph describing what the video is about, \
+            followed by a bulleted list of its key points in the order they are made. \
+            Only use information from the transcript and leave out promotional content.\n\n\
+            Format the summary using Markdown.\n\n\
+            Transcript:\n\n";
 
-        let prompt = format!(
-            "Please clean up and improve the following transcript. \
-            Fix any grammar errors, improve sentence structure, remove filler words and repetitions, \
-            and make it more readable while preserving the original meaning and content. \
-            Do not add any information that wasn't in the original transcript.\n\n\
-            IMPORTANT: Remove all references to products, websites, courses, training programs, \
-            email addresses, social media handles, or any promotional content that the presenter may offer. \
-            Focus only on the educational or informational content.\n\n\
-            {}\
-            Transcript:\n\n{}",
-            format_instruction,
-            transcript_text
+        let summary = self
+            .complete(
+                "You are a helpful assistant that writes concise, accurate summaries of video transcripts.",
+                instructions,
+                transcript_text,
+                false,
+            )
+            .await?;
+
+        Ok(summary.trim().to_string())
+    }
+
+    /// Translate a transcript into `target_language` (a language code).
+    ///
+    /// Items are sent in batches and translated line by line, so every
+    /// translated item keeps the timing of its source item.
+    pub async fn translate_transcript(
+        &self,
+        transcript: &TranscriptResponse,
+        target_language: &str,
+    ) -> Result<TranscriptResponse> {
+        let instructions = format!(
+            "Translate the text of every line of the following transcript excerpt from the language \
+            with code '{}' into the language with code '{}'. \
+            Translate each line on its own and keep the meaning and tone of the original. \
+            Do not merge, split, drop or reorder lines.\n\n\
+            Respond only with a JSON object of the same form as the input, \
+            {{\"lines\": [{{\"id\": <id>, \"text\": \"<translated text>\"}}]}}, \
+            containing every id of the input.\n\n\
+            Transcript excerpt:\n\n",
+            transcript.language_code, target_language
         );
 
-        let request = ChatRequest {
-            model: "gpt-4o-mini".to_string(),
+        let mut translated_items = Vec::with_capacity(transcript.transcript.len());
+        for batch in transcript.transcript.chunks(TRANSLATION_BATCH_SIZE) {
+            let input = serde_json::to_string(&TranslationLines {
+                lines: batch
+                    .iter()
+                    .enumerate()
+                    .map(|(id, item)| TranslationLine {
+                        id,
+                        text: item.text.clone(),
+                    })
+                    .collect(),
+            })?;
+
+            let content = self
+                .complete(
+                    "You are a professional subtitle translator.",
+                    &instructions,
+                    &input,
+                    true,
+                )
+                .await?;
+
+            let translated = parse_translation(&content, batch.len())?;
+            translated_items.extend(batch.iter().zip(translated).map(|(item, text)| {
+                TranscriptItem {
+                    text,
+                    start: item.start,
+                    duration: item.duration,
+                }
+            }));
+        }
+
+        Ok(TranscriptResponse {
+            video_id: transcript.video_id.clone(),
+            title: transcript.title.clone(),
+            channel: transcript.channel.clone(),
+            upload_date: transcript.upload_date.clone(),
+            language: target_language.to_string(),
+            language_code: target_language.to_string(),
+            is_generated: true,
+            is_translatable: false,
+            translation_origin: Some(TranslationOrigin::Llm),
+            transcript: translated_items,
+        })
+    }
+
+    /// Send `instructions` followed by `input` (the transcript) to the model.
+    ///
+    /// Responses are served from the cache when possible, and every request
+    /// is checked against the cost budget before it is sent.
+    async fn complete(
+        &self,
+        system_prompt: &str,
+        instructions: &str,
+        input: &str,
+        json_output: bool,
+    ) -> Result<String> {
+        let cache_key = self.cache_key(system_prompt, instructions, input);
+        if let Some(content) = self.cached_response(&cache_key) {
+            return Ok(content);
+        }
+
+        self.check_budget(system_prompt, instructions, input)?;
+
+        let mut request = self.build_request(system_prompt, instructions, input);
+        if json_output {
+            request.response_format = Some(ResponseFormat {
+                format_type: "json_object".to_string(),
+            });
+        }
+
+        let response = self.send_request(&request).await?;
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| TranscriptError::http_transport(OPENAI_API_URL, e).in_stage(Stage::OpenAi))?;
+        let chat_response: ChatResponse = serde_json::from_slice(&body)
+            .map_err(|e| TranscriptError::json(Some(Stage::OpenAi), "Failed to parse OpenAI response", e))?;
+
+        let content = chat_response
+            .choices
+            .first()
+            .map(|choice| choice.message.content.clone())
+            .ok_or_else(|| TranscriptError::http(Some(Stage::OpenAi), "No choices in the OpenAI response"))?;
+
+        self.finish_request(&cache_key, &content, chat_response.usage);
+        Ok(content)
+    }
+
+    /// Streaming counterpart of `complete`: yields the response as it is generated.
+    async fn stream_completion(
+        &self,
+        system_prompt: &str,
+        instructions: &str,
+        input: &str,
+    ) -> Result<TextStream<'_>> {
+        let cache_key = self.cache_key(system_prompt, instructions, input);
+        if let Some(content) = self.cached_response(&cache_key) {
+            return Ok(Box::pin(stream::once(async move { Ok(content) })));
+        }
+
+        self.check_budget(system_prompt, instructions, input)?;
+
+        let mut request = self.build_request(system_prompt, instructions, input);
+        request.stream = true;
+        request.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+
+        let response = self.send_request(&request).await?;
+        let bytes = Box::pin(response.bytes_stream().map(|chunk| chunk.map(|b| b.to_vec())));
+        Ok(self.text_stream(bytes, cache_key))
+    }
+
+    /// The text deltas of a streamed completion's server-sent events
+    fn text_stream(
+        &self,
+        bytes: Pin<Box<dyn Stream<Item = reqwest::Result<Vec<u8>>> + Send>>,
+        cache_key: String,
+    ) -> TextStream<'_> {
+        let state = StreamState {
+            bytes,
+            buffer: Vec::new(),
+            content: String::new(),
+            usage: None,
+            cache_key,
+            done: false,
+            finished: false,
+            closed: false,
+        };
+
+        Box::pin(stream::unfold(state, move |mut state| async move {
+            loop {
+                if state.finished {
+                    return None;
+                }
+
+                // Handle every complete line that is already buffered
+                while let Some(data) = next_event_data(&mut state.buffer) {
+                    if data == "[DONE]" {
+                        state.done = true;
+                        break;
+                    }
+
+                    let chunk: StreamChunk = match serde_json::from_str(&data) {
+                        Ok(chunk) => chunk,
+                        Err(e) => {
+                            state.finished = true;
+                            return Some((
+                                Err(TranscriptError::json(
+                                    Some(Stage::OpenAi),
+                                    "Failed to parse OpenAI stream chunk",
+                                    e,
+                                )),
+                                state,
+                            ));
+                        }
+                    };
+
+                    if chunk.usage.is_some() {
+                        state.usage = chunk.usage;
+                    }
+
+                    let mut text: String = chunk
+                        .choices
+                        .into_iter()
+                        .filter_map(|choice| choice.delta.content)
+                        .collect();
+                    // Mirror the trimming done by the non-streaming methods
+                    if state.content.is_empty() {
+                        text = text.trim_start().to_string();
+                    }
+                    if !text.is_empty() {
+                        state.content.push_str(&text);
+                        return Some((Ok(text), state));
+                    }
+                }
+
+                // Only a response that ended with [DONE] is complete and may be cached
+                if state.done {
+                    state.finished = true;
+                    let usage = state.usage.take();
+                    self.finish_request(&state.cache_key, state.content.trim_end(), usage);
+                    return None;
+                }
+                if state.closed {
+                    state.finished = true;
+                    return Some((
+                        Err(TranscriptError::http(
+                            Some(Stage::OpenAi),
+                            "OpenAI closed the stream before the response was complete",
+                        )),
+                        state,
+                    ));
+                }
+
+                match state.bytes.next().await {
+                    Some(Ok(bytes)) => state.buffer.extend_from_slice(&bytes),
+                    Some(Err(e)) => {
+                        state.finished = true;
+                        return Some((
+                            Err(TranscriptError::http_transport(OPENAI_API_URL, e).in_stage(Stage::OpenAi)),
+                            state,
+                        ));
+                    }
+                    None => {
+                        // Terminate a trailing line that has no newline
+                        state.buffer.push(b'\n');
+                        state.closed = true;
+                    }
+                }
+            }
+        }))
+    }
+
+    fn cache_key(&self, system_prompt: &str, instructions: &str, input: &str) -> String {
+        LlmCache::key(
+            PROVIDER,
+            &self.model,
+            &format!("{}\n{}", system_prompt, instructions),
+            input,
+        )
+    }
+
+    /// Look up a cached response, counting it as an (unbilled) request.
+    fn cached_response(&self, cache_key: &str) -> Option<String> {
+        let cached = self.cache.as_ref()?.get(cache_key)?;
+        debug!("Using cached ChatGPT response {}", cache_key);
+        let mut accounting = self.accounting.lock().unwrap_or_else(|e| e.into_inner());
+        accounting.pending.requests += 1;
+        accounting.pending.cached_requests += 1;
+        Some(cached.content)
+    }
+
+    fn build_request(&self, system_prompt: &str, instructions: &str, input: &str) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
             messages: vec![
                 Message {
                     role: "system".to_string(),
-                    content: "You are a helpful assistant that cleans up and improves transcripts while preserving their original meaning. You remove promotional content like product mentions, website URLs, course offers, and training programs.".to_string(),
+                    content: system_prompt.to_string(),
                 },
                 Message {
                     role: "user".to_string(),
-                    content: prompt,
+                    content: format!("{}{}", instructions, input),
                 },
             ],
             temperature: 0.3,
-        };
+            response_format: None,
+            stream: false,
+            stream_options: None,
+        }
+    }
 
+    async fn send_request(&self, request: &ChatRequest) -> Result<reqwest::Response> {
         let response = self
             .client
             .post(OPENAI_API_URL)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
-            .json(&request)
+            .json(request)
             .send()
             .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to call OpenAI API: {}", e)))?;
+            .map_err(|e| TranscriptError::http_transport(OPENAI_API_URL, e).in_stage(Stage::OpenAi))?;
 
         if !response.status().is_success() {
-            let status = response.status();
+            let status = response.status().as_u16();
             let error_text = response.text().await.unwrap_or_default();
-            return Err(TranscriptError::HttpError(format!(
-                "OpenAI API error ({}): {}",
-                status, error_text
+            let mut error = TranscriptError::http_status(Some(Stage::OpenAi), OPENAI_API_URL, status);
+            if let TranscriptError::HttpError { message, .. } = &mut error {
+                message.push_str(&format!(": {}", error_text.trim()));
+            }
+            return Err(error);
+        }
+
+        Ok(response)
+    }
+
+    /// Record the usage of a completed request and cache its response.
+    fn finish_request(&self, cache_key: &str, content: &str, usage: Option<Usage>) {
+        let (prompt_tokens, completion_tokens) = usage
+            .map(|usage| (usage.prompt_tokens, usage.completion_tokens))
+            .unwrap_or_default();
+        let cost = estimate_cost(&self.model, prompt_tokens, completion_tokens);
+        debug!(
+            "ChatGPT request finished: {} prompt + {} completion tokens, ${:.4}",
+            prompt_tokens, completion_tokens, cost
+        );
+
+        {
+            let mut accounting = self.accounting.lock().unwrap_or_else(|e| e.into_inner());
+            accounting.pending.requests += 1;
+            accounting.pending.prompt_tokens += prompt_tokens;
+            accounting.pending.completion_tokens += completion_tokens;
+            accounting.pending.cost += cost;
+            accounting.total_cost += cost;
+        }
+
+        if let Some(cache) = &self.cache {
+            // The request has been paid for already; failing to cache it
+            // should not throw the response away
+            let _ = cache.put(
+                cache_key,
+                &CachedCompletion {
+                    content: content.to_string(),
+                    prompt_tokens,
+                    completion_tokens,
+                },
+            );
+        }
+    }
+
+    fn check_budget(&self, system_prompt: &str, instructions: &str, input: &str) -> Result<()> {
+        let Some(max_cost) = self.max_cost else {
+            return Ok(());
+        };
+        if model_pricing(&self.model).is_none() {
+            return Err(TranscriptError::BudgetExceeded(format!(
+                "the cost of {} is not known, so the budget cannot be enforced",
+                self.model
             )));
         }
 
-        let chat_response: ChatResponse = response.json().await.map_err(|e| {
-            TranscriptError::JsonParseError(format!("Failed to parse OpenAI response: {}", e))
-        })?;
+        // Assume the completion is about as long as the transcript, which holds
+        // for cleanup and overestimates everything else
+        let prompt_tokens =
+            estimate_tokens(system_prompt) + estimate_tokens(instructions) + estimate_tokens(input);
+        let estimated = estimate_cost(&self.model, prompt_tokens, estimate_tokens(input));
 
-        let cleaned_text = chat_response
-            .choices
-            .first()
-            .map(|choice| choice.message.content.clone())
-            .ok_or_else(|| TranscriptError::HttpError("No response from OpenAI API".to_string()))?;
+        let spent = self.accounting.lock().unwrap_or_else(|e| e.into_inner()).total_cost;
+        if spent + estimated > max_cost {
+            return Err(TranscriptError::BudgetExceeded(format!(
+                "next request is estimated at ${:.4} and ${:.4} of the ${:.2} budget is already spent",
+                estimated, spent, max_cost
+            )));
+        }
 
-        Ok(cleaned_text.trim().to_string())
+        Ok(())
+    }
+}
+
+const CLEANUP_SYSTEM_PROMPT: &str = "You are a helpful assistant that cleans up and improves transcripts while preserving their original meaning. You remove promotional content like product mentions, website URLs, course offers, and training programs.";
+
+fn cleanup_instructions(format_markdown: bool) -> String {
+    let format_instruction = if format_markdown {
+        "Format the cleaned transcript using Markdown syntax. Use appropriate markdown elements like:\n\
+        - **Bold** for emphasis on important points\n\
+        - *Italics* for subtle emphasis\n\
+        - Headings (##, ###) to organize sections if the transcript has clear topics\n\
+        - Bullet points (-) or numbered lists (1.) for lists\n\
+        - Blockquotes (>) for notable quotes\n\
+        - Line breaks between paragraphs\n\
+        Make it well-structured and readable with proper markdown formatting.\n\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "Please clean up and improve the following transcript. \
+        Fix any grammar errors, improve sentence structure, remove filler words and repetitions, \
+        and make it more readable while preserving the original meaning and content. \
+        Do not add any information that wasn't in the original transcript.\n\n\
+        IMPORTANT: Remove all references to products, websites, courses, training programs, \
+        email addresses, social media handles, or any promotional content that the presenter may offer. \
+        Focus only on the educational or informational content.\n\n\
+        {}\
+        Transcript:\n\n",
+        format_instruction
+    )
+}
+
+/// Take the payload of the next complete `data:` line of a server-sent event
+/// stream out of `buffer`, skipping blank lines, comments and other fields.
+fn next_event_data(buffer: &mut Vec<u8>) -> Option<String> {
+    while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=newline).collect();
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if let Some(data) = line.strip_prefix("data:") {
+            return Some(data.trim_start().to_string());
+        }
+    }
+    None
+}
+
+/// Parse a translated batch, returning the texts in input order.
+fn parse_translation(content: &str, expected_lines: usize) -> Result<Vec<String>> {
+    let response: TranslationLines = serde_json::from_str(strip_code_fence(content))
+        .map_err(|e| TranscriptError::LlmResponseInvalid(format!("Failed to parse translation: {}", e)))?;
+
+    let mut texts: Vec<Option<String>> = vec![None; expected_lines];
+    for line in response.lines {
+        match texts.get_mut(line.id) {
+            Some(slot) if slot.is_none() => *slot = Some(line.text.trim().to_string()),
+            _ => {
+                return Err(TranscriptError::LlmResponseInvalid(format!(
+                    "Unexpected or duplicate line {} in translation",
+                    line.id
+                )))
+            }
+        }
+    }
+
+    texts
+        .into_iter()
+        .enumerate()
+        .map(|(id, text)| {
+            text.ok_or_else(|| {
+                TranscriptError::LlmResponseInvalid(format!("Line {} missing from translation", id))
+            })
+        })
+        .collect()
+}
+
+/// Strip a surrounding Markdown code fence (```json ... ```) from a model response.
+fn strip_code_fence(content: &str) -> &str {
+    let trimmed = content.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+            rest.strip_suffix("```").unwrap_or(rest).trim()
+        }
+        None => trimmed,
     }
 }
 
@@ -146,6 +829,161 @@ mod tests {
         assert!(ChatGPT::new(Some("test-key".to_string())).is_ok());
     }
 
+    #[test]
+    fn test_estimate_cost() {
+        assert!((estimate_cost("gpt-4o-mini", 1_000_000, 1_000_000) - 0.75).abs() < 1e-9);
+        assert_eq!(estimate_cost("unknown-model", 1000, 1000), 0.0);
+    }
+
+    #[test]
+    fn test_usage_summary_add() {
+        let mut total = UsageSummary::default();
+        assert!(total.is_empty());
+
+        total.add(&UsageSummary {
+            requests: 2,
+            cached_requests: 1,
+            prompt_tokens: 100,
+            completion_tokens: 50,
+            cost: 0.01,
+        });
+        total.add(&UsageSummary {
+            requests: 1,
+            cached_requests: 0,
+            prompt_tokens: 10,
+            completion_tokens: 5,
+            cost: 0.001,
+        });
+
+        assert_eq!(total.requests, 3);
+        assert_eq!(total.cached_requests, 1);
+        assert_eq!(total.prompt_tokens, 110);
+        assert_eq!(
+            total.to_string(),
+            "3 request(s) (1 cached), 110 prompt + 55 completion tokens, $0.0110"
+        );
+    }
+
+    #[test]
+    fn test_check_budget() {
+        let chatgpt = ChatGPT::new(Some("test-key".to_string())).unwrap();
+        let transcript = "word ".repeat(100_000);
+        assert!(chatgpt.check_budget("system", "instructions", &transcript).is_ok());
+
+        let chatgpt = chatgpt.with_max_cost(0.01);
+        assert!(chatgpt.check_budget("system", "instructions", "short").is_ok());
+        assert!(matches!(
+            chatgpt.check_budget("system", "instructions", &transcript),
+            Err(TranscriptError::BudgetExceeded(_))
+        ));
+
+        // Without a price the cost would be estimated as zero, so nothing is sent
+        let mut unpriced = ChatGPT::new(Some("test-key".to_string())).unwrap().with_max_cost(1.0);
+        unpriced.model = "gpt-unpriced".to_string();
+        assert!(matches!(
+            unpriced.check_budget("system", "instructions", "short"),
+            Err(TranscriptError::BudgetExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_next_event_data() {
+        let mut buffer = b": keep-alive\n\ndata: {\"a\": 1}\r\n\ndata: [DONE]\ndata: {\"partial".to_vec();
+
+        assert_eq!(next_event_data(&mut buffer).as_deref(), Some("{\"a\": 1}"));
+        assert_eq!(next_event_data(&mut buffer).as_deref(), Some("[DONE]"));
+        assert_eq!(next_event_data(&mut buffer), None);
+        // Incomplete lines stay buffered until the rest arrives
+        assert_eq!(buffer, b"data: {\"partial");
+    }
+
+    #[test]
+    fn test_next_event_data_split_utf8() {
+        let text = "data: {\"choices\":[{\"delta\":{\"content\":\"caf\u{e9}\"}}]}\n";
+        let bytes = text.as_bytes();
+        let split = text.find('\u{e9}').unwrap() + 1;
+
+        let mut buffer = bytes[..split].to_vec();
+        assert_eq!(next_event_data(&mut buffer), None);
+        buffer.extend_from_slice(&bytes[split..]);
+
+        let chunk: StreamChunk = serde_json::from_str(&next_event_data(&mut buffer).unwrap()).unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("caf\u{e9}"));
+        assert!(chunk.usage.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_text_stream_caches_only_complete_responses() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let chatgpt = ChatGPT::new(Some("test-key".to_string()))
+            .unwrap()
+            .with_cache(LlmCache::new(temp_dir.path()));
+        let events = |body: &str| -> Pin<Box<dyn Stream<Item = reqwest::Result<Vec<u8>>> + Send>> {
+            let chunks: Vec<reqwest::Result<Vec<u8>>> = body
+                .lines()
+                .map(|line| Ok(format!("{}\n", line).into_bytes()))
+                .collect();
+            Box::pin(stream::iter(chunks))
+        };
+        let delta = |text: &str| {
+            format!("data: {{\"choices\":[{{\"delta\":{{\"content\":\"{}\"}}}}]}}", text)
+        };
+
+        // The connection drops before [DONE]
+        let body = format!("{}\n{}\n", delta("Hello"), delta(" wor"));
+        let items: Vec<_> = chatgpt
+            .text_stream(events(&body), "cut".to_string())
+            .collect()
+            .await;
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_deref().unwrap(), "Hello");
+        assert!(matches!(&items[2], Err(e) if e.stage() == Some(Stage::OpenAi)));
+        assert!(chatgpt.cache.as_ref().unwrap().get("cut").is_none());
+        assert_eq!(chatgpt.take_usage().requests, 0);
+
+        let body = format!("{}\n{}\ndata: [DONE]\n", delta("Hello"), delta(" world"));
+        let items: Vec<_> = chatgpt
+            .text_stream(events(&body), "complete".to_string())
+            .collect()
+            .await;
+        assert!(items.iter().all(Result::is_ok));
+        let cached = chatgpt.cache.as_ref().unwrap().get("complete").unwrap();
+        assert_eq!(cached.content, "Hello world");
+    }
+
+    #[test]
+    fn test_stream_chunk_with_usage() {
+        let chunk: StreamChunk = serde_json::from_str(
+            r#"{"choices":[],"usage":{"prompt_tokens":12,"completion_tokens":3,"total_tokens":15}}"#,
+        )
+        .unwrap();
+        assert!(chunk.choices.is_empty());
+        assert_eq!(chunk.usage.unwrap().completion_tokens, 3);
+    }
+
+    #[test]
+    fn test_parse_translation() {
+        let content = r#"{"lines": [{"id": 1, "text": " Welt "}, {"id": 0, "text": "Hallo"}]}"#;
+        assert_eq!(parse_translation(content, 2).unwrap(), vec!["Hallo", "Welt"]);
+
+        // Missing, duplicate and out of range lines are rejected
+        assert!(parse_translation(r#"{"lines": [{"id": 0, "text": "Hallo"}]}"#, 2).is_err());
+        assert!(parse_translation(
+            r#"{"lines": [{"id": 0, "text": "a"}, {"id": 0, "text": "b"}]}"#,
+            2
+        )
+        .is_err());
+        assert!(parse_translation(r#"{"lines": [{"id": 5, "text": "a"}]}"#, 1).is_err());
+        assert!(parse_translation("not json", 1).is_err());
+    }
+
+    #[test]
+    fn test_strip_code_fence() {
+        assert_eq!(strip_code_fence("{\"a\": 1}"), "{\"a\": 1}");
+        assert_eq!(strip_code_fence("```json\n{\"a\": 1}\n```"), "{\"a\": 1}");
+        assert_eq!(strip_code_fence("```\n{\"a\": 1}\n```"), "{\"a\": 1}");
+    }
+
     #[test]
     fn test_chatgpt_new_with_env_var() {
         std::env::set_var("OPENAI_API_KEY", "test-env-key");
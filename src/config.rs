@@ -0,0 +1,286 @@
+use crate::error::{Result, TranscriptError};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the project-local configuration file, looked up in the current directory
+pub const PROJECT_CONFIG_FILE: &str = ".ytt.toml";
+
+/// Option values that can be set in a configuration file, either at the top
+/// level or inside a `[profiles.<name>]` table.
+///
+/// Every field is optional; unset fields fall back to the next layer down.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub languages: Option<Vec<String>>,
+    pub format: Option<String>,
+    pub output: Option<String>,
+    pub output_template: Option<String>,
+    pub delay: Option<u64>,
+    pub openai_key: Option<String>,
+    pub max_cost: Option<f64>,
+    pub name: Option<bool>,
+    pub url: Option<bool>,
+    pub timestamps: Option<bool>,
+    /// InnerTube clients to try in order, see [`crate::innertube::ClientProfile`]
+    pub clients: Option<Vec<String>>,
+    /// Where to reach YouTube instead of `https://www.youtube.com`
+    pub youtube_url: Option<String>,
+    /// PO token for videos that require one, see [`crate::potoken`]
+    pub po_token: Option<String>,
+    pub visitor_data: Option<String>,
+    /// Helper command that prints a PO token, see [`crate::potoken::PoTokenCommand`]
+    pub po_token_command: Option<String>,
+}
+
+impl Settings {
+    /// Overlay `other` on top of `self`: fields set in `other` win.
+    pub fn merge(self, other: Settings) -> Settings {
+        Settings {
+            languages: other.languages.or(self.languages),
+            format: other.format.or(self.format),
+            output: other.output.or(self.output),
+            output_template: other.output_template.or(self.output_template),
+            delay: other.delay.or(self.delay),
+            openai_key: other.openai_key.or(self.openai_key),
+            max_cost: other.max_cost.or(self.max_cost),
+            name: other.name.or(self.name),
+            url: other.url.or(self.url),
+            timestamps: other.timestamps.or(self.timestamps),
+            clients: other.clients.or(self.clients),
+            youtube_url: other.youtube_url.or(self.youtube_url),
+            po_token: other.po_token.or(self.po_token),
+            visitor_data: other.visitor_data.or(self.visitor_data),
+            po_token_command: other.po_token_command.or(self.po_token_command),
+        }
+    }
+}
+
+/// Contents of a `ytt` configuration file.
+///
+/// ```toml
+/// delay = 1000
+/// languages = ["en"]
+/// clients = ["android", "web"]
+///
+/// [profiles.research]
+/// languages = ["de", "en"]
+/// format = "md"
+/// output = "notes/transcripts"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub defaults: Settings,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+/// On-disk layout of a configuration file. Top-level settings are spelled out
+/// rather than flattened so that misspelled keys are rejected.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    languages: Option<Vec<String>>,
+    format: Option<String>,
+    output: Option<String>,
+    output_template: Option<String>,
+    delay: Option<u64>,
+    openai_key: Option<String>,
+    max_cost: Option<f64>,
+    name: Option<bool>,
+    url: Option<bool>,
+    timestamps: Option<bool>,
+    clients: Option<Vec<String>>,
+    youtube_url: Option<String>,
+    po_token: Option<String>,
+    visitor_data: Option<String>,
+    po_token_command: Option<String>,
+    profiles: BTreeMap<String, Settings>,
+}
+
+impl From<RawConfig> for Config {
+    fn from(raw: RawConfig) -> Self {
+        Config {
+            defaults: Settings {
+                languages: raw.languages,
+                format: raw.format,
+                output: raw.output,
+                output_template: raw.output_template,
+                delay: raw.delay,
+                openai_key: raw.openai_key,
+                max_cost: raw.max_cost,
+                name: raw.name,
+                url: raw.url,
+                timestamps: raw.timestamps,
+                clients: raw.clients,
+                youtube_url: raw.youtube_url,
+                po_token: raw.po_token,
+                visitor_data: raw.visitor_data,
+                po_token_command: raw.po_token_command,
+            },
+            profiles: raw.profiles,
+        }
+    }
+}
+
+impl Config {
+    /// Parse a configuration file's contents. `source` is only used in error messages.
+    pub fn parse(content: &str, source: &str) -> Result<Self> {
+        toml::from_str::<RawConfig>(content)
+            .map(Config::from)
+            .map_err(|e| TranscriptError::ConfigError(format!("{}: {}", source, e)))
+    }
+
+    /// Load a configuration file, returning `None` if it does not exist.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content, &path.display().to_string()).map(Some),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(TranscriptError::ConfigError(format!(
+                "Failed to read {}: {}",
+                path.display(),
+                e
+            ))),
+        }
+    }
+
+    /// User configuration location: `<user config dir>/ytt/config.toml`
+    /// (`$XDG_CONFIG_HOME/ytt/config.toml` on Linux)
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("ytt").join("config.toml"))
+    }
+
+    /// Load the user configuration and the project-local `.ytt.toml`, with the
+    /// project file taking precedence. Missing files are simply skipped.
+    pub fn discover() -> Result<Self> {
+        let mut config = Config::default();
+        let paths = Self::user_path()
+            .into_iter()
+            .chain(std::iter::once(PathBuf::from(PROJECT_CONFIG_FILE)));
+        for path in paths {
+            if let Some(loaded) = Self::load(&path)? {
+                config = config.merge(loaded);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Overlay `other` on top of `self`, merging profiles with the same name field by field.
+    pub fn merge(mut self, other: Config) -> Config {
+        for (name, settings) in other.profiles {
+            let merged = match self.profiles.remove(&name) {
+                Some(existing) => existing.merge(settings),
+                None => settings,
+            };
+            self.profiles.insert(name, merged);
+        }
+        Config {
+            defaults: self.defaults.merge(other.defaults),
+            profiles: self.profiles,
+        }
+    }
+
+    /// The settings to apply for a run: the top-level values, overridden by the
+    /// selected profile if there is one.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Settings> {
+        let Some(name) = profile else {
+            return Ok(self.defaults.clone());
+        };
+
+        let settings = self.profiles.get(name).ok_or_else(|| {
+            let available = if self.profiles.is_empty() {
+                "none defined".to_string()
+            } else {
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            };
+            TranscriptError::ConfigError(format!(
+                "Unknown profile '{}' (available: {})",
+                name, available
+            ))
+        })?;
+
+        Ok(self.defaults.clone().merge(settings.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const SAMPLE: &str = r#"
+delay = 1000
+languages = ["en"]
+format = "text"
+
+[profiles.research]
+languages = ["de", "en"]
+format = "md"
+output = "notes"
+name = true
+"#;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(SAMPLE, "test").unwrap();
+        assert_eq!(config.defaults.delay, Some(1000));
+        assert_eq!(config.defaults.languages, Some(vec!["en".to_string()]));
+        assert_eq!(config.profiles["research"].format.as_deref(), Some("md"));
+
+        let config = Config::parse("clients = [\"ios\", \"web@2.20250101\"]", "test").unwrap();
+        assert_eq!(
+            config.defaults.clients,
+            Some(vec!["ios".to_string(), "web@2.20250101".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        assert!(Config::parse("dealy = 100", "test").is_err());
+        assert!(Config::parse("[profiles.x]\nformatt = \"md\"", "test").is_err());
+    }
+
+    #[test]
+    fn test_resolve_profile_overrides_defaults() {
+        let config = Config::parse(SAMPLE, "test").unwrap();
+
+        let settings = config.resolve(Some("research")).unwrap();
+        assert_eq!(settings.delay, Some(1000));
+        assert_eq!(settings.format.as_deref(), Some("md"));
+        assert_eq!(settings.languages, Some(vec!["de".to_string(), "en".to_string()]));
+        assert_eq!(settings.name, Some(true));
+
+        assert_eq!(config.resolve(None).unwrap(), config.defaults);
+    }
+
+    #[test]
+    fn test_resolve_unknown_profile() {
+        let config = Config::parse(SAMPLE, "test").unwrap();
+        let err = config.resolve(Some("missing")).unwrap_err();
+        assert!(err.to_string().contains("research"));
+    }
+
+    #[test]
+    fn test_merge_prefers_later_config() {
+        let user = Config::parse(SAMPLE, "user").unwrap();
+        let project = Config::parse("delay = 0\n[profiles.research]\nformat = \"json\"", "project").unwrap();
+        let merged = user.merge(project);
+
+        assert_eq!(merged.defaults.delay, Some(0));
+        assert_eq!(merged.defaults.format.as_deref(), Some("text"));
+        let research = &merged.profiles["research"];
+        assert_eq!(research.format.as_deref(), Some("json"));
+        assert_eq!(research.output.as_deref(), Some("notes"));
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(Config::load(&temp_dir.path().join("missing.toml")).unwrap().is_none());
+
+        let path = temp_dir.path().join("config.toml");
+        std::fs::write(&path, SAMPLE).unwrap();
+        assert!(Config::load(&path).unwrap().is_some());
+    }
+}
@@ -1,5 +1,49 @@
+use serde::Serialize;
+use std::fmt;
 use thiserror::Error;
 
+/// An underlying error kept as the [`source`](std::error::Error::source) of a [`TranscriptError`]
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The request an error happened in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// The video's watch page
+    Html,
+    /// The InnerTube player API
+    Innertube,
+    /// A caption track
+    Timedtext,
+    /// A playlist or channel page
+    Playlist,
+    /// The OpenAI API
+    OpenAi,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stage::Html => "html",
+            Stage::Innertube => "innertube",
+            Stage::Timedtext => "timedtext",
+            Stage::Playlist => "playlist",
+            Stage::OpenAi => "open_ai",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// ` (stage)` for messages, empty without a stage
+fn stage_suffix(stage: &Option<Stage>) -> String {
+    stage.map(|stage| format!(" ({})", stage)).unwrap_or_default()
+}
+
 #[derive(Error, Debug)]
 pub enum TranscriptError {
     #[error("Video unavailable: {0}")]
@@ -26,23 +70,49 @@ pub enum TranscriptError {
     #[error("Failed to create consent cookie for video: {0}")]
     FailedToCreateConsentCookie(String),
 
-    #[error("YouTube data unparsable for video: {0}")]
-    YouTubeDataUnparsable(String),
+    /// A page or API response did not have the expected shape. `id` is the
+    /// video, playlist or channel it was requested for.
+    #[error("YouTube data unparsable for {id} ({stage}): {reason}")]
+    YouTubeDataUnparsable { id: String, stage: Stage, reason: String },
 
     #[error("Protected video requires token: {0}")]
     PoTokenRequired(String),
 
+    /// A [`PoTokenProvider`](crate::potoken::PoTokenProvider) could not
+    /// produce a token, e.g. because its command could not be started (`source`)
+    #[error("PO token provider failed: {message}")]
+    PoTokenProviderFailed {
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
+
     #[error("Invalid video ID: {0}")]
     InvalidVideoId(String),
 
-    #[error("HTTP request failed: {0}")]
-    HttpError(String),
+    /// A request got an error status (`status`) or no response at all (`source`).
+    /// `video_id` is set for requests made for a video.
+    #[error("HTTP request failed{}: {message}", stage_suffix(.stage))]
+    HttpError {
+        stage: Option<Stage>,
+        video_id: Option<String>,
+        url: Option<String>,
+        status: Option<u16>,
+        message: String,
+        #[source]
+        source: Option<BoxError>,
+    },
 
-    #[error("Failed to parse XML: {0}")]
-    XmlParseError(String),
+    #[error("Failed to parse XML for video {video_id}: {message}")]
+    XmlParseError { video_id: String, message: String },
 
-    #[error("Failed to parse JSON: {0}")]
-    JsonParseError(String),
+    #[error("Failed to parse JSON{}: {message}", stage_suffix(.stage))]
+    JsonParseError {
+        stage: Option<Stage>,
+        message: String,
+        #[source]
+        source: Option<serde_json::Error>,
+    },
 
     #[error("Translation not available: {0}")]
     NotTranslatable(String),
@@ -50,19 +120,209 @@ pub enum TranscriptError {
     #[error("Translation language not available: {0}")]
     TranslationLanguageNotAvailable(String),
 
-    #[error("IO error: {0}")]
-    IoError(String),
+    #[error("Invalid LLM response: {0}")]
+    LlmResponseInvalid(String),
+
+    #[error("LLM cost budget exceeded: {0}")]
+    BudgetExceeded(String),
+
+    #[error("Invalid configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Invalid output template: {0}")]
+    InvalidOutputTemplate(String),
+
+    /// Input read from a file or stdin is not usable, e.g. a CSV file
+    /// without a `url` column
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Reading or writing a file or stream failed. `message` says what was
+    /// being done, e.g. `Failed to read archive notes.txt`.
+    #[error("IO error: {message}")]
+    IoError {
+        message: String,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl TranscriptError {
+    /// An [`HttpError`](TranscriptError::HttpError) without a status or cause
+    pub fn http(stage: Option<Stage>, message: impl Into<String>) -> Self {
+        TranscriptError::HttpError {
+            stage,
+            video_id: None,
+            url: None,
+            status: None,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// The error for a response from `url` with the non-success `status`
+    pub fn http_status(stage: Option<Stage>, url: impl Into<String>, status: u16) -> Self {
+        let reason = reqwest::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("Unknown error");
+        TranscriptError::HttpError {
+            stage,
+            video_id: None,
+            url: Some(url.into()),
+            status: Some(status),
+            message: format!("HTTP {} {}", status, reason),
+            source: None,
+        }
+    }
+
+    /// The error for a request to `url` that got no response because of `source`
+    pub fn http_transport(url: impl Into<String>, source: impl Into<BoxError>) -> Self {
+        let url = url.into();
+        TranscriptError::HttpError {
+            stage: None,
+            video_id: None,
+            message: format!("No response from {}", url),
+            url: Some(url),
+            status: None,
+            source: Some(source.into()),
+        }
+    }
+
+    /// An [`IoError`](TranscriptError::IoError) caused by `source`
+    pub fn io(message: impl Into<String>, source: std::io::Error) -> Self {
+        TranscriptError::IoError {
+            message: message.into(),
+            source,
+        }
+    }
+
+    /// A [`JsonParseError`](TranscriptError::JsonParseError) caused by `source`
+    pub fn json(stage: Option<Stage>, message: impl Into<String>, source: serde_json::Error) -> Self {
+        TranscriptError::JsonParseError {
+            stage,
+            message: message.into(),
+            source: Some(source),
+        }
+    }
+
+    /// Record the stage of an HTTP or JSON error that does not have one yet
+    pub fn in_stage(mut self, new_stage: Stage) -> Self {
+        if let TranscriptError::HttpError { stage, .. } | TranscriptError::JsonParseError { stage, .. } = &mut self {
+            stage.get_or_insert(new_stage);
+        }
+        self
+    }
+
+    /// Record the video an HTTP error happened for, if it does not have one yet
+    pub fn for_video(mut self, id: &str) -> Self {
+        if let TranscriptError::HttpError { video_id, .. } = &mut self {
+            video_id.get_or_insert_with(|| id.to_string());
+        }
+        self
+    }
+
+    /// The request the error happened in, if known
+    pub fn stage(&self) -> Option<Stage> {
+        match self {
+            TranscriptError::HttpError { stage, .. } | TranscriptError::JsonParseError { stage, .. } => *stage,
+            TranscriptError::YouTubeDataUnparsable { stage, .. } => Some(*stage),
+            TranscriptError::XmlParseError { .. } => Some(Stage::Timedtext),
+            _ => None,
+        }
+    }
+
+    /// HTTP status of the response that caused the error
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            TranscriptError::HttpError { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// Whether trying again later may succeed: rate limiting, bot checks,
+    /// server errors and requests that got no response
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TranscriptError::IpBlocked(_) | TranscriptError::RequestBlocked(_) => true,
+            TranscriptError::HttpError { status: None, source, .. } => source.is_some(),
+            TranscriptError::HttpError { status: Some(status), .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Whether the request can never succeed as made, e.g. because the video
+    /// does not exist or has no transcript in the requested languages
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            TranscriptError::VideoUnavailable(_)
+            | TranscriptError::TranscriptsDisabled(_)
+            | TranscriptError::NoTranscriptFound(_, _)
+            | TranscriptError::AgeRestricted(_)
+            | TranscriptError::VideoUnplayable(_, _)
+            | TranscriptError::InvalidVideoId(_)
+            | TranscriptError::NotTranslatable(_)
+            | TranscriptError::TranslationLanguageNotAvailable(_)
+            | TranscriptError::ConfigError(_)
+            | TranscriptError::InvalidOutputTemplate(_)
+            | TranscriptError::InvalidInput(_) => true,
+            TranscriptError::HttpError { status: Some(status), .. } => matches!(status, 400 | 404 | 410),
+            _ => false,
+        }
+    }
+
+    /// The message followed by those of its underlying causes, separated by `: `
+    pub fn full_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        message
+    }
+
+    /// Stable snake_case name of the error variant, for reports and logs.
+    /// Codes are never renamed; new variants get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            TranscriptError::VideoUnavailable(_) => "video_unavailable",
+            TranscriptError::TranscriptsDisabled(_) => "transcripts_disabled",
+            TranscriptError::NoTranscriptFound(_, _) => "no_transcript_found",
+            TranscriptError::AgeRestricted(_) => "age_restricted",
+            TranscriptError::IpBlocked(_) => "ip_blocked",
+            TranscriptError::RequestBlocked(_) => "request_blocked",
+            TranscriptError::VideoUnplayable(_, _) => "video_unplayable",
+            TranscriptError::FailedToCreateConsentCookie(_) => "failed_to_create_consent_cookie",
+            TranscriptError::YouTubeDataUnparsable { .. } => "youtube_data_unparsable",
+            TranscriptError::PoTokenRequired(_) => "po_token_required",
+            TranscriptError::PoTokenProviderFailed { .. } => "po_token_provider_failed",
+            TranscriptError::InvalidVideoId(_) => "invalid_video_id",
+            TranscriptError::HttpError { .. } => "http_error",
+            TranscriptError::XmlParseError { .. } => "xml_parse_error",
+            TranscriptError::JsonParseError { .. } => "json_parse_error",
+            TranscriptError::NotTranslatable(_) => "not_translatable",
+            TranscriptError::TranslationLanguageNotAvailable(_) => "translation_language_not_available",
+            TranscriptError::LlmResponseInvalid(_) => "llm_response_invalid",
+            TranscriptError::BudgetExceeded(_) => "budget_exceeded",
+            TranscriptError::ConfigError(_) => "config_error",
+            TranscriptError::InvalidOutputTemplate(_) => "invalid_output_template",
+            TranscriptError::InvalidInput(_) => "invalid_input",
+            TranscriptError::IoError { .. } => "io_error",
+        }
+    }
 }
 
 impl From<std::io::Error> for TranscriptError {
     fn from(err: std::io::Error) -> Self {
-        TranscriptError::IoError(err.to_string())
+        TranscriptError::io("Read or write failed", err)
     }
 }
 
 impl From<serde_json::Error> for TranscriptError {
     fn from(err: serde_json::Error) -> Self {
-        TranscriptError::JsonParseError(err.to_string())
+        TranscriptError::json(None, "Invalid JSON", err)
     }
 }
 
@@ -78,11 +338,54 @@ mod tests {
         assert!(err.to_string().contains("test123"));
     }
 
+    #[test]
+    fn test_error_code() {
+        assert_eq!(TranscriptError::IpBlocked("x".to_string()).code(), "ip_blocked");
+        assert_eq!(
+            TranscriptError::NoTranscriptFound("x".to_string(), vec![]).code(),
+            "no_transcript_found"
+        );
+        assert_eq!(TranscriptError::http(None, "x").code(), "http_error");
+    }
+
+    #[test]
+    fn test_http_error_details() {
+        let err = TranscriptError::http_status(Some(Stage::Innertube), "https://example.com/x", 404);
+        assert_eq!(err.to_string(), "HTTP request failed (innertube): HTTP 404 Not Found");
+        assert_eq!(err.status(), Some(404));
+        assert_eq!(err.stage(), Some(Stage::Innertube));
+        assert!(err.is_permanent() && !err.is_retryable());
+
+        let err = TranscriptError::http_status(None, "https://example.com/x", 503).in_stage(Stage::Html);
+        assert_eq!(err.stage(), Some(Stage::Html));
+        assert!(err.is_retryable() && !err.is_permanent());
+
+        let io_err = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+        let err = TranscriptError::http_transport("https://example.com/x", io_err).in_stage(Stage::Timedtext);
+        assert!(err.is_retryable());
+        assert_eq!(
+            err.full_message(),
+            "HTTP request failed (timedtext): No response from https://example.com/x: connection refused"
+        );
+
+        assert!(TranscriptError::IpBlocked("x".to_string()).is_retryable());
+        assert!(TranscriptError::VideoUnavailable("x".to_string()).is_permanent());
+        assert!(!TranscriptError::io("x", std::io::ErrorKind::Other.into()).is_retryable());
+    }
+
     #[test]
     fn test_error_from_io_error() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
         let transcript_err: TranscriptError = io_err.into();
-        assert!(matches!(transcript_err, TranscriptError::IoError(_)));
+        assert!(matches!(transcript_err, TranscriptError::IoError { .. }));
+        assert_eq!(transcript_err.full_message(), "IO error: Read or write failed: file not found");
+
+        let err = TranscriptError::io(
+            "Failed to read archive a.txt",
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "permission denied"),
+        );
+        assert_eq!(err.to_string(), "IO error: Failed to read archive a.txt");
+        assert!(matches!(&err, TranscriptError::IoError { source, .. } if source.kind() == std::io::ErrorKind::PermissionDenied));
     }
 
     #[test]
@@ -91,7 +394,7 @@ mod tests {
         assert!(json_err.is_err());
         if let Err(e) = json_err {
             let transcript_err: TranscriptError = e.into();
-            assert!(matches!(transcript_err, TranscriptError::JsonParseError(_)));
+            assert!(matches!(transcript_err, TranscriptError::JsonParseError { source: Some(_), .. }));
         }
     }
 }
@@ -0,0 +1,213 @@
+//! InnerTube client profiles: which YouTube app the player API is told it is
+//! talking to.
+//!
+//! YouTube regularly starts blocking one client or requiring PO tokens for it
+//! while others keep working, so the client is configurable and
+//! [`YouTubeTranscript::with_clients`](crate::YouTubeTranscript::with_clients)
+//! accepts a list of profiles to fall back through.
+
+use crate::error::{Result, TranscriptError};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Names accepted by [`ClientProfile::from_str`], with a description for completions
+pub const CLIENT_NAMES: &[(&str, &str)] = &[
+    ("android", "YouTube Android app (the default)"),
+    ("web", "Desktop website"),
+    ("ios", "YouTube iOS app"),
+    ("tv", "TV app (TVHTML5)"),
+    ("mweb", "Mobile website"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientProfile {
+    /// `clientName` of the request context, e.g. `ANDROID`
+    pub name: String,
+    /// `clientVersion` of the request context
+    pub version: String,
+    /// Numeric client ID, sent as `X-YouTube-Client-Name`
+    pub id: u32,
+    pub user_agent: String,
+    /// Further fields of the `client` context, e.g. the OS version
+    pub context: Map<String, Value>,
+}
+
+impl Default for ClientProfile {
+    fn default() -> Self {
+        Self::android()
+    }
+}
+
+impl ClientProfile {
+    pub fn android() -> Self {
+        Self::new(
+            "ANDROID",
+            "20.10.38",
+            3,
+            "com.google.android.youtube/20.10.38 (Linux; U; Android 11) gzip",
+            json!({ "androidSdkVersion": 30, "osName": "Android", "osVersion": "11" }),
+        )
+    }
+
+    pub fn web() -> Self {
+        Self::new(
+            "WEB",
+            "2.20250312.04.00",
+            1,
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/134.0.0.0 Safari/537.36",
+            json!({}),
+        )
+    }
+
+    pub fn ios() -> Self {
+        Self::new(
+            "IOS",
+            "20.10.4",
+            5,
+            "com.google.ios.youtube/20.10.4 (iPhone16,2; U; CPU iOS 18_3_2 like Mac OS X;)",
+            json!({
+                "deviceMake": "Apple",
+                "deviceModel": "iPhone16,2",
+                "osName": "iPhone",
+                "osVersion": "18.3.2.22D82"
+            }),
+        )
+    }
+
+    pub fn tv() -> Self {
+        Self::new(
+            "TVHTML5",
+            "7.20250312.16.00",
+            7,
+            "Mozilla/5.0 (ChromiumStylePlatform) Cobalt/Version",
+            json!({}),
+        )
+    }
+
+    pub fn mweb() -> Self {
+        Self::new(
+            "MWEB",
+            "2.20250311.03.00",
+            2,
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 18_3_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.3 Mobile/15E148 Safari/604.1",
+            json!({}),
+        )
+    }
+
+    fn new(name: &str, version: &str, id: u32, user_agent: &str, context: Value) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            id,
+            user_agent: user_agent.to_string(),
+            context: match context {
+                Value::Object(context) => context,
+                _ => Map::new(),
+            },
+        }
+    }
+
+    /// Claim another app version, e.g. once YouTube stops accepting the built-in one
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Body of a player request for `video_id`
+    pub(crate) fn player_request(&self, video_id: &str) -> Value {
+        let mut client = self.context.clone();
+        client.insert("clientName".to_string(), self.name.clone().into());
+        client.insert("clientVersion".to_string(), self.version.clone().into());
+        client.insert("hl".to_string(), "en".into());
+        json!({
+            "context": { "client": client },
+            "videoId": video_id
+        })
+    }
+
+    /// Headers YouTube expects from this client
+    pub(crate) fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("User-Agent", self.user_agent.clone()),
+            ("X-YouTube-Client-Name", self.id.to_string()),
+            ("X-YouTube-Client-Version", self.version.clone()),
+        ]
+    }
+}
+
+/// `name` or `name@version`, e.g. `web` or `android@20.12.1`
+impl FromStr for ClientProfile {
+    type Err = TranscriptError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, version) = match s.trim().split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (s.trim(), None),
+        };
+        let profile = match name.to_ascii_lowercase().as_str() {
+            "android" => Self::android(),
+            "web" => Self::web(),
+            "ios" => Self::ios(),
+            "tv" | "tvhtml5" => Self::tv(),
+            "mweb" => Self::mweb(),
+            _ => {
+                let known: Vec<_> = CLIENT_NAMES.iter().map(|(name, _)| *name).collect();
+                return Err(TranscriptError::ConfigError(format!(
+                    "Unknown InnerTube client '{}' (available: {})",
+                    name,
+                    known.join(", ")
+                )));
+            }
+        };
+        Ok(match version {
+            Some(version) if !version.is_empty() => profile.with_version(version),
+            _ => profile,
+        })
+    }
+}
+
+impl fmt::Display for ClientProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Whether the next client profile might succeed where one failed with `error`
+pub(crate) fn should_fall_back(error: &TranscriptError) -> bool {
+    matches!(
+        error,
+        TranscriptError::RequestBlocked(_) | TranscriptError::PoTokenRequired(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_client_profile() {
+        assert_eq!("android".parse::<ClientProfile>().unwrap(), ClientProfile::android());
+        assert_eq!("TVHTML5".parse::<ClientProfile>().unwrap().name, "TVHTML5");
+
+        let web: ClientProfile = "web@2.20250101.00.00".parse().unwrap();
+        assert_eq!(web.name, "WEB");
+        assert_eq!(web.version, "2.20250101.00.00");
+        assert_eq!(web.to_string(), "WEB@2.20250101.00.00");
+
+        let err = "symbian".parse::<ClientProfile>().unwrap_err();
+        assert!(err.to_string().contains("mweb"));
+    }
+
+    #[test]
+    fn test_player_request() {
+        let request = ClientProfile::ios().with_version("21.0").player_request("dQw4w9WgXcQ");
+        assert_eq!(request["videoId"], "dQw4w9WgXcQ");
+        assert_eq!(request["context"]["client"]["clientName"], "IOS");
+        assert_eq!(request["context"]["client"]["clientVersion"], "21.0");
+        assert_eq!(request["context"]["client"]["deviceMake"], "Apple");
+
+        let headers = ClientProfile::web().headers();
+        assert!(headers.contains(&("X-YouTube-Client-Name", "1".to_string())));
+    }
+}
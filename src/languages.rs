@@ -0,0 +1,202 @@
+/// Language codes YouTube commonly uses for caption tracks and translations,
+/// with their English names. Used to suggest values for `-l` and `-t`; codes
+/// that are not listed here can still be requested.
+pub const LANGUAGES: &[(&str, &str)] = &[
+    ("af", "Afrikaans"),
+    ("am", "Amharic"),
+    ("ar", "Arabic"),
+    ("az", "Azerbaijani"),
+    ("be", "Belarusian"),
+    ("bg", "Bulgarian"),
+    ("bn", "Bangla"),
+    ("bs", "Bosnian"),
+    ("ca", "Catalan"),
+    ("cs", "Czech"),
+    ("cy", "Welsh"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("en-GB", "English (United Kingdom)"),
+    ("en-US", "English (United States)"),
+    ("es", "Spanish"),
+    ("es-419", "Spanish (Latin America)"),
+    ("et", "Estonian"),
+    ("eu", "Basque"),
+    ("fa", "Persian"),
+    ("fi", "Finnish"),
+    ("fil", "Filipino"),
+    ("fr", "French"),
+    ("ga", "Irish"),
+    ("gl", "Galician"),
+    ("gu", "Gujarati"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("hr", "Croatian"),
+    ("hu", "Hungarian"),
+    ("hy", "Armenian"),
+    ("id", "Indonesian"),
+    ("is", "Icelandic"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ka", "Georgian"),
+    ("kk", "Kazakh"),
+    ("km", "Khmer"),
+    ("kn", "Kannada"),
+    ("ko", "Korean"),
+    ("lt", "Lithuanian"),
+    ("lv", "Latvian"),
+    ("mk", "Macedonian"),
+    ("ml", "Malayalam"),
+    ("mn", "Mongolian"),
+    ("mr", "Marathi"),
+    ("ms", "Malay"),
+    ("my", "Burmese"),
+    ("ne", "Nepali"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pa", "Punjabi"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("pt-BR", "Portuguese (Brazil)"),
+    ("pt-PT", "Portuguese (Portugal)"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("si", "Sinhala"),
+    ("sk", "Slovak"),
+    ("sl", "Slovenian"),
+    ("sq", "Albanian"),
+    ("sr", "Serbian"),
+    ("sv", "Swedish"),
+    ("sw", "Swahili"),
+    ("ta", "Tamil"),
+    ("te", "Telugu"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("ur", "Urdu"),
+    ("uz", "Uzbek"),
+    ("vi", "Vietnamese"),
+    ("zh-Hans", "Chinese (Simplified)"),
+    ("zh-Hant", "Chinese (Traditional)"),
+    ("zu", "Zulu"),
+];
+
+/// Deprecated language subtags YouTube and older software still use, with their replacements
+const LEGACY_CODES: &[(&str, &str)] = &[("in", "id"), ("iw", "he"), ("ji", "yi"), ("jw", "jv"), ("mo", "ro")];
+
+/// Normalise a language tag to the canonical BCP-47 form: `_` separators become
+/// `-`, the language is lowercase, scripts are title case and regions uppercase
+/// (`EN_us` becomes `en-US`, `zh-hant` becomes `zh-Hant`), and deprecated codes
+/// are replaced (`iw` becomes `he`).
+pub fn canonicalize(tag: &str) -> String {
+    let mut subtags = tag.trim().split(['-', '_']).filter(|subtag| !subtag.is_empty());
+    let Some(language) = subtags.next() else {
+        return String::new();
+    };
+
+    let language = language.to_ascii_lowercase();
+    let mut canonical = LEGACY_CODES
+        .iter()
+        .find(|(legacy, _)| *legacy == language)
+        .map_or(language, |(_, replacement)| replacement.to_string());
+
+    // Everything after an extension or private use singleton is lowercase
+    let mut in_extension = false;
+    for subtag in subtags {
+        canonical.push('-');
+        let alphabetic = subtag.chars().all(|c| c.is_ascii_alphabetic());
+        match subtag.len() {
+            1 => {
+                in_extension = true;
+                canonical.push_str(&subtag.to_ascii_lowercase());
+            }
+            2 if alphabetic && !in_extension => canonical.push_str(&subtag.to_ascii_uppercase()),
+            4 if alphabetic && !in_extension => {
+                canonical.push_str(&subtag[..1].to_ascii_uppercase());
+                canonical.push_str(&subtag[1..].to_ascii_lowercase());
+            }
+            _ => canonical.push_str(&subtag.to_ascii_lowercase()),
+        }
+    }
+    canonical
+}
+
+/// The language subtag of a canonical tag (`en` for `en-US`)
+pub fn primary_language(tag: &str) -> &str {
+    tag.split('-').next().unwrap_or(tag)
+}
+
+/// How well a track's language code matches a requested one, lower is better.
+/// `None` if it does not match at all.
+pub(crate) fn match_rank(requested: &str, track_code: &str, match_region: bool) -> Option<u8> {
+    let requested = canonicalize(requested);
+    let track_code = canonicalize(track_code);
+    if requested == track_code {
+        Some(0)
+    } else if match_region && primary_language(&requested) == primary_language(&track_code) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Which kind of track wins when a video has several in the preferred languages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackPreference {
+    /// For each language in turn, a manually created track, then a generated one
+    #[default]
+    ManualPerLanguage,
+    /// A manually created track in any of the languages before a generated one
+    /// in the most preferred language
+    ManualFirst,
+    /// For each language in turn, a generated track, then a manually created one
+    GeneratedPerLanguage,
+}
+
+/// How [`TranscriptList::find_with_policy`](crate::TranscriptList::find_with_policy)
+/// picks a track for a list of preferred languages.
+///
+/// Codes are always compared in their canonical form (see [`canonicalize`]).
+/// The default matches [`TranscriptList::find_transcript`](crate::TranscriptList::find_transcript).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguagePolicy {
+    /// Let a language match tracks of other regions of it (`en` matches `en-US`
+    /// and `en-GB`), after tracks in exactly that language
+    pub match_region: bool,
+    pub preference: TrackPreference,
+    /// When no track matches, have YouTube translate a track to the most
+    /// preferred language it can translate to
+    pub translate_fallback: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_languages_are_sorted_and_unique() {
+        assert!(LANGUAGES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        assert_eq!(canonicalize("EN_us"), "en-US");
+        assert_eq!(canonicalize("zh-hant-tw"), "zh-Hant-TW");
+        assert_eq!(canonicalize("es-419"), "es-419");
+        assert_eq!(canonicalize("iw"), "he");
+        assert_eq!(canonicalize("en-x-ab"), "en-x-ab");
+        assert_eq!(canonicalize(" "), "");
+        assert!(LANGUAGES.iter().all(|(code, _)| canonicalize(code) == *code));
+    }
+
+    #[test]
+    fn test_match_rank() {
+        assert_eq!(match_rank("en-us", "en-US", false), Some(0));
+        assert_eq!(match_rank("en", "en-GB", false), None);
+        assert_eq!(match_rank("en", "en-GB", true), Some(1));
+        assert_eq!(match_rank("en-US", "en", true), Some(1));
+        assert_eq!(match_rank("he", "iw", false), Some(0));
+        assert_eq!(match_rank("en", "es", true), None);
+    }
+}
@@ -1,15 +1,38 @@
+pub mod archive;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod cache;
+pub mod chapters;
 pub mod chatgpt;
+pub mod config;
 mod error;
+pub mod innertube;
+pub mod languages;
 mod parser;
-
-pub use error::{Result, TranscriptError};
+pub mod potoken;
+pub mod search;
+pub mod template;
+pub mod transport;
+
+use chatgpt::ChatGPT;
+pub use error::{Result, Stage, TranscriptError};
+use futures_util::future::Either;
+use futures_util::stream::{self, Stream, StreamExt};
+use innertube::{should_fall_back, ClientProfile};
+use languages::{match_rank, LanguagePolicy, TrackPreference};
 use parser::TranscriptParser;
+use potoken::{PoToken, PoTokenProvider};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, trace};
+use transport::{HttpRequest, HttpResponse, HttpTransport, ReqwestTransport};
 
-const WATCH_URL: &str = "https://www.youtube.com/watch?v={video_id}";
-const PLAYLIST_URL: &str = "https://www.youtube.com/playlist?list={playlist_id}";
-const INNERTUBE_API_URL: &str = "https://www.youtube.com/youtubei/v1/player?key={api_key}";
+const YOUTUBE_URL: &str = "https://www.youtube.com";
+const WATCH_PATH: &str = "/watch?v={video_id}";
+const PLAYLIST_PATH: &str = "/playlist?list={playlist_id}";
+const INNERTUBE_API_PATH: &str = "/youtubei/v1/player?key={api_key}";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptItem {
@@ -22,13 +45,48 @@ pub struct TranscriptItem {
 pub struct TranscriptResponse {
     pub video_id: String,
     pub title: Option<String>,
+    /// Name of the channel that uploaded the video
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Upload date as `YYYY-MM-DD`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_date: Option<String>,
     pub language: String,
     pub language_code: String,
     pub is_generated: bool,
     pub is_translatable: bool,
+    /// Who translated the transcript, `None` if it is in its original language
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation_origin: Option<TranslationOrigin>,
     pub transcript: Vec<TranscriptItem>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationOrigin {
+    /// Translated by YouTube (`tlang` parameter of the caption track)
+    YouTube,
+    /// Translated by an LLM because YouTube could not translate the track
+    Llm,
+}
+
+/// Whether a caption track was uploaded by the creator or generated by YouTube's speech recognition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptKind {
+    Manual,
+    Generated,
+}
+
+impl TranscriptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptKind::Manual => "manual",
+            TranscriptKind::Generated => "generated",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TranscriptInfo {
     pub language_code: String,
@@ -37,23 +95,103 @@ pub struct TranscriptInfo {
     pub is_translatable: bool,
     pub base_url: String,
     pub translation_languages: Vec<TranslationLanguage>,
+    /// Language code of the track YouTube translates this one from, `None` for original tracks
+    pub translated_from: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+impl TranscriptInfo {
+    pub fn kind(&self) -> TranscriptKind {
+        if self.is_generated {
+            TranscriptKind::Generated
+        } else {
+            TranscriptKind::Manual
+        }
+    }
+
+    /// The track YouTube produces by translating this one into `language_code`.
+    ///
+    /// Nothing is requested until the returned track is fetched with
+    /// [`TranscriptList::fetch`]. Errors name this track's language code, since a
+    /// track does not know which video it belongs to.
+    pub fn translate(&self, language_code: &str) -> Result<TranscriptInfo> {
+        if !self.is_translatable {
+            return Err(TranscriptError::NotTranslatable(self.language_code.clone()));
+        }
+        let target = self
+            .translation_languages
+            .iter()
+            .find(|t| t.language_code == language_code)
+            .ok_or_else(|| TranscriptError::TranslationLanguageNotAvailable(language_code.to_string()))?;
+
+        Ok(TranscriptInfo {
+            language_code: target.language_code.clone(),
+            language: target.language.clone(),
+            is_generated: true,
+            is_translatable: false,
+            base_url: format!("{}&tlang={}", self.base_url, target.language_code),
+            translation_languages: Vec::new(),
+            translated_from: Some(self.language_code.clone()),
+        })
+    }
+
+    /// Whether YouTube only serves this track with a PO token
+    pub fn requires_po_token(&self) -> bool {
+        self.base_url.contains("&exp=xpe")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct TranslationLanguage {
     pub language: String,
     pub language_code: String,
 }
 
+/// The caption tracks available for a video.
+///
+/// Serializes with the tracks and translation languages in a stable order
+/// (see [`TranscriptList::all_transcripts`]); track URLs are left out since
+/// they expire, only whether a track has one is included.
 pub struct TranscriptList {
     pub video_id: String,
     pub title: Option<String>,
+    pub channel: Option<String>,
+    /// Upload date as `YYYY-MM-DD`
+    pub upload_date: Option<String>,
     pub manually_created: HashMap<String, TranscriptInfo>,
     pub generated: HashMap<String, TranscriptInfo>,
     pub translation_languages: Vec<TranslationLanguage>,
 }
 
+/// Which tracks [`TranscriptList::select`] and
+/// [`YouTubeTranscript::fetch_all_transcripts`] include.
+///
+/// The default selects every manually created and auto-generated track and no translations.
+#[derive(Debug, Clone)]
+pub struct TranscriptFilter {
+    pub manually_created: bool,
+    pub generated: bool,
+    /// Languages to add YouTube translations for, unless the video already has
+    /// a selected track in that language
+    pub translations: Vec<String>,
+}
+
+impl Default for TranscriptFilter {
+    fn default() -> Self {
+        Self {
+            manually_created: true,
+            generated: true,
+            translations: Vec::new(),
+        }
+    }
+}
+
 impl TranscriptList {
+    /// Whether none of the tracks can be fetched without a PO token
+    fn requires_po_token(&self) -> bool {
+        let mut tracks = self.manually_created.values().chain(self.generated.values()).peekable();
+        tracks.peek().is_some() && tracks.all(TranscriptInfo::requires_po_token)
+    }
+
     pub fn find_transcript(&self, language_codes: &[&str]) -> Result<&TranscriptInfo> {
         // Try manually created first, then generated
         for lang_code in language_codes {
@@ -94,16 +232,181 @@ impl TranscriptList {
         ))
     }
 
+    /// Find a track for `language_codes`, most preferred first, following `policy`.
+    ///
+    /// With [`LanguagePolicy::translate_fallback`] the result may be a
+    /// translation made with [`TranscriptInfo::translate`]; fetch it with
+    /// [`TranscriptList::fetch`] either way.
+    pub fn find_with_policy(&self, language_codes: &[&str], policy: &LanguagePolicy) -> Result<TranscriptInfo> {
+        let (preferred, other) = match policy.preference {
+            TrackPreference::GeneratedPerLanguage => (&self.generated, &self.manually_created),
+            _ => (&self.manually_created, &self.generated),
+        };
+        let best = |tracks, language_code: &&str| best_match(tracks, language_code, policy.match_region);
+
+        let found = if policy.preference == TrackPreference::ManualFirst {
+            language_codes
+                .iter()
+                .find_map(|code| best(preferred, code))
+                .or_else(|| language_codes.iter().find_map(|code| best(other, code)))
+        } else {
+            language_codes
+                .iter()
+                .find_map(|code| best(preferred, code).or_else(|| best(other, code)))
+        };
+        if let Some(transcript) = found {
+            return Ok(transcript.clone());
+        }
+
+        if policy.translate_fallback {
+            if let Some(source) = self.all_transcripts().into_iter().find(|info| info.is_translatable) {
+                let target = language_codes.iter().find_map(|code| {
+                    source
+                        .translation_languages
+                        .iter()
+                        .filter_map(|t| Some((match_rank(code, &t.language_code, policy.match_region)?, t)))
+                        .min_by_key(|(rank, _)| *rank)
+                });
+                if let Some((_, target)) = target {
+                    return source.translate(&target.language_code);
+                }
+            }
+        }
+
+        Err(TranscriptError::NoTranscriptFound(
+            self.video_id.clone(),
+            language_codes.iter().map(|s| s.to_string()).collect(),
+        ))
+    }
+
+    /// Every track: manually created ones first, each group sorted by language code
     pub fn all_transcripts(&self) -> Vec<&TranscriptInfo> {
-        let mut all: Vec<&TranscriptInfo> = self.manually_created.values().collect();
-        all.extend(self.generated.values());
-        all
+        let mut manually_created: Vec<&TranscriptInfo> = self.manually_created.values().collect();
+        manually_created.sort_by(|a, b| a.language_code.cmp(&b.language_code));
+        let mut generated: Vec<&TranscriptInfo> = self.generated.values().collect();
+        generated.sort_by(|a, b| a.language_code.cmp(&b.language_code));
+
+        manually_created.extend(generated);
+        manually_created
+    }
+
+    /// The tracks `filter` selects, in [`TranscriptList::all_transcripts`] order,
+    /// followed by the requested translations.
+    ///
+    /// Translations are made from the first translatable track, so manually
+    /// created tracks are preferred as the source. A translation that cannot be
+    /// made is an error in its place, without affecting the other tracks.
+    pub fn select(&self, filter: &TranscriptFilter) -> Vec<Result<TranscriptInfo>> {
+        let all = self.all_transcripts();
+        let mut selected: Vec<Result<TranscriptInfo>> = all
+            .iter()
+            .filter(|info| if info.is_generated { filter.generated } else { filter.manually_created })
+            .map(|info| Ok((*info).clone()))
+            .collect();
+
+        for language_code in &filter.translations {
+            if selected.iter().flatten().any(|info| &info.language_code == language_code) {
+                continue;
+            }
+            let translation = all
+                .iter()
+                .find(|info| info.is_translatable)
+                .ok_or_else(|| TranscriptError::NotTranslatable(self.video_id.clone()))
+                .and_then(|source| source.translate(language_code));
+            selected.push(translation);
+        }
+        selected
+    }
+
+    /// Fetch every track `filter` selects, see [`TranscriptList::select`].
+    /// Each track succeeds or fails on its own, so one failed track does not
+    /// lose the others.
+    pub async fn fetch_all(&self, api: &YouTubeTranscript, filter: &TranscriptFilter) -> Vec<Result<TranscriptResponse>> {
+        let mut transcripts = Vec::new();
+        for selected in self.select(filter) {
+            let transcript = match selected {
+                Ok(transcript_info) => self.fetch(api, &transcript_info).await,
+                Err(e) => Err(e),
+            };
+            transcripts.push(transcript);
+        }
+        transcripts
+    }
+
+    /// Fetch one of this list's tracks, or a translation of one made with
+    /// [`TranscriptInfo::translate`], without requesting the watch page again.
+    ///
+    /// List once with [`YouTubeTranscript::list_transcripts`] to fetch several
+    /// tracks of a video for the cost of one listing.
+    pub async fn fetch(&self, api: &YouTubeTranscript, transcript_info: &TranscriptInfo) -> Result<TranscriptResponse> {
+        api.fetch_transcript_data(self, transcript_info).await
+    }
+
+    /// Languages the translatable tracks can be translated to, sorted by language code
+    pub fn sorted_translation_languages(&self) -> Vec<&TranslationLanguage> {
+        let mut languages: Vec<&TranslationLanguage> = self.translation_languages.iter().collect();
+        languages.sort_by(|a, b| a.language_code.cmp(&b.language_code));
+        languages
+    }
+}
+
+impl Serialize for TranscriptList {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Track<'a> {
+            language_code: &'a str,
+            language: &'a str,
+            kind: TranscriptKind,
+            is_generated: bool,
+            is_translatable: bool,
+            has_base_url: bool,
+        }
+
+        #[derive(Serialize)]
+        struct List<'a> {
+            video_id: &'a str,
+            title: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            channel: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            upload_date: Option<&'a str>,
+            transcripts: Vec<Track<'a>>,
+            translation_languages: Vec<&'a TranslationLanguage>,
+        }
+
+        List {
+            video_id: &self.video_id,
+            title: self.title.as_deref(),
+            channel: self.channel.as_deref(),
+            upload_date: self.upload_date.as_deref(),
+            transcripts: self
+                .all_transcripts()
+                .into_iter()
+                .map(|info| Track {
+                    language_code: &info.language_code,
+                    language: &info.language,
+                    kind: info.kind(),
+                    is_generated: info.is_generated,
+                    is_translatable: info.is_translatable,
+                    has_base_url: !info.base_url.is_empty(),
+                })
+                .collect(),
+            translation_languages: self.sorted_translation_languages(),
+        }
+        .serialize(serializer)
     }
 }
 
 pub struct YouTubeTranscript {
-    client: reqwest::Client,
+    transport: Arc<dyn HttpTransport>,
     delay_ms: u64,
+    /// Where watch pages, playlists and the InnerTube API are requested from
+    base_url: String,
+    /// InnerTube clients to try in turn, never empty
+    clients: Vec<ClientProfile>,
+    po_token_provider: Option<Arc<dyn PoTokenProvider>>,
+    /// The last video's PO token, so the provider is asked once per video
+    last_po_token: Mutex<Option<(String, PoToken)>>,
 }
 
 impl Default for YouTubeTranscript {
@@ -118,23 +421,93 @@ impl YouTubeTranscript {
     }
 
     pub fn with_delay(delay_ms: u64) -> Self {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert(
-            reqwest::header::ACCEPT_LANGUAGE,
-            reqwest::header::HeaderValue::from_static("en-US"),
-        );
+        Self::with_transport(ReqwestTransport::new(), delay_ms)
+    }
 
+    /// A client that sends its requests through `transport`, e.g. a
+    /// [`transport::ReplayTransport`] serving recorded responses
+    pub fn with_transport(transport: impl HttpTransport + 'static, delay_ms: u64) -> Self {
         Self {
-            client: reqwest::Client::builder()
-                .cookie_store(true)
-                .default_headers(headers)
-                .build()
-                .expect("Failed to create HTTP client"),
+            transport: Arc::new(transport),
             delay_ms,
+            base_url: YOUTUBE_URL.to_string(),
+            clients: vec![ClientProfile::default()],
+            po_token_provider: None,
+            last_po_token: Mutex::new(None),
+        }
+    }
+
+    /// Send PO tokens from `provider` with player requests and with requests
+    /// for caption tracks that need one, see [`potoken`]
+    pub fn with_po_token_provider(mut self, provider: impl PoTokenProvider + 'static) -> Self {
+        self.po_token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Request player responses as the first of `clients`, falling back to the
+    /// next one when YouTube blocks a client or wants a PO token for all of
+    /// its caption tracks. An empty list keeps the default (`ANDROID`).
+    pub fn with_clients(mut self, clients: impl IntoIterator<Item = ClientProfile>) -> Self {
+        let clients: Vec<_> = clients.into_iter().collect();
+        if !clients.is_empty() {
+            self.clients = clients;
+        }
+        self
+    }
+
+    /// Request watch pages, playlists and the InnerTube API from `base_url`
+    /// (e.g. `http://127.0.0.1:8080`) instead of `https://www.youtube.com`, for
+    /// tests against a mock server or a proxy
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Send a request for `id` (a video, playlist or channel), turning error
+    /// statuses into errors and recording `stage` and the video in them
+    async fn send(&self, request: HttpRequest, id: &str, stage: Stage) -> Result<HttpResponse> {
+        // Playlist and channel pages are the only requests not made for a video
+        let for_video = |error: TranscriptError| match stage {
+            Stage::Playlist => error,
+            _ => error.for_video(id),
+        };
+        let url = request.url.clone();
+        let response = self
+            .transport
+            .send(request)
+            .await
+            .map_err(|e| for_video(e.in_stage(stage)))?;
+        if response.status == 429 {
+            return Err(TranscriptError::IpBlocked(id.to_string()));
+        }
+        if !response.is_success() {
+            return Err(for_video(TranscriptError::http_status(Some(stage), url, response.status)));
+        }
+        Ok(response)
+    }
+
+    /// The PO token for `video_id`, `None` without a provider
+    async fn po_token(&self, video_id: &str) -> Result<Option<PoToken>> {
+        let Some(provider) = &self.po_token_provider else {
+            return Ok(None);
+        };
+        if let Some((cached_id, token)) = &*self.last_po_token.lock().unwrap() {
+            if cached_id == video_id {
+                return Ok(Some(token.clone()));
+            }
         }
+        debug!("Requesting a PO token for {}", video_id);
+        let token = provider.po_token(video_id).await?;
+        *self.last_po_token.lock().unwrap() = Some((video_id.to_string(), token.clone()));
+        Ok(Some(token))
     }
 
     async fn delay(&self) {
+        trace!("Waiting {} ms before the next request", self.delay_ms);
         tokio::time::sleep(tokio::time::Duration::from_millis(self.delay_ms)).await;
     }
 
@@ -272,64 +645,102 @@ impl YouTubeTranscript {
 
     /// Fetch all video IDs from a playlist
     pub async fn get_playlist_video_ids(&self, playlist_id: &str) -> Result<Vec<String>> {
-        use regex::Regex;
-        
-        let url = PLAYLIST_URL.replace("{playlist_id}", playlist_id);
+        let url = self.url(&PLAYLIST_PATH.replace("{playlist_id}", playlist_id));
         
         // Add delay before request
         self.delay().await;
         
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to fetch playlist: {}", e)))?;
+        let response = self.send(HttpRequest::get(url), playlist_id, Stage::Playlist).await?;
 
-        self.check_http_errors(&response, playlist_id)?;
+        let html = response.text();
 
-        let html = response
-            .text()
-            .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to read playlist HTML: {}", e)))?;
+        let video_ids = extract_video_ids_from_html(&html, playlist_id)?;
 
-        // Extract video IDs from the playlist page
-        // YouTube stores video IDs in various places in the HTML
-        // We'll look for the pattern "/watch?v=VIDEO_ID" or "videoId":"VIDEO_ID"
-        let mut video_ids = Vec::new();
-        
-        // Pattern 1: "videoId":"VIDEO_ID"
-        let re1 = Regex::new(r#""videoId":"([a-zA-Z0-9_-]{11})""#)
-            .map_err(|_| TranscriptError::YouTubeDataUnparsable(playlist_id.to_string()))?;
-        
-        for cap in re1.captures_iter(&html) {
-            if let Some(video_id) = cap.get(1) {
-                let id = video_id.as_str().to_string();
-                if !video_ids.contains(&id) {
-                    video_ids.push(id);
-                }
+        if video_ids.is_empty() {
+            return Err(TranscriptError::YouTubeDataUnparsable {
+                id: playlist_id.to_string(),
+                stage: Stage::Playlist,
+                reason: "No videos found in the playlist".to_string(),
+            });
+        }
+
+        Ok(video_ids)
+    }
+
+    /// Extract a channel's base URL from a channel URL or `@handle`
+    ///
+    /// Supports `/@handle`, `/channel/ID`, `/c/NAME` and `/user/NAME` URLs.
+    pub fn extract_channel_url(url_or_handle: &str) -> Result<String> {
+        let input = url_or_handle.trim();
+
+        // Bare handle: @name
+        if let Some(handle) = input.strip_prefix('@') {
+            if !handle.is_empty()
+                && handle
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '.')
+            {
+                return Ok(format!("https://www.youtube.com/@{}", handle));
             }
         }
-        
-        // Pattern 2: /watch?v=VIDEO_ID (as fallback)
-        if video_ids.is_empty() {
-            let re2 = Regex::new(r#"/watch\?v=([a-zA-Z0-9_-]{11})"#)
-                .map_err(|_| TranscriptError::YouTubeDataUnparsable(playlist_id.to_string()))?;
-            
-            for cap in re2.captures_iter(&html) {
-                if let Some(video_id) = cap.get(1) {
-                    let id = video_id.as_str().to_string();
-                    if !video_ids.contains(&id) {
-                        video_ids.push(id);
+
+        // Try parsing as URL (with or without protocol)
+        let url_str = if input.starts_with("http://") || input.starts_with("https://") {
+            input.to_string()
+        } else if input.contains("youtube.com") {
+            format!("https://{}", input)
+        } else {
+            input.to_string()
+        };
+
+        if let Ok(url) = url::Url::parse(&url_str) {
+            if url
+                .host_str()
+                .map(|h| h.contains("youtube.com"))
+                .unwrap_or(false)
+            {
+                let segments: Vec<&str> = url
+                    .path_segments()
+                    .map(|s| s.filter(|s| !s.is_empty()).collect())
+                    .unwrap_or_default();
+
+                match segments.as_slice() {
+                    [handle, ..] if handle.starts_with('@') && handle.len() > 1 => {
+                        return Ok(format!("https://www.youtube.com/{}", handle));
+                    }
+                    [kind @ ("channel" | "c" | "user"), name, ..] => {
+                        return Ok(format!("https://www.youtube.com/{}/{}", kind, name));
                     }
+                    _ => {}
                 }
             }
         }
 
+        Err(TranscriptError::InvalidVideoId(format!(
+            "{} (Could not extract channel from URL)",
+            url_or_handle
+        )))
+    }
+
+    /// Fetch the video IDs listed on a channel's Videos tab (most recent uploads first)
+    pub async fn get_channel_video_ids(&self, channel_url: &str) -> Result<Vec<String>> {
+        let url = format!("{}/videos", channel_url.trim_end_matches('/'));
+
+        // Add delay before request
+        self.delay().await;
+
+        let response = self.send(HttpRequest::get(url), channel_url, Stage::Playlist).await?;
+
+        let html = response.text();
+
+        let video_ids = extract_video_ids_from_html(&html, channel_url)?;
+
         if video_ids.is_empty() {
-            return Err(TranscriptError::YouTubeDataUnparsable(
-                format!("No videos found in playlist: {}", playlist_id)
-            ));
+            return Err(TranscriptError::YouTubeDataUnparsable {
+                id: channel_url.to_string(),
+                stage: Stage::Playlist,
+                reason: "No videos found for the channel".to_string(),
+            });
         }
 
         Ok(video_ids)
@@ -341,8 +752,8 @@ impl YouTubeTranscript {
         // Delay between HTML fetch and API call to avoid rate limiting
         self.delay().await;
         let api_key = self.extract_innertube_api_key(&html, video_id)?;
-        let innertube_data = self.fetch_innertube_data(video_id, &api_key).await?;
-        self.extract_video_title(video_id, &innertube_data)
+        self.with_player_response(video_id, &api_key, |data, _| self.extract_video_title(video_id, data))
+            .await
     }
 
     /// List all available transcripts for a video
@@ -351,8 +762,21 @@ impl YouTubeTranscript {
         // Delay between HTML fetch and API call to avoid rate limiting
         self.delay().await;
         let api_key = self.extract_innertube_api_key(&html, video_id)?;
-        let innertube_data = self.fetch_innertube_data(video_id, &api_key).await?;
-        self.extract_captions_json(video_id, &innertube_data)
+        let mut transcript_list = self
+            .with_player_response(video_id, &api_key, |data, has_fallback| {
+                let transcript_list = self.extract_captions_json(video_id, data)?;
+                // Listing works without a token, so the last client's tracks are
+                // returned even if they cannot be fetched
+                if has_fallback && self.po_token_provider.is_none() && transcript_list.requires_po_token() {
+                    return Err(TranscriptError::PoTokenRequired(video_id.to_string()));
+                }
+                Ok(transcript_list)
+            })
+            .await?;
+        if transcript_list.upload_date.is_none() {
+            transcript_list.upload_date = extract_upload_date_from_html(&html);
+        }
+        Ok(transcript_list)
     }
 
     /// Fetch transcript for a specific language
@@ -364,11 +788,91 @@ impl YouTubeTranscript {
         let transcript_list = self.list_transcripts(video_id).await?;
 
         let languages = languages.unwrap_or_else(|| vec!["en"]);
-        let title = transcript_list.title.clone();
         let transcript_info = transcript_list.find_transcript(&languages)?;
 
-        self.fetch_transcript_data(video_id, transcript_info, None, title)
-            .await
+        self.fetch_transcript_data(&transcript_list, transcript_info).await
+    }
+
+    /// Like [`fetch_transcript`](Self::fetch_transcript), but yields the lines of
+    /// the transcript as they are parsed instead of collecting them first. The
+    /// caption track itself is still downloaded in full before the first line is
+    /// yielded. If the transcript cannot be found or downloaded, that error is the
+    /// only item.
+    pub fn fetch_transcript_stream(
+        &self,
+        video_id: &str,
+        languages: Option<Vec<&str>>,
+    ) -> impl Stream<Item = Result<TranscriptItem>> + '_ {
+        let video_id = video_id.to_string();
+        let languages: Vec<String> = languages
+            .unwrap_or_else(|| vec!["en"])
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        stream::once(async move {
+            let transcript_list = self.list_transcripts(&video_id).await?;
+            let languages: Vec<&str> = languages.iter().map(String::as_str).collect();
+            let transcript_info = transcript_list.find_transcript(&languages)?;
+            let xml = self.fetch_caption_xml(&video_id, transcript_info).await?;
+            Ok((video_id, xml))
+        })
+        .flat_map(|fetched: Result<(String, Vec<u8>)>| match fetched {
+            Ok((video_id, xml)) => {
+                let items = TranscriptParser::new(false).items(Cursor::new(xml));
+                Either::Left(stream::iter(items.map(move |item| {
+                    item.map_err(|message| TranscriptError::XmlParseError {
+                        video_id: video_id.clone(),
+                        message,
+                    })
+                })))
+            }
+            Err(e) => Either::Right(stream::iter([Err(e)])),
+        })
+    }
+
+    /// Fetch the transcripts of a playlist's videos one at a time, as the
+    /// returned stream is polled, so a large batch never holds more than one
+    /// transcript. A video that fails does not end the stream.
+    pub async fn fetch_playlist_stream(
+        &self,
+        playlist_id: &str,
+        languages: &[&str],
+    ) -> Result<impl Stream<Item = (String, Result<TranscriptResponse>)> + '_> {
+        let video_ids = self.get_playlist_video_ids(playlist_id).await?;
+        let languages: Vec<String> = languages.iter().map(|code| code.to_string()).collect();
+
+        Ok(stream::iter(video_ids).then(move |video_id| {
+            let languages = languages.clone();
+            async move {
+                let languages: Vec<&str> = languages.iter().map(String::as_str).collect();
+                let result = self.fetch_transcript(&video_id, Some(languages)).await;
+                (video_id, result)
+            }
+        }))
+    }
+
+    /// Fetch the track `policy` picks for `languages`, see [`TranscriptList::find_with_policy`]
+    pub async fn fetch_transcript_with_policy(
+        &self,
+        video_id: &str,
+        languages: &[&str],
+        policy: &LanguagePolicy,
+    ) -> Result<TranscriptResponse> {
+        let transcript_list = self.list_transcripts(video_id).await?;
+        let transcript_info = transcript_list.find_with_policy(languages, policy)?;
+        transcript_list.fetch(self, &transcript_info).await
+    }
+
+    /// Fetch every track of a video that `filter` selects, listing the video
+    /// only once. Only the listing fails as a whole; see [`TranscriptList::fetch_all`].
+    pub async fn fetch_all_transcripts(
+        &self,
+        video_id: &str,
+        filter: &TranscriptFilter,
+    ) -> Result<Vec<Result<TranscriptResponse>>> {
+        let transcript_list = self.list_transcripts(video_id).await?;
+        Ok(transcript_list.fetch_all(self, filter).await)
     }
 
     /// Translate a transcript to another language
@@ -379,46 +883,53 @@ impl YouTubeTranscript {
         target_language: &str,
     ) -> Result<TranscriptResponse> {
         let transcript_list = self.list_transcripts(video_id).await?;
-        let title = transcript_list.title.clone();
         let source_transcript = transcript_list.find_transcript(source_languages)?;
 
-        if !source_transcript.is_translatable {
-            return Err(TranscriptError::NotTranslatable(video_id.to_string()));
-        }
+        check_translation(video_id, source_transcript, target_language)?;
 
-        let translation_exists = source_transcript
-            .translation_languages
-            .iter()
-            .any(|t| t.language_code == target_language);
+        transcript_list
+            .fetch(self, &source_transcript.translate(target_language)?)
+            .await
+    }
 
-        if !translation_exists {
-            return Err(TranscriptError::TranslationLanguageNotAvailable(
-                target_language.to_string(),
-            ));
-        }
+    /// Translate a transcript, falling back to ChatGPT when YouTube cannot
+    /// translate the source track into `target_language`.
+    ///
+    /// Check `translation_origin` on the result to see which one was used.
+    pub async fn translate_transcript_with_fallback(
+        &self,
+        video_id: &str,
+        source_languages: &[&str],
+        target_language: &str,
+        chatgpt: &ChatGPT,
+    ) -> Result<TranscriptResponse> {
+        let transcript_list = self.list_transcripts(video_id).await?;
+        let source_transcript = transcript_list.find_transcript(source_languages)?;
 
-        self.fetch_transcript_data(video_id, source_transcript, Some(target_language), title)
-            .await
+        match check_translation(video_id, source_transcript, target_language) {
+            Ok(()) => {
+                transcript_list
+                    .fetch(self, &source_transcript.translate(target_language)?)
+                    .await
+            }
+            Err(TranscriptError::NotTranslatable(_))
+            | Err(TranscriptError::TranslationLanguageNotAvailable(_)) => {
+                let source = transcript_list.fetch(self, source_transcript).await?;
+                chatgpt.translate_transcript(&source, target_language).await
+            }
+            Err(e) => Err(e),
+        }
     }
 
     async fn fetch_video_html(&self, video_id: &str) -> Result<String> {
         // Add initial delay to avoid rate limiting
         self.delay().await;
 
-        let url = WATCH_URL.replace("{video_id}", video_id);
-        let mut response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to fetch HTML: {}", e)))?;
-
-        self.check_http_errors(&response, video_id)?;
+        let url = self.url(&WATCH_PATH.replace("{video_id}", video_id));
+        debug!("Fetching watch page {}", url);
+        let response = self.send(HttpRequest::get(&url), video_id, Stage::Html).await?;
 
-        let html = response
-            .text()
-            .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to read HTML: {}", e)))?;
+        let html = response.text();
 
         // Handle consent cookie if needed
         if html.contains("action=\"https://consent.youtube.com/s\"") {
@@ -426,16 +937,9 @@ impl YouTubeTranscript {
             // Add delay before retry
             self.delay().await;
             // Retry request
-            response = self.client.get(&url).send().await.map_err(|e| {
-                TranscriptError::HttpError(format!("Failed to fetch HTML after consent: {}", e))
-            })?;
+            let response = self.send(HttpRequest::get(&url), video_id, Stage::Html).await?;
 
-            self.check_http_errors(&response, video_id)?;
-
-            let html = response
-                .text()
-                .await
-                .map_err(|e| TranscriptError::HttpError(format!("Failed to read HTML: {}", e)))?;
+            let html = response.text();
 
             if html.contains("action=\"https://consent.youtube.com/s\"") {
                 return Err(TranscriptError::FailedToCreateConsentCookie(
@@ -457,8 +961,12 @@ impl YouTubeTranscript {
             return Err(TranscriptError::IpBlocked(video_id.to_string()));
         }
 
-        let re = Regex::new(r#""INNERTUBE_API_KEY":\s*"([a-zA-Z0-9_-]+)""#)
-            .map_err(|_| TranscriptError::YouTubeDataUnparsable(video_id.to_string()))?;
+        let unparsable = || TranscriptError::YouTubeDataUnparsable {
+            id: video_id.to_string(),
+            stage: Stage::Html,
+            reason: "No InnerTube API key on the watch page".to_string(),
+        };
+        let re = Regex::new(r#""INNERTUBE_API_KEY":\s*"([a-zA-Z0-9_-]+)""#).map_err(|_| unparsable())?;
 
         if let Some(captures) = re.captures(html) {
             if let Some(api_key) = captures.get(1) {
@@ -466,43 +974,90 @@ impl YouTubeTranscript {
             }
         }
 
-        Err(TranscriptError::YouTubeDataUnparsable(video_id.to_string()))
+        Err(unparsable())
+    }
+
+    /// Fetch the player response with each client in turn until `extract`
+    /// accepts one. `extract` also gets whether another client is left to try.
+    /// A player request answered with an error status also moves on to the
+    /// next client.
+    async fn with_player_response<T>(
+        &self,
+        video_id: &str,
+        api_key: &str,
+        extract: impl Fn(&serde_json::Value, bool) -> Result<T>,
+    ) -> Result<T> {
+        let (last, fallbacks) = self.clients.split_last().expect("at least one client");
+        for (client, next) in fallbacks.iter().zip(self.clients.iter().skip(1)) {
+            let innertube_data = match self.fetch_innertube_data(video_id, api_key, client).await {
+                Ok(innertube_data) => innertube_data,
+                Err(e) if e.status().is_some() => {
+                    debug!("{} with the {} client, trying {}", e, client, next);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            match extract(&innertube_data, true) {
+                Err(e) if should_fall_back(&e) => {
+                    debug!("{} with the {} client, trying {}", e, client, next);
+                }
+                result => return result,
+            }
+        }
+        let innertube_data = self.fetch_innertube_data(video_id, api_key, last).await?;
+        extract(&innertube_data, false)
     }
 
     async fn fetch_innertube_data(
         &self,
         video_id: &str,
         api_key: &str,
+        client: &ClientProfile,
+    ) -> Result<serde_json::Value> {
+        let data = self.request_player(video_id, api_key, client, None).await?;
+        if self.po_token_provider.is_none() || !asks_for_po_token(&data) {
+            return Ok(data);
+        }
+
+        // Ask again with the token, so the tracks belong to its visitor session
+        let po_token = self.po_token(video_id).await?;
+        debug!("Fetching player response for {} again with a PO token", video_id);
+        self.request_player(video_id, api_key, client, po_token.as_ref()).await
+    }
+
+    async fn request_player(
+        &self,
+        video_id: &str,
+        api_key: &str,
+        client: &ClientProfile,
+        po_token: Option<&PoToken>,
     ) -> Result<serde_json::Value> {
-        let url = INNERTUBE_API_URL.replace("{api_key}", api_key);
+        let url = self.url(&INNERTUBE_API_PATH.replace("{api_key}", api_key));
+        debug!("Fetching player response for {} as {}", video_id, client);
+
+        let mut body = client.player_request(video_id);
+        if let Some(po_token) = po_token {
+            body["serviceIntegrityDimensions"] = serde_json::json!({ "poToken": po_token.token });
+            if let Some(visitor_data) = &po_token.visitor_data {
+                body["context"]["client"]["visitorData"] = visitor_data.as_str().into();
+            }
+        }
 
-        let context = serde_json::json!({
-            "context": {
-                "client": {
-                    "clientName": "ANDROID",
-                    "clientVersion": "20.10.38"
-                }
-            },
-            "videoId": video_id
-        });
+        let mut request = HttpRequest::post_json(url, &body);
+        for (name, value) in client.headers() {
+            request = request.header(name, value);
+        }
+        if let Some(visitor_data) = po_token.and_then(|po_token| po_token.visitor_data.as_ref()) {
+            request = request.header("X-Goog-Visitor-Id", visitor_data.clone());
+        }
 
         // Add delay before API request to avoid rate limiting
         self.delay().await;
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&context)
-            .send()
-            .await
-            .map_err(|e| {
-                TranscriptError::HttpError(format!("Failed to fetch InnerTube data: {}", e))
-            })?;
-
-        self.check_http_errors(&response, video_id)?;
+        let response = self.send(request, video_id, Stage::Innertube).await?;
 
-        let data: serde_json::Value = response.json().await.map_err(|e| {
-            TranscriptError::JsonParseError(format!("Failed to parse InnerTube response: {}", e))
+        let data: serde_json::Value = serde_json::from_slice(&response.body).map_err(|e| {
+            TranscriptError::json(Some(Stage::Innertube), "Failed to parse InnerTube response", e)
         })?;
 
         Ok(data)
@@ -516,14 +1071,15 @@ impl YouTubeTranscript {
         // Check playability status
         self.assert_playability(video_id, innertube_data)?;
 
-        let video_details = innertube_data
+        let title = innertube_data
             .get("videoDetails")
-            .ok_or_else(|| TranscriptError::YouTubeDataUnparsable(video_id.to_string()))?;
-
-        let title = video_details
-            .get("title")
+            .and_then(|details| details.get("title"))
             .and_then(|t| t.as_str())
-            .ok_or_else(|| TranscriptError::YouTubeDataUnparsable(video_id.to_string()))?;
+            .ok_or_else(|| TranscriptError::YouTubeDataUnparsable {
+                id: video_id.to_string(),
+                stage: Stage::Innertube,
+                reason: "No video title in the player response".to_string(),
+            })?;
 
         Ok(title.to_string())
     }
@@ -585,6 +1141,7 @@ impl YouTubeTranscript {
                 };
 
                 let base_url = match caption.get("baseUrl").and_then(|u| u.as_str()) {
+                    Some(url) if url.starts_with('/') => self.url(&url.replace("&fmt=srv3", "")),
                     Some(url) => url.replace("&fmt=srv3", ""),
                     None => continue,
                 };
@@ -623,6 +1180,7 @@ impl YouTubeTranscript {
                     is_translatable,
                     base_url,
                     translation_languages: transcript_translation_languages,
+                    translated_from: None,
                 };
 
                 if is_generated {
@@ -637,16 +1195,28 @@ impl YouTubeTranscript {
             return Err(TranscriptError::TranscriptsDisabled(video_id.to_string()));
         }
 
-        // Extract video title
-        let title = innertube_data
-            .get("videoDetails")
+        // Extract video metadata
+        let video_details = innertube_data.get("videoDetails");
+        let title = video_details
             .and_then(|vd| vd.get("title"))
             .and_then(|t| t.as_str())
             .map(|s| s.to_string());
+        let channel = video_details
+            .and_then(|vd| vd.get("author"))
+            .and_then(|a| a.as_str())
+            .map(|s| s.to_string());
+        let upload_date = innertube_data
+            .get("microformat")
+            .and_then(|m| m.get("playerMicroformatRenderer"))
+            .and_then(|r| r.get("uploadDate").or_else(|| r.get("publishDate")))
+            .and_then(|d| d.as_str())
+            .and_then(normalize_date);
 
         Ok(TranscriptList {
             video_id: video_id.to_string(),
             title,
+            channel,
+            upload_date,
             manually_created,
             generated,
             translation_languages,
@@ -682,13 +1252,11 @@ impl YouTubeTranscript {
                     return Err(TranscriptError::AgeRestricted(video_id.to_string()));
                 }
             }
-            "ERROR" => {
-                if reason.contains("unavailable") {
-                    if video_id.starts_with("http://") || video_id.starts_with("https://") {
-                        return Err(TranscriptError::InvalidVideoId(video_id.to_string()));
-                    }
-                    return Err(TranscriptError::VideoUnavailable(video_id.to_string()));
+            "ERROR" if reason.contains("unavailable") => {
+                if video_id.starts_with("http://") || video_id.starts_with("https://") {
+                    return Err(TranscriptError::InvalidVideoId(video_id.to_string()));
                 }
+                return Err(TranscriptError::VideoUnavailable(video_id.to_string()));
             }
             _ => {}
         }
@@ -706,10 +1274,8 @@ impl YouTubeTranscript {
 
         if let Some(captures) = re.captures(html) {
             if let Some(value) = captures.get(1) {
-                let _cookie_value = format!("YES+{}", value.as_str());
-                // Note: reqwest handles cookies automatically via cookie_store
-                // We would need to manually set cookies if needed, but for now
-                // the retry after detecting consent should work
+                self.transport
+                    .add_cookie(&self.base_url, &format!("CONSENT=YES+{}; Path=/", value.as_str()));
                 return Ok(());
             }
         }
@@ -719,83 +1285,187 @@ impl YouTubeTranscript {
         ))
     }
 
-    fn check_http_errors(&self, response: &reqwest::Response, video_id: &str) -> Result<()> {
-        if response.status() == 429 {
-            return Err(TranscriptError::IpBlocked(video_id.to_string()));
-        }
-        if !response.status().is_success() {
-            return Err(TranscriptError::HttpError(format!(
-                "HTTP {}: {}",
-                response.status(),
-                response
-                    .status()
-                    .canonical_reason()
-                    .unwrap_or("Unknown error")
-            )));
-        }
-        Ok(())
-    }
-
     async fn fetch_transcript_data(
         &self,
-        video_id: &str,
+        transcript_list: &TranscriptList,
         transcript_info: &TranscriptInfo,
-        translate_to: Option<&str>,
-        title: Option<String>,
     ) -> Result<TranscriptResponse> {
+        let video_id = transcript_list.video_id.as_str();
+        let xml = self.fetch_caption_xml(video_id, transcript_info).await?;
+
+        let parser = TranscriptParser::new(false);
+        let transcript_items = parser
+            .parse(&String::from_utf8_lossy(&xml))
+            .map_err(|message| TranscriptError::XmlParseError {
+                video_id: video_id.to_string(),
+                message,
+            })?;
+
+        Ok(TranscriptResponse {
+            video_id: video_id.to_string(),
+            title: transcript_list.title.clone(),
+            channel: transcript_list.channel.clone(),
+            upload_date: transcript_list.upload_date.clone(),
+            language: transcript_info.language.clone(),
+            language_code: transcript_info.language_code.clone(),
+            is_generated: transcript_info.is_generated,
+            is_translatable: transcript_info.is_translatable,
+            translation_origin: transcript_info
+                .translated_from
+                .as_ref()
+                .map(|_| TranslationOrigin::YouTube),
+            transcript: transcript_items,
+        })
+    }
+
+    /// Download a caption track of `video_id`, unparsed
+    async fn fetch_caption_xml(&self, video_id: &str, transcript_info: &TranscriptInfo) -> Result<Vec<u8>> {
         let mut url = transcript_info.base_url.clone();
 
-        if let Some(target_lang) = translate_to {
-            url = format!("{}&tlang={}", url, target_lang);
+        match &transcript_info.translated_from {
+            Some(source) => debug!(
+                "Fetching {} transcript for {} translated to {}",
+                source, video_id, transcript_info.language_code
+            ),
+            None => debug!("Fetching {} transcript for {}", transcript_info.language_code, video_id),
         }
 
-        // Check for protected video token requirement
-        if url.contains("&exp=xpe") {
-            return Err(TranscriptError::PoTokenRequired(video_id.to_string()));
+        // Protected tracks are only served with a PO token
+        if transcript_info.requires_po_token() {
+            let po_token = self
+                .po_token(video_id)
+                .await?
+                .ok_or_else(|| TranscriptError::PoTokenRequired(video_id.to_string()))?;
+            url.push_str("&pot=");
+            url.extend(url::form_urlencoded::byte_serialize(po_token.token.as_bytes()));
         }
 
         // Add delay before fetching transcript to avoid rate limiting
         self.delay().await;
 
-        let response = self.client.get(&url).send().await.map_err(|e| {
-            TranscriptError::HttpError(format!("Failed to fetch transcript: {}", e))
-        })?;
+        let response = self
+            .send(HttpRequest::get(url), video_id, Stage::Timedtext)
+            .await?;
 
-        self.check_http_errors(&response, video_id)?;
+        Ok(response.body)
+    }
+}
 
-        let xml_content = response
-            .text()
-            .await
-            .map_err(|e| TranscriptError::HttpError(format!("Failed to read transcript: {}", e)))?;
+/// Whether a player response would be worth asking for again with a PO token:
+/// a caption track is protected or YouTube wants proof the client is no bot
+fn asks_for_po_token(innertube_data: &serde_json::Value) -> bool {
+    let bot_check = innertube_data["playabilityStatus"]["reason"]
+        .as_str()
+        .is_some_and(|reason| reason.contains("Sign in to confirm you're not a bot"));
+    let protected_track = innertube_data["captions"]["playerCaptionsTracklistRenderer"]["captionTracks"]
+        .as_array()
+        .is_some_and(|tracks| {
+            tracks
+                .iter()
+                .filter_map(|track| track["baseUrl"].as_str())
+                .any(|url| url.contains("&exp=xpe"))
+        });
+    bot_check || protected_track
+}
 
-        let parser = TranscriptParser::new(false);
-        let transcript_items = parser
-            .parse(&xml_content)
-            .map_err(|e| TranscriptError::XmlParseError(format!("Failed to parse XML: {}", e)))?;
+/// Find the upload date in a watch page, for player responses without a microformat
+fn extract_upload_date_from_html(html: &str) -> Option<String> {
+    use regex::Regex;
 
-        let language = if let Some(target_lang) = translate_to {
-            transcript_info
-                .translation_languages
-                .iter()
-                .find(|t| t.language_code == target_lang)
-                .map(|t| t.language.clone())
-                .unwrap_or_else(|| transcript_info.language.clone())
-        } else {
-            transcript_info.language.clone()
-        };
+    let re = Regex::new(r#""(?:uploadDate|publishDate)":"([^"]+)""#).ok()?;
+    re.captures(html).and_then(|cap| normalize_date(&cap[1]))
+}
 
-        Ok(TranscriptResponse {
-            video_id: video_id.to_string(),
-            title,
-            language,
-            language_code: translate_to
-                .unwrap_or(&transcript_info.language_code)
-                .to_string(),
-            is_generated: transcript_info.is_generated || translate_to.is_some(),
-            is_translatable: transcript_info.is_translatable,
-            transcript: transcript_items,
-        })
+/// Reduce an ISO 8601 date or timestamp (`2009-10-24T23:57:33-07:00`) to `YYYY-MM-DD`
+fn normalize_date(date: &str) -> Option<String> {
+    let day = date.get(..10)?;
+    let valid = day.char_indices().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        _ => c.is_ascii_digit(),
+    });
+    valid.then(|| day.to_string())
+}
+
+/// Extract video IDs from a playlist or channel page, in page order
+fn extract_video_ids_from_html(html: &str, source: &str) -> Result<Vec<String>> {
+    use regex::Regex;
+
+    // YouTube stores video IDs in various places in the HTML
+    // We'll look for the pattern "/watch?v=VIDEO_ID" or "videoId":"VIDEO_ID"
+    let mut video_ids = Vec::new();
+    let unparsable = || TranscriptError::YouTubeDataUnparsable {
+        id: source.to_string(),
+        stage: Stage::Playlist,
+        reason: "Invalid video ID pattern".to_string(),
+    };
+
+    // Pattern 1: "videoId":"VIDEO_ID"
+    let re1 = Regex::new(r#""videoId":"([a-zA-Z0-9_-]{11})""#)
+        .map_err(|_| unparsable())?;
+
+    for cap in re1.captures_iter(html) {
+        if let Some(video_id) = cap.get(1) {
+            let id = video_id.as_str().to_string();
+            if !video_ids.contains(&id) {
+                video_ids.push(id);
+            }
+        }
+    }
+
+    // Pattern 2: /watch?v=VIDEO_ID (as fallback)
+    if video_ids.is_empty() {
+        let re2 = Regex::new(r#"/watch\?v=([a-zA-Z0-9_-]{11})"#)
+            .map_err(|_| unparsable())?;
+
+        for cap in re2.captures_iter(html) {
+            if let Some(video_id) = cap.get(1) {
+                let id = video_id.as_str().to_string();
+                if !video_ids.contains(&id) {
+                    video_ids.push(id);
+                }
+            }
+        }
+    }
+
+    Ok(video_ids)
+}
+
+/// The track of `tracks` that best matches `language_code`: an exact match,
+/// else the regional variant with the lowest code if `match_region` is set
+fn best_match<'a>(
+    tracks: &'a HashMap<String, TranscriptInfo>,
+    language_code: &str,
+    match_region: bool,
+) -> Option<&'a TranscriptInfo> {
+    tracks
+        .values()
+        .filter_map(|info| Some((match_rank(language_code, &info.language_code, match_region)?, info)))
+        .min_by(|(rank_a, a), (rank_b, b)| rank_a.cmp(rank_b).then_with(|| a.language_code.cmp(&b.language_code)))
+        .map(|(_, info)| info)
+}
+
+/// Check that YouTube can translate `transcript_info` into `target_language`.
+fn check_translation(
+    video_id: &str,
+    transcript_info: &TranscriptInfo,
+    target_language: &str,
+) -> Result<()> {
+    if !transcript_info.is_translatable {
+        return Err(TranscriptError::NotTranslatable(video_id.to_string()));
+    }
+
+    let translation_exists = transcript_info
+        .translation_languages
+        .iter()
+        .any(|t| t.language_code == target_language);
+
+    if !translation_exists {
+        return Err(TranscriptError::TranslationLanguageNotAvailable(
+            target_language.to_string(),
+        ));
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -841,6 +1511,48 @@ mod tests {
         assert!(YouTubeTranscript::extract_video_id("https://example.com").is_err());
     }
 
+    #[test]
+    fn test_extract_channel_url() {
+        assert_eq!(
+            YouTubeTranscript::extract_channel_url("@rustlang").unwrap(),
+            "https://www.youtube.com/@rustlang"
+        );
+        assert_eq!(
+            YouTubeTranscript::extract_channel_url("https://www.youtube.com/@rustlang/videos").unwrap(),
+            "https://www.youtube.com/@rustlang"
+        );
+        assert_eq!(
+            YouTubeTranscript::extract_channel_url("youtube.com/channel/UC_abc123").unwrap(),
+            "https://www.youtube.com/channel/UC_abc123"
+        );
+        assert_eq!(
+            YouTubeTranscript::extract_channel_url("https://www.youtube.com/user/someone/").unwrap(),
+            "https://www.youtube.com/user/someone"
+        );
+        assert!(YouTubeTranscript::extract_channel_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ").is_err());
+        assert!(YouTubeTranscript::extract_channel_url("https://example.com/@someone").is_err());
+    }
+
+    #[test]
+    fn test_extract_video_ids_from_html() {
+        let html = r#"{"videoId":"dQw4w9WgXcQ"},{"videoId":"_NuH3D4SN-c"},{"videoId":"dQw4w9WgXcQ"}"#;
+        assert_eq!(
+            extract_video_ids_from_html(html, "test").unwrap(),
+            vec!["dQw4w9WgXcQ", "_NuH3D4SN-c"]
+        );
+
+        let html = r#"<a href="/watch?v=dQw4w9WgXcQ&list=PL1">"#;
+        assert_eq!(extract_video_ids_from_html(html, "test").unwrap(), vec!["dQw4w9WgXcQ"]);
+    }
+
+    #[test]
+    fn test_extract_upload_date_from_html() {
+        let html = r#"{"publishDate":"2009-10-24T23:57:33-07:00","uploadDate":"2009-10-24"}"#;
+        assert_eq!(extract_upload_date_from_html(html).as_deref(), Some("2009-10-24"));
+        assert_eq!(extract_upload_date_from_html("<html></html>"), None);
+        assert_eq!(normalize_date("Oct 24, 2009"), None);
+    }
+
     #[test]
     fn test_transcript_list_find_transcript() {
         let mut manually_created = HashMap::new();
@@ -855,6 +1567,7 @@ mod tests {
                 is_translatable: true,
                 base_url: "https://example.com/en".to_string(),
                 translation_languages: vec![],
+                translated_from: None,
             },
         );
 
@@ -867,12 +1580,15 @@ mod tests {
                 is_translatable: false,
                 base_url: "https://example.com/es".to_string(),
                 translation_languages: vec![],
+                translated_from: None,
             },
         );
 
         let list = TranscriptList {
             video_id: "test".to_string(),
             title: None,
+            channel: None,
+            upload_date: None,
             manually_created,
             generated,
             translation_languages: vec![],
@@ -900,12 +1616,15 @@ mod tests {
                 is_translatable: true,
                 base_url: "https://example.com/en".to_string(),
                 translation_languages: vec![],
+                translated_from: None,
             },
         );
 
         let list = TranscriptList {
             video_id: "test".to_string(),
             title: None,
+            channel: None,
+            upload_date: None,
             manually_created,
             generated: HashMap::new(),
             translation_languages: vec![],
@@ -930,12 +1649,15 @@ mod tests {
                 is_translatable: false,
                 base_url: "https://example.com/es".to_string(),
                 translation_languages: vec![],
+                translated_from: None,
             },
         );
 
         let list = TranscriptList {
             video_id: "test".to_string(),
             title: None,
+            channel: None,
+            upload_date: None,
             manually_created: HashMap::new(),
             generated,
             translation_languages: vec![],
@@ -945,6 +1667,406 @@ mod tests {
         assert!(list.find_generated(&["en"]).is_err());
     }
 
+    fn info(language_code: &str, is_generated: bool) -> TranscriptInfo {
+        TranscriptInfo {
+            language_code: language_code.to_string(),
+            language: language_code.to_uppercase(),
+            is_generated,
+            is_translatable: !is_generated,
+            base_url: format!("https://example.com/{}", language_code),
+            translation_languages: vec![],
+            translated_from: None,
+        }
+    }
+
+    #[test]
+    fn test_transcript_list_serializes_sorted() {
+        let list = TranscriptList {
+            video_id: "test".to_string(),
+            title: Some("Title".to_string()),
+            channel: None,
+            upload_date: None,
+            manually_created: ["fr", "de"]
+                .into_iter()
+                .map(|code| (code.to_string(), info(code, false)))
+                .collect(),
+            generated: [("en".to_string(), info("en", true))].into_iter().collect(),
+            translation_languages: ["es", "af"]
+                .into_iter()
+                .map(|code| TranslationLanguage {
+                    language: code.to_uppercase(),
+                    language_code: code.to_string(),
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_value(&list).unwrap();
+        let codes: Vec<&str> = json["transcripts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["language_code"].as_str().unwrap())
+            .collect();
+        assert_eq!(codes, vec!["de", "fr", "en"]);
+        assert_eq!(json["transcripts"][2]["kind"], "generated");
+        assert_eq!(json["transcripts"][0]["has_base_url"], true);
+        assert!(json["transcripts"][0].get("base_url").is_none());
+        assert_eq!(json["translation_languages"][0]["language_code"], "af");
+        assert!(json.get("channel").is_none());
+    }
+
+    #[test]
+    fn test_requires_po_token() {
+        let mut list = TranscriptList {
+            video_id: "test".to_string(),
+            title: None,
+            channel: None,
+            upload_date: None,
+            manually_created: HashMap::new(),
+            generated: HashMap::new(),
+            translation_languages: vec![],
+        };
+        assert!(!list.requires_po_token());
+
+        let mut en = info("en", true);
+        en.base_url.push_str("?v=test&exp=xpe");
+        list.generated.insert("en".to_string(), en);
+        assert!(list.requires_po_token());
+
+        list.manually_created.insert("fr".to_string(), info("fr", false));
+        assert!(!list.requires_po_token());
+    }
+
+    #[test]
+    fn test_check_translation() {
+        let mut info = TranscriptInfo {
+            language_code: "en".to_string(),
+            language: "English".to_string(),
+            is_generated: false,
+            is_translatable: false,
+            base_url: "https://example.com/en".to_string(),
+            translation_languages: vec![TranslationLanguage {
+                language: "German".to_string(),
+                language_code: "de".to_string(),
+            }],
+            translated_from: None,
+        };
+
+        assert!(matches!(
+            check_translation("test", &info, "de"),
+            Err(TranscriptError::NotTranslatable(_))
+        ));
+
+        info.is_translatable = true;
+        assert!(check_translation("test", &info, "de").is_ok());
+        assert!(matches!(
+            check_translation("test", &info, "fr"),
+            Err(TranscriptError::TranslationLanguageNotAvailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_transcript_list_select() {
+        let mut english = info("en", false);
+        english.translation_languages = ["de", "en", "fr"]
+            .into_iter()
+            .map(|code| TranslationLanguage {
+                language: code.to_uppercase(),
+                language_code: code.to_string(),
+            })
+            .collect();
+        let list = TranscriptList {
+            video_id: "test".to_string(),
+            title: None,
+            channel: None,
+            upload_date: None,
+            manually_created: [("en".to_string(), english)].into_iter().collect(),
+            generated: [("de".to_string(), info("de", true)), ("en".to_string(), info("en", true))]
+                .into_iter()
+                .collect(),
+            translation_languages: Vec::new(),
+        };
+        let codes = |filter: &TranscriptFilter| -> Vec<(String, bool)> {
+            list.select(filter)
+                .into_iter()
+                .map(|info| info.unwrap())
+                .map(|info| (info.language_code, info.translated_from.is_some()))
+                .collect()
+        };
+        let code = |code: &str, translated: bool| (code.to_string(), translated);
+
+        assert_eq!(
+            codes(&TranscriptFilter::default()),
+            [code("en", false), code("de", false), code("en", false)]
+        );
+
+        let filter = TranscriptFilter {
+            generated: false,
+            translations: vec!["en".to_string(), "de".to_string(), "fr".to_string()],
+            ..TranscriptFilter::default()
+        };
+        assert_eq!(codes(&filter), [code("en", false), code("de", true), code("fr", true)]);
+
+        // An unavailable translation fails on its own
+        let filter = TranscriptFilter {
+            translations: vec!["xx".to_string(), "fr".to_string()],
+            ..TranscriptFilter::default()
+        };
+        let selected = list.select(&filter);
+        assert_eq!(selected.len(), 5);
+        assert!(matches!(
+            &selected[3],
+            Err(TranscriptError::TranslationLanguageNotAvailable(_))
+        ));
+        assert_eq!(selected[4].as_ref().unwrap().language_code, "fr");
+    }
+
+    #[test]
+    fn test_transcript_list_find_with_policy() {
+        let mut german = info("de", false);
+        german.translation_languages = vec![TranslationLanguage {
+            language: "French".to_string(),
+            language_code: "fr".to_string(),
+        }];
+        let list = TranscriptList {
+            video_id: "test".to_string(),
+            title: None,
+            channel: None,
+            upload_date: None,
+            manually_created: [("de".to_string(), german), ("en-GB".to_string(), info("en-GB", false))]
+                .into_iter()
+                .collect(),
+            generated: [("en".to_string(), info("en", true)), ("es".to_string(), info("es", true))]
+                .into_iter()
+                .collect(),
+            translation_languages: Vec::new(),
+        };
+        let find = |codes: &[&str], policy: &LanguagePolicy| {
+            list.find_with_policy(codes, policy)
+                .ok()
+                .map(|info| (info.language_code, info.is_generated))
+        };
+        let found = |code: &str, is_generated: bool| Some((code.to_string(), is_generated));
+
+        let default = LanguagePolicy::default();
+        assert_eq!(find(&["EN", "de"], &default), found("en", true));
+        assert!(find(&["en-US"], &default).is_none());
+
+        let regional = LanguagePolicy {
+            match_region: true,
+            ..LanguagePolicy::default()
+        };
+        assert_eq!(find(&["en"], &regional), found("en-GB", false));
+        assert_eq!(find(&["en-US"], &regional), found("en-GB", false));
+
+        let manual_first = LanguagePolicy {
+            preference: TrackPreference::ManualFirst,
+            ..LanguagePolicy::default()
+        };
+        assert_eq!(find(&["es", "de"], &manual_first), found("de", false));
+        assert_eq!(find(&["es", "fr"], &manual_first), found("es", true));
+
+        let generated = LanguagePolicy {
+            preference: TrackPreference::GeneratedPerLanguage,
+            match_region: true,
+            ..LanguagePolicy::default()
+        };
+        assert_eq!(find(&["en"], &generated), found("en", true));
+
+        let translate = LanguagePolicy {
+            translate_fallback: true,
+            ..LanguagePolicy::default()
+        };
+        let translation = list.find_with_policy(&["it", "fr"], &translate).unwrap();
+        assert_eq!(translation.language_code, "fr");
+        assert_eq!(translation.translated_from.as_deref(), Some("de"));
+        assert!(matches!(
+            list.find_with_policy(&["it"], &translate),
+            Err(TranscriptError::NoTranscriptFound(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_transcript_info_translate() {
+        let mut source = info("en", false);
+        source.translation_languages = vec![TranslationLanguage {
+            language: "German".to_string(),
+            language_code: "de".to_string(),
+        }];
+
+        let translation = source.translate("de").unwrap();
+        assert_eq!(translation.language, "German");
+        assert_eq!(translation.language_code, "de");
+        assert_eq!(translation.base_url, "https://example.com/en&tlang=de");
+        assert_eq!(translation.translated_from.as_deref(), Some("en"));
+        assert!(translation.is_generated);
+        assert!(matches!(
+            translation.translate("en"),
+            Err(TranscriptError::NotTranslatable(_))
+        ));
+        assert!(matches!(
+            source.translate("fr"),
+            Err(TranscriptError::TranslationLanguageNotAvailable(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_transcript_with_replay_transport() {
+        use transport::{Method, RecordedExchange, ReplayTransport};
+
+        let exchange = |method, url: &str, body: String| RecordedExchange {
+            method,
+            url: url.to_string(),
+            status: 200,
+            body: Some(body),
+            body_file: None,
+        };
+        let player = serde_json::json!({
+            "playabilityStatus": { "status": "OK" },
+            "videoDetails": { "title": "Replayed", "author": "Channel" },
+            "captions": { "playerCaptionsTracklistRenderer": { "captionTracks": [{
+                "languageCode": "en",
+                "baseUrl": "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en",
+                "name": { "runs": [{ "text": "English" }] },
+                "isTranslatable": false
+            }]}}
+        });
+        let transport = Arc::new(ReplayTransport::new(vec![
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                r#"<script>{"INNERTUBE_API_KEY": "test-key"}</script>"#.to_string(),
+            ),
+            exchange(
+                Method::Post,
+                "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                player.to_string(),
+            ),
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en",
+                r#"<transcript><text start="0.5" dur="1.5">Hello</text></transcript>"#.to_string(),
+            ),
+        ]));
+        let api = YouTubeTranscript::with_transport(transport.clone(), 0);
+
+        let transcript = api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+        assert_eq!(transcript.title.as_deref(), Some("Replayed"));
+        assert_eq!(transcript.channel.as_deref(), Some("Channel"));
+        assert_eq!(transcript.transcript.len(), 1);
+        assert_eq!(transcript.transcript[0].text, "Hello");
+        assert_eq!(transport.requests().len(), 3);
+
+        // Not recorded, so it fails like a network error would
+        assert!(matches!(
+            api.fetch_transcript("xxxxxxxxxxx", None).await,
+            Err(TranscriptError::HttpError { video_id: Some(id), .. }) if id == "xxxxxxxxxxx"
+        ));
+
+        // Without protected tracks, the provider isn't asked for a token
+        let api = YouTubeTranscript::with_transport(transport.clone(), 0)
+            .with_po_token_provider(potoken::PoToken::new("tok").with_visitor_data("Cgt4"));
+        let before = transport.requests().len();
+        api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+        let requests = transport.requests();
+        assert_eq!(requests.len() - before, 3);
+        let body: serde_json::Value = serde_json::from_slice(requests[before + 1].body.as_ref().unwrap()).unwrap();
+        assert!(body.get("serviceIntegrityDimensions").is_none());
+
+        // A protected track makes the player request go out again with the token
+        let mut protected = player.clone();
+        protected["captions"]["playerCaptionsTracklistRenderer"]["captionTracks"][0]["baseUrl"] =
+            "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en&exp=xpe".into();
+        let transport = Arc::new(ReplayTransport::new(vec![
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                r#"<script>{"INNERTUBE_API_KEY": "test-key"}</script>"#.to_string(),
+            ),
+            exchange(
+                Method::Post,
+                "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                protected.to_string(),
+            ),
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en&exp=xpe&pot=tok",
+                r#"<transcript><text start="0.5" dur="1.5">Hello</text></transcript>"#.to_string(),
+            ),
+        ]));
+        let api = YouTubeTranscript::with_transport(transport.clone(), 0)
+            .with_po_token_provider(potoken::PoToken::new("tok").with_visitor_data("Cgt4"));
+        api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+        let player_requests: Vec<_> = transport
+            .requests()
+            .into_iter()
+            .filter(|request| request.url.contains("/youtubei/v1/player"))
+            .collect();
+        assert_eq!(player_requests.len(), 2);
+        let body: serde_json::Value = serde_json::from_slice(player_requests[1].body.as_ref().unwrap()).unwrap();
+        assert_eq!(body["serviceIntegrityDimensions"]["poToken"], "tok");
+        assert_eq!(body["context"]["client"]["visitorData"], "Cgt4");
+        assert!(player_requests[1]
+            .headers
+            .contains(&("X-Goog-Visitor-Id".to_string(), "Cgt4".to_string())));
+
+        // An error status for the first client's player request moves on to the next one
+        let transport = Arc::new(ReplayTransport::new(vec![
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                r#"<script>{"INNERTUBE_API_KEY": "test-key"}</script>"#.to_string(),
+            ),
+            RecordedExchange {
+                status: 503,
+                ..exchange(
+                    Method::Post,
+                    "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                    String::new(),
+                )
+            },
+            exchange(
+                Method::Post,
+                "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                player.to_string(),
+            ),
+            exchange(
+                Method::Get,
+                "https://www.youtube.com/api/timedtext?v=dQw4w9WgXcQ&lang=en",
+                r#"<transcript><text start="0.5" dur="1.5">Hello</text></transcript>"#.to_string(),
+            ),
+        ]));
+        let api = YouTubeTranscript::with_transport(transport.clone(), 0)
+            .with_clients([ClientProfile::android(), ClientProfile::web()]);
+        let transcript = api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+        assert_eq!(transcript.transcript[0].text, "Hello");
+        assert_eq!(transport.requests().len(), 4);
+
+        // With no client left, the error status is returned
+        let api = YouTubeTranscript::with_transport(
+            Arc::new(ReplayTransport::new(vec![
+                exchange(
+                    Method::Get,
+                    "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                    r#"<script>{"INNERTUBE_API_KEY": "test-key"}</script>"#.to_string(),
+                ),
+                RecordedExchange {
+                    status: 503,
+                    ..exchange(
+                        Method::Post,
+                        "https://www.youtube.com/youtubei/v1/player?key=test-key",
+                        String::new(),
+                    )
+                },
+            ])),
+            0,
+        );
+        assert!(matches!(
+            api.fetch_transcript("dQw4w9WgXcQ", None).await,
+            Err(TranscriptError::HttpError { status: Some(503), .. })
+        ));
+    }
+
     #[test]
     fn test_youtube_transcript_default() {
         let api = YouTubeTranscript::default();
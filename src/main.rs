@@ -1,526 +1,3648 @@
-use clap::Parser;
-use std::fs::File;
-use std::io::{self, Write};
-use std::path::Path;
-use ytt::chatgpt::ChatGPT;
-use ytt::{TranscriptError, TranscriptItem, YouTubeTranscript};
+use clap::builder::{PossibleValue, StringValueParser, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use clap_mangen::Man;
+use futures_util::StreamExt;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, error, info, warn};
+use tracing_subscriber::EnvFilter;
+use ytt::archive::DownloadArchive;
+use ytt::cache::LlmCache;
+use ytt::chapters::{self, Chapter};
+use ytt::chatgpt::{ChatGPT, UsageSummary};
+use ytt::config::{Config, Settings};
+use ytt::innertube::{ClientProfile, CLIENT_NAMES};
+use ytt::languages::{LanguagePolicy, TrackPreference, LANGUAGES};
+use ytt::potoken::{PoToken, PoTokenCommand};
+use ytt::search;
+use ytt::template::{sanitize_filename, unique_path, OutputTemplate, TemplateFields};
+use ytt::{
+    Stage, TranscriptError, TranscriptFilter, TranscriptInfo, TranscriptItem, TranscriptList, TranscriptResponse,
+    TranslationLanguage, TranslationOrigin, YouTubeTranscript,
+};
 
 #[derive(Parser)]
-#[command(name = "ytt")]
+#[command(name = "ytt", version)]
 #[command(about = "YouTube Transcript API - Fetch transcripts from YouTube videos", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[command(after_help = "Without a subcommand, ytt behaves like `ytt fetch`; --list and --playlist select `ytt list` and `ytt playlist`.")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    legacy: LegacyArgs,
+
+    /// Configuration profile to apply (a [profiles.<name>] table in the config file)
+    #[arg(long, global = true, env = "YTT_PROFILE")]
+    profile: Option<String>,
+
+    /// Log more detail: -v for debug, -vv for trace
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less: -q for warnings and errors only, -qq for errors only
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Format of log messages on stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+/// How to talk to YouTube, shared by every command
+#[derive(clap::Args, Clone)]
+struct ClientArgs {
+    /// InnerTube clients to request videos as, tried in order when YouTube blocks one (e.g. android,web or ios@20.10.4)
+    #[arg(long = "client", global = true, env = "YTT_CLIENT", value_delimiter = ',', value_parser = Suggestions(CLIENT_NAMES), hide_possible_values = true)]
+    clients: Vec<String>,
+
+    /// Reach YouTube at this URL instead of https://www.youtube.com, e.g. through a proxy or a mock server
+    #[arg(long, global = true, env = "YTT_YOUTUBE_URL")]
+    youtube_url: Option<String>,
+
+    /// PO token to send for videos that require one
+    #[arg(long, global = true, env = "YTT_PO_TOKEN", hide_env_values = true)]
+    po_token: Option<String>,
+
+    /// Visitor data the --po-token was generated for
+    #[arg(long, global = true, env = "YTT_VISITOR_DATA", hide_env_values = true)]
+    visitor_data: Option<String>,
+
+    /// Helper command that prints a PO token for a video; {video_id} in it is replaced with the video ID
+    #[arg(long, global = true, env = "YTT_PO_TOKEN_COMMAND", conflicts_with = "po_token")]
+    po_token_command: Option<String>,
+}
+
+impl Args {
+    /// The command to run together with the matches its options were parsed
+    /// from. The flat invocation is mapped onto the equivalent subcommand.
+    fn into_command(self, matches: &ArgMatches) -> (Command, &ArgMatches) {
+        match (self.command, matches.subcommand()) {
+            (Some(command), Some((_, sub_matches))) => (command, sub_matches),
+            _ => (self.legacy.into_command(), matches),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+/// The original flat invocation, `ytt [OPTIONS] <VIDEO>`
+#[derive(clap::Args)]
+struct LegacyArgs {
     /// YouTube video URL or video ID
-    video: String,
+    #[arg(required_unless_present = "input")]
+    video: Option<String>,
+
+    /// Read video, playlist and channel URLs from a file (one per line, or CSV with a url column); use - for stdin
+    #[arg(short, long, conflicts_with_all = ["video", "playlist"])]
+    input: Option<String>,
+
+    /// List available transcripts instead of fetching (same as `ytt list`); -f selects text, json, csv or table
+    #[arg(long)]
+    list: bool,
+
+    /// The provided URL is a playlist URL - fetch transcripts for all videos in the playlist (same as `ytt playlist`)
+    #[arg(short = 'p', long)]
+    playlist: bool,
+
+    /// Maximum number of videos to process per playlist or channel (ignored in normal mode)
+    #[arg(short = 'm', long)]
+    max: Option<usize>,
+
+    /// Choose the transcript or translation from a menu of the video's tracks
+    #[arg(long, conflicts_with_all = ["input", "list", "playlist", "translate", "all_languages"])]
+    interactive: bool,
 
+    #[command(flatten)]
+    options: FetchOptions,
+}
+
+impl LegacyArgs {
+    fn into_command(self) -> Command {
+        let LegacyArgs {
+            video,
+            input,
+            list,
+            playlist,
+            max,
+            interactive,
+            options,
+        } = self;
+
+        if list {
+            Command::List(ListArgs {
+                video,
+                input,
+                playlist,
+                max,
+                format: ListFormat::from_fetch_format(&options.format),
+                delay: options.delay,
+            })
+        } else if playlist {
+            Command::Playlist(PlaylistArgs {
+                playlist: video.unwrap_or_default(),
+                max,
+                options,
+            })
+        } else {
+            Command::Fetch(FetchArgs {
+                video,
+                input,
+                max,
+                interactive,
+                options,
+            })
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch the transcript of a video, or of every video listed in a batch input file
+    Fetch(FetchArgs),
+    /// List the transcripts available for a video
+    List(ListArgs),
+    /// Fetch the transcripts of every video in a playlist
+    Playlist(PlaylistArgs),
+    /// Fetch the transcripts of a channel's videos
+    Channel(ChannelArgs),
+    /// Show the parts of a transcript that best match a query
+    Search(SearchArgs),
+    /// Summarize a video using ChatGPT
+    Summarize(SummarizeArgs),
+    /// Convert a transcript saved with `-f json` to another format
+    Convert(ConvertArgs),
+    /// Serve transcripts over HTTP
+    Serve(ServeArgs),
+    /// Inspect or clear the cache of ChatGPT responses
+    Cache(CacheArgs),
+    /// Answer a question about a video using ChatGPT, citing transcript timestamps
+    Ask(AskArgs),
+    /// Print a shell completion script
+    Completions(CompletionsArgs),
+    /// Print the man page, or write man pages for every command to a directory
+    Manpage(ManpageArgs),
+}
+
+/// Output formats for transcripts, offered by shell completions
+const OUTPUT_FORMATS: &[(&str, &str)] = &[
+    ("text", "Plain text (default)"),
+    ("txt", "Plain text"),
+    ("json", "JSON array of timed items"),
+    ("srt", "SubRip subtitles"),
+    ("markdown", "Markdown document"),
+    ("md", "Markdown document"),
+];
+
+/// Value parser that accepts any string but offers a list of known values
+/// to shell completions, for options like `-l` where other values are valid too
+#[derive(Clone)]
+struct Suggestions(&'static [(&'static str, &'static str)]);
+
+impl TypedValueParser for Suggestions {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            self.0.iter().map(|(value, help)| PossibleValue::new(*value).help(*help)),
+        ))
+    }
+}
+
+/// Options controlling how transcripts are fetched, processed and written,
+/// shared by every command that writes transcripts
+#[derive(clap::Args)]
+struct FetchOptions {
     /// Language codes (e.g., en, es, fr). Can specify multiple.
-    #[arg(short, long)]
+    #[arg(short, long, env = "YTT_LANGUAGES", value_delimiter = ',', value_parser = Suggestions(LANGUAGES), hide_possible_values = true)]
     languages: Option<Vec<String>>,
 
     /// Translate transcript to this language code
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = Suggestions(LANGUAGES), hide_possible_values = true)]
     translate: Option<String>,
 
+    /// Translate with ChatGPT when YouTube cannot translate to --translate (requires OPENAI_API_KEY env var or --openai-key)
+    #[arg(long, requires = "translate")]
+    llm_fallback: bool,
+
+    /// Let a language match its regional variants (en matches en-US and en-GB)
+    #[arg(long)]
+    match_region: bool,
+
+    /// Prefer a manually created track in any of the --languages over an auto-generated one
+    #[arg(long)]
+    manual_first: bool,
+
+    /// Prefer auto-generated tracks over manually created ones in the same language
+    #[arg(long, conflicts_with = "manual_first")]
+    prefer_generated: bool,
+
+    /// Have YouTube translate a track to the first possible --languages entry when none has a track
+    #[arg(long, conflicts_with = "translate")]
+    translate_fallback: bool,
+
+    /// Write every manual and/or auto-generated track, one file per language (ignores -l; -t adds a translation)
+    #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "all", conflicts_with = "llm_fallback")]
+    all_languages: Option<TrackKinds>,
+
     /// Output format: json, text, txt, srt, or markdown
-    #[arg(short, long, default_value = "text")]
+    #[arg(short, long, env = "YTT_FORMAT", default_value = "text", value_parser = Suggestions(OUTPUT_FORMATS), hide_possible_values = true)]
     format: String,
 
     /// Show transcript text with timestamps (deprecated: timestamps removed by default)
-    #[arg(long)]
+    #[arg(long, overrides_with = "no_timestamps")]
     timestamps: bool,
 
-    /// List available transcripts instead of fetching
-    #[arg(long)]
-    list: bool,
+    /// Leave timestamps out even if the configuration file turns them on
+    #[arg(long, overrides_with = "timestamps")]
+    no_timestamps: bool,
 
     /// Delay between requests in milliseconds (default: 500ms)
-    #[arg(long, default_value = "500")]
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
     delay: u64,
 
     /// Clean up transcript using ChatGPT (requires OPENAI_API_KEY env var or --openai-key)
     #[arg(long)]
     cleanup: bool,
 
+    /// Generate chapters using ChatGPT (requires OPENAI_API_KEY env var or --openai-key)
+    #[arg(long, conflicts_with = "cleanup")]
+    chapters: bool,
+
     /// OpenAI API key (alternative to OPENAI_API_KEY env var)
-    #[arg(long)]
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
     openai_key: Option<String>,
 
+    /// Maximum ChatGPT spend in US dollars for this run; stops before a request would exceed it
+    #[arg(long)]
+    max_cost: Option<f64>,
+
+    /// Always call ChatGPT instead of reusing cached responses
+    #[arg(long)]
+    no_cache: bool,
+
     /// Output file path (if not specified, outputs to stdout)
-    #[arg(short, long)]
+    #[arg(short, long, env = "YTT_OUTPUT")]
     output: Option<String>,
 
     /// Use video title as the basename for the output file
-    #[arg(short = 'n', long)]
+    #[arg(short = 'n', long, overrides_with = "no_name")]
     name: bool,
 
+    /// Use the video ID as the basename even if the configuration file sets `name`
+    #[arg(long, overrides_with = "name")]
+    no_name: bool,
+
+    /// Record processed videos in this file and skip videos already recorded in it
+    #[arg(long)]
+    archive: Option<String>,
+
+    /// Skip videos whose output file already exists
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// Write a JSON report with the status, track, error and timing of every video
+    #[arg(long)]
+    report: Option<String>,
+
+    /// Output path template, e.g. "{channel}/{upload_date}_{title}_{id}.{ext}" (relative to -o if given)
+    #[arg(long, conflicts_with = "name")]
+    output_template: Option<String>,
+
     /// Include video URL at the start of markdown output (only works with -f md/markdown)
-    #[arg(short = 'u', long)]
+    #[arg(short = 'u', long, overrides_with = "no_url")]
     url: bool,
 
-    /// The provided URL is a playlist URL - fetch transcripts for all videos in the playlist
+    /// Leave the video URL out even if the configuration file sets `url`
+    #[arg(long, overrides_with = "url")]
+    no_url: bool,
+}
+
+#[derive(clap::Args)]
+struct FetchArgs {
+    /// YouTube video URL or video ID
+    #[arg(required_unless_present = "input")]
+    video: Option<String>,
+
+    /// Read video, playlist and channel URLs from a file (one per line, or CSV with a url column); use - for stdin
+    #[arg(short, long, conflicts_with = "video")]
+    input: Option<String>,
+
+    /// Maximum number of videos to process per playlist or channel in the input file
+    #[arg(short = 'm', long, requires = "input")]
+    max: Option<usize>,
+
+    /// Choose the transcript or translation from a menu of the video's tracks
+    #[arg(long, conflicts_with_all = ["input", "translate", "all_languages"])]
+    interactive: bool,
+
+    #[command(flatten)]
+    options: FetchOptions,
+}
+
+#[derive(clap::Args)]
+struct ListArgs {
+    /// YouTube video URL or video ID
+    #[arg(required_unless_present = "input")]
+    video: Option<String>,
+
+    /// List the transcripts of every video, playlist and channel URL in this file; use - for stdin
+    #[arg(short, long, conflicts_with_all = ["video", "playlist"])]
+    input: Option<String>,
+
+    /// The provided URL is a playlist URL - list transcripts for all videos in the playlist
     #[arg(short = 'p', long)]
     playlist: bool,
 
-    /// Maximum number of videos to process in playlist mode (ignored in normal mode)
+    /// Maximum number of videos to list per playlist or channel
+    #[arg(short = 'm', long)]
+    max: Option<usize>,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = ListFormat::Text)]
+    format: ListFormat,
+
+    /// Delay between requests in milliseconds (default: 500ms)
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
+    delay: u64,
+}
+
+#[derive(clap::Args)]
+struct PlaylistArgs {
+    /// YouTube playlist URL or playlist ID
+    playlist: String,
+
+    /// Maximum number of videos to process
     #[arg(short = 'm', long)]
     max: Option<usize>,
+
+    #[command(flatten)]
+    options: FetchOptions,
+}
+
+#[derive(clap::Args)]
+struct ChannelArgs {
+    /// YouTube channel URL or @handle
+    channel: String,
+
+    /// Maximum number of videos to process, newest first
+    #[arg(short = 'm', long)]
+    max: Option<usize>,
+
+    #[command(flatten)]
+    options: FetchOptions,
+}
+
+#[derive(clap::Args)]
+struct SearchArgs {
+    /// YouTube video URL or video ID
+    video: String,
+
+    /// Words to look for in the transcript
+    query: String,
+
+    /// Language codes (e.g., en, es, fr). Can specify multiple.
+    #[arg(short, long, env = "YTT_LANGUAGES", value_delimiter = ',', value_parser = Suggestions(LANGUAGES), hide_possible_values = true)]
+    languages: Option<Vec<String>>,
+
+    /// Maximum number of transcript excerpts to show
+    #[arg(short = 'n', long, default_value = "5")]
+    results: usize,
+
+    /// Delay between requests in milliseconds (default: 500ms)
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
+    delay: u64,
+}
+
+#[derive(clap::Args)]
+struct SummarizeArgs {
+    /// YouTube video URL or video ID
+    video: String,
+
+    /// Language codes (e.g., en, es, fr). Can specify multiple.
+    #[arg(short, long, env = "YTT_LANGUAGES", value_delimiter = ',', value_parser = Suggestions(LANGUAGES), hide_possible_values = true)]
+    languages: Option<Vec<String>>,
+
+    /// Output file path (if not specified, outputs to stdout)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Delay between requests in milliseconds (default: 500ms)
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
+    delay: u64,
+
+    /// OpenAI API key (alternative to OPENAI_API_KEY env var)
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    openai_key: Option<String>,
+
+    /// Maximum ChatGPT spend in US dollars; fails before a request would exceed it
+    #[arg(long)]
+    max_cost: Option<f64>,
+
+    /// Always call ChatGPT instead of reusing cached responses
+    #[arg(long)]
+    no_cache: bool,
+}
+
+#[derive(clap::Args)]
+struct ConvertArgs {
+    /// Transcript written with `-f json` (or by `ytt serve`); use - for stdin
+    input: String,
+
+    /// Output format: json, text, txt, srt, or markdown
+    #[arg(short, long, env = "YTT_FORMAT", default_value = "text", value_parser = Suggestions(OUTPUT_FORMATS), hide_possible_values = true)]
+    format: String,
+
+    /// Show transcript text with timestamps
+    #[arg(long, overrides_with = "no_timestamps")]
+    timestamps: bool,
+
+    /// Leave timestamps out even if the configuration file turns them on
+    #[arg(long, overrides_with = "timestamps")]
+    no_timestamps: bool,
+
+    /// Output file path (if not specified, outputs to stdout)
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind: String,
+
+    /// Delay between requests to YouTube in milliseconds (default: 500ms)
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
+    delay: u64,
+}
+
+#[derive(clap::Args)]
+struct CacheArgs {
+    #[command(subcommand)]
+    action: CacheAction,
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Print the cache directory
+    Path,
+    /// Show the number and size of cached responses
+    Stats,
+    /// Remove all cached responses
+    Clear,
+}
+
+#[derive(clap::Args)]
+struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+#[derive(clap::Args)]
+struct ManpageArgs {
+    /// Write `ytt.1` and a page per command (`ytt-fetch.1`, ...) to this directory instead of printing `ytt.1`
+    #[arg(short, long)]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct AskArgs {
+    /// YouTube video URL or video ID
+    video: String,
+
+    /// Question to answer from the transcript
+    question: String,
+
+    /// Language codes (e.g., en, es, fr). Can specify multiple.
+    #[arg(short, long, env = "YTT_LANGUAGES", value_delimiter = ',', value_parser = Suggestions(LANGUAGES), hide_possible_values = true)]
+    languages: Option<Vec<String>>,
+
+    /// Maximum number of transcript excerpts sent to ChatGPT
+    #[arg(long, default_value = "5")]
+    excerpts: usize,
+
+    /// Delay between requests in milliseconds (default: 500ms)
+    #[arg(long, env = "YTT_DELAY", default_value = "500")]
+    delay: u64,
+
+    /// OpenAI API key (alternative to OPENAI_API_KEY env var)
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    openai_key: Option<String>,
+
+    /// Always call ChatGPT instead of reusing cached responses
+    #[arg(long)]
+    no_cache: bool,
 }
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    init_logging(args.verbose, args.quiet, args.log_format);
+
+    let profile = args.profile.clone();
+    let client_args = args.client.clone();
+    let (mut command, command_matches) = args.into_command(&matches);
+    check_interactive(&command, command_matches).unwrap_or_else(|e| e.exit());
+    let result = match Config::discover().and_then(|config| config.resolve(profile.as_deref())) {
+        Ok(settings) => match ClientSettings::new(client_args, &settings) {
+            Ok(client_settings) => {
+                CLIENT_SETTINGS.get_or_init(|| client_settings);
+                apply_settings(&mut command, command_matches, settings);
+                run(command).await
+            }
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
 
-    if let Err(e) = run(args).await {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => {
+            error!("{}", e.full_message());
+            error_exit_code(&e)
+        }
+    };
+    if code != EXIT_SUCCESS {
+        std::process::exit(code);
     }
 }
 
-async fn run(args: Args) -> Result<(), TranscriptError> {
-    let api = YouTubeTranscript::with_delay(args.delay);
+/// How every command talks to YouTube, from [`ClientArgs`] or the configuration file
+static CLIENT_SETTINGS: OnceLock<ClientSettings> = OnceLock::new();
 
-    // Handle playlist mode
-    if args.playlist {
-        let playlist_id = YouTubeTranscript::extract_playlist_id(&args.video)?;
-        eprintln!("Fetching video IDs from playlist: {}", playlist_id);
-        let video_ids = api.get_playlist_video_ids(&playlist_id).await?;
-        eprintln!("Found {} videos in playlist", video_ids.len());
+#[derive(Debug, Default)]
+struct ClientSettings {
+    clients: Vec<ClientProfile>,
+    youtube_url: Option<String>,
+    po_token: Option<PoToken>,
+    po_token_command: Option<PoTokenCommand>,
+}
 
-        // Limit to max number if specified
-        let videos_to_process: Vec<&String> = if let Some(max) = args.max {
-            let limit = max.min(video_ids.len());
-            if limit < video_ids.len() {
-                eprintln!("Processing first {} videos (limited by --max)", limit);
-            }
-            video_ids.iter().take(limit).collect()
+impl ClientSettings {
+    /// Settings from the command line, falling back to the configuration file
+    fn new(args: ClientArgs, settings: &Settings) -> Result<Self, TranscriptError> {
+        let clients = if args.clients.is_empty() {
+            settings.clients.clone().unwrap_or_default()
         } else {
-            video_ids.iter().collect()
+            args.clients
         };
 
-        let total = videos_to_process.len();
-        for (index, video_id) in videos_to_process.iter().enumerate() {
-            eprintln!("\n[{}/{}] Processing video: {}", index + 1, total, video_id);
-            if let Err(e) = process_single_video(&api, &args, video_id, Some(index + 1), Some(total)).await {
-                eprintln!("Error processing video {}: {}", video_id, e);
-                // Continue with next video instead of failing completely
-                continue;
-            }
+        // A token or command on the command line replaces both configured ones
+        let (po_token, visitor_data, po_token_command) = if args.po_token.is_some() || args.po_token_command.is_some() {
+            (args.po_token, args.visitor_data, args.po_token_command)
+        } else {
+            (
+                settings.po_token.clone(),
+                args.visitor_data.or_else(|| settings.visitor_data.clone()),
+                settings.po_token_command.clone(),
+            )
+        };
+        if po_token.is_some() && po_token_command.is_some() {
+            return Err(TranscriptError::ConfigError(
+                "po_token and po_token_command cannot both be set".to_string(),
+            ));
         }
-        return Ok(());
+        if visitor_data.is_some() && po_token.is_none() {
+            return Err(TranscriptError::ConfigError(
+                "Visitor data is only used together with a PO token".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            clients: clients.iter().map(|name| name.parse()).collect::<Result<_, _>>()?,
+            youtube_url: args.youtube_url.or_else(|| settings.youtube_url.clone()),
+            po_token: po_token.map(|token| match visitor_data {
+                Some(visitor_data) => PoToken::new(token).with_visitor_data(visitor_data),
+                None => PoToken::new(token),
+            }),
+            po_token_command: po_token_command.as_deref().map(PoTokenCommand::parse).transpose()?,
+        })
     }
+}
 
-    // Single video mode
-    let video_id = YouTubeTranscript::extract_video_id(&args.video)?;
-    process_single_video(&api, &args, &video_id, None, None).await
+/// A client for YouTube, set up as the command line and configuration ask
+fn youtube_client(delay_ms: u64) -> YouTubeTranscript {
+    let settings = CLIENT_SETTINGS.get_or_init(ClientSettings::default);
+    let mut api = YouTubeTranscript::with_delay(delay_ms).with_clients(settings.clients.clone());
+    if let Some(url) = &settings.youtube_url {
+        api = api.with_base_url(url);
+    }
+    if let Some(po_token) = &settings.po_token {
+        api = api.with_po_token_provider(po_token.clone());
+    }
+    if let Some(command) = &settings.po_token_command {
+        api = api.with_po_token_provider(command.clone());
+    }
+    api
 }
 
-async fn process_single_video(
-    api: &YouTubeTranscript,
-    args: &Args,
-    video_id: &str,
-    video_index: Option<usize>,
-    total_videos: Option<usize>,
-) -> Result<(), TranscriptError> {
-    if args.list {
-        let transcript_list = api.list_transcripts(video_id).await?;
-        if let (Some(idx), Some(total)) = (video_index, total_videos) {
-            println!("[{}/{}] Available transcripts for video: {}", idx, total, video_id);
-        } else {
-            println!("Available transcripts for video: {}", video_id);
+/// Progress bars are drawn through this, so log lines can be printed above them
+static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// Writes log lines to stderr, hiding progress bars while doing so.
+/// stdout is reserved for transcript data.
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        PROGRESS.suspend(|| io::stderr().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+fn log_level(verbose: u8, quiet: u8) -> LevelFilter {
+    match (verbose, quiet) {
+        (0, 0) => LevelFilter::INFO,
+        (0, 1) => LevelFilter::WARN,
+        (0, _) => LevelFilter::ERROR,
+        (1, _) => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Set up logging to stderr. `RUST_LOG` overrides the -v/-q level.
+/// Progress bars are only drawn for text logs on a terminal.
+fn init_logging(verbose: u8, quiet: u8, format: LogFormat) {
+    let level = log_level(verbose, quiet);
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) if !directives.is_empty() => EnvFilter::new(directives),
+        // Dependencies only get to log warnings, even with -vv
+        _ => EnvFilter::new(format!("{},ytt={}", level.min(LevelFilter::WARN), level)),
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| LogWriter);
+    // Progress bars would only garble JSON logs or redirected output
+    if quiet > 0 || format == LogFormat::Json || !io::stderr().is_terminal() {
+        PROGRESS.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    match format {
+        LogFormat::Json => builder.json().init(),
+        LogFormat::Text if verbose > 0 => builder.with_ansi(io::stderr().is_terminal()).init(),
+        LogFormat::Text => builder
+            .with_ansi(io::stderr().is_terminal())
+            .without_time()
+            .with_target(false)
+            .init(),
+    }
+}
+
+/// Progress bar for a batch of videos
+fn batch_progress(total: usize) -> ProgressBar {
+    let style = ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} [{elapsed_precise}, eta {eta}] {msg}")
+        .expect("valid progress template")
+        .progress_chars("=> ");
+    PROGRESS.add(ProgressBar::new(total as u64).with_style(style))
+}
+
+// Process exit codes. 2 is also what clap uses for invalid arguments.
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_PARTIAL_FAILURE: i32 = 3;
+const EXIT_TOTAL_FAILURE: i32 = 4;
+const EXIT_NO_TRANSCRIPT: i32 = 10;
+const EXIT_VIDEO_UNAVAILABLE: i32 = 11;
+const EXIT_BLOCKED: i32 = 12;
+const EXIT_YOUTUBE_ERROR: i32 = 13;
+const EXIT_LLM_ERROR: i32 = 14;
+
+/// Exit code for a run that failed with `error`
+fn error_exit_code(error: &TranscriptError) -> i32 {
+    match error {
+        TranscriptError::InvalidVideoId(_)
+        | TranscriptError::ConfigError(_)
+        | TranscriptError::InvalidOutputTemplate(_)
+        | TranscriptError::InvalidInput(_) => EXIT_USAGE,
+        TranscriptError::TranscriptsDisabled(_)
+        | TranscriptError::NoTranscriptFound(_, _)
+        | TranscriptError::NotTranslatable(_)
+        | TranscriptError::TranslationLanguageNotAvailable(_) => EXIT_NO_TRANSCRIPT,
+        TranscriptError::VideoUnavailable(_)
+        | TranscriptError::VideoUnplayable(_, _)
+        | TranscriptError::AgeRestricted(_)
+        | TranscriptError::PoTokenRequired(_) => EXIT_VIDEO_UNAVAILABLE,
+        TranscriptError::IpBlocked(_)
+        | TranscriptError::RequestBlocked(_)
+        | TranscriptError::FailedToCreateConsentCookie(_) => EXIT_BLOCKED,
+        TranscriptError::HttpError { .. }
+        | TranscriptError::YouTubeDataUnparsable { .. }
+        | TranscriptError::XmlParseError { .. }
+        | TranscriptError::JsonParseError { .. } => EXIT_YOUTUBE_ERROR,
+        TranscriptError::LlmResponseInvalid(_) | TranscriptError::BudgetExceeded(_) => EXIT_LLM_ERROR,
+        TranscriptError::IoError { .. } | TranscriptError::PoTokenProviderFailed { .. } => EXIT_FAILURE,
+    }
+}
+
+/// Fill in options that were not given on the command line or through the
+/// environment from the configuration file settings. `matches` are the
+/// matches of `command`, i.e. of the subcommand if one was used.
+fn apply_settings(command: &mut Command, matches: &ArgMatches, settings: Settings) {
+    let configured_delay = |delay: &mut u64, settings: &Settings| {
+        if let Some(configured) = settings.delay.filter(|_| !is_explicit(matches, "delay")) {
+            *delay = configured;
+        }
+    };
+
+    match command {
+        Command::Fetch(FetchArgs { options, .. })
+        | Command::Playlist(PlaylistArgs { options, .. })
+        | Command::Channel(ChannelArgs { options, .. }) => {
+            configured_delay(&mut options.delay, &settings);
+            apply_fetch_settings(options, matches, settings);
         }
-        println!("\nManually created:");
-        for transcript in transcript_list.manually_created.values() {
-            println!("  {} ({})", transcript.language, transcript.language_code);
+        Command::List(list_args) => configured_delay(&mut list_args.delay, &settings),
+        Command::Search(search_args) => {
+            configured_delay(&mut search_args.delay, &settings);
+            search_args.languages = search_args.languages.take().or(settings.languages);
         }
-        println!("\nAuto-generated:");
-        for transcript in transcript_list.generated.values() {
-            println!("  {} ({})", transcript.language, transcript.language_code);
+        Command::Summarize(summarize_args) => {
+            configured_delay(&mut summarize_args.delay, &settings);
+            summarize_args.languages = summarize_args.languages.take().or(settings.languages);
+            summarize_args.openai_key = summarize_args.openai_key.take().or(settings.openai_key);
+            summarize_args.max_cost = summarize_args.max_cost.or(settings.max_cost);
         }
-        if !transcript_list.translation_languages.is_empty() {
-            println!("\nTranslation languages:");
-            for lang in &transcript_list.translation_languages {
-                println!("  {} ({})", lang.language, lang.language_code);
+        Command::Convert(convert_args) => {
+            if let Some(format) = settings.format.filter(|_| !is_explicit(matches, "format")) {
+                convert_args.format = format;
             }
+            configured_flag(&mut convert_args.timestamps, matches, "timestamps", settings.timestamps);
+        }
+        Command::Serve(serve_args) => configured_delay(&mut serve_args.delay, &settings),
+        Command::Cache(_) | Command::Completions(_) | Command::Manpage(_) => {}
+        Command::Ask(ask_args) => {
+            configured_delay(&mut ask_args.delay, &settings);
+            ask_args.languages = ask_args.languages.take().or(settings.languages);
+            ask_args.openai_key = ask_args.openai_key.take().or(settings.openai_key);
         }
-        return Ok(());
     }
+}
 
-    if video_index.is_none() {
-        println!("Fetching transcript for video: {}", video_id);
+fn apply_fetch_settings(options: &mut FetchOptions, matches: &ArgMatches, settings: Settings) {
+    options.languages = options.languages.take().or(settings.languages);
+    options.output = options.output.take().or(settings.output);
+    // -n on the command line replaces a configured template rather than conflicting with it
+    if !options.name {
+        options.output_template = options.output_template.take().or(settings.output_template);
     }
+    options.openai_key = options.openai_key.take().or(settings.openai_key);
+    options.max_cost = options.max_cost.or(settings.max_cost);
+    if let Some(format) = settings.format.filter(|_| !is_explicit(matches, "format")) {
+        options.format = format;
+    }
+    configured_flag(&mut options.name, matches, "name", settings.name);
+    configured_flag(&mut options.url, matches, "url", settings.url);
+    configured_flag(&mut options.timestamps, matches, "timestamps", settings.timestamps);
+}
 
-    let transcript = if let Some(target_lang) = &args.translate {
-        let source_langs: Vec<&str> = args
-            .languages
-            .as_ref()
-            .map(|v| v.iter().map(|s| s.as_str()).collect())
-            .unwrap_or_else(|| vec!["en"]);
-        api.translate_transcript(video_id, &source_langs, target_lang)
-            .await?
-    } else {
-        let lang_codes: Option<Vec<&str>> = args
-            .languages
-            .as_ref()
-            .map(|v| v.iter().map(|s| s.as_str()).collect());
-        api.fetch_transcript(video_id, lang_codes).await?
-    };
-
-    // Determine if we need markdown formatting from ChatGPT
-    let format_markdown = args.cleanup
-        && (args.format.to_lowercase() == "markdown" || args.format.to_lowercase() == "md");
+/// Take a flag from the configuration unless `--<id>` or `--no-<id>` was given
+fn configured_flag(flag: &mut bool, matches: &ArgMatches, id: &str, configured: Option<bool>) {
+    let negated = format!("no_{}", id);
+    if let Some(configured) = configured.filter(|_| !is_explicit(matches, id) && !is_explicit(matches, &negated)) {
+        *flag = configured;
+    }
+}
 
-    // If cleanup is requested, send to ChatGPT first
-    let transcript_items = if args.cleanup {
-        if video_index.is_none() {
-            eprintln!("Cleaning up transcript with ChatGPT...");
-        }
-        let transcript_text: String = transcript
-            .transcript
-            .iter()
-            .map(|item| item.text.as_str())
-            .collect::<Vec<_>>()
-            .join(" ");
+/// `--interactive` picks the language from a menu, so `-l` on the command line
+/// contradicts it. Languages from `YTT_LANGUAGES` or the configuration file are
+/// just not used, which is why this isn't a clap conflict.
+fn check_interactive(command: &Command, matches: &ArgMatches) -> Result<(), clap::Error> {
+    let interactive = matches!(command, Command::Fetch(FetchArgs { interactive: true, .. }));
+    if interactive && matches.value_source("languages") == Some(ValueSource::CommandLine) {
+        return Err(Args::command().error(
+            ErrorKind::ArgumentConflict,
+            "the argument '--interactive' cannot be used with '--languages <LANGUAGES>'",
+        ));
+    }
+    Ok(())
+}
 
-        let chatgpt = ChatGPT::new(args.openai_key.clone())?;
-        let cleaned_text = chatgpt
-            .cleanup_transcript(&transcript_text, format_markdown)
-            .await?;
+fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
 
-        // For cleanup, output the cleaned text directly as a single item
-        // This preserves the cleaned flow better than trying to split it back
-        vec![TranscriptItem {
-            text: cleaned_text,
-            start: transcript
-                .transcript
-                .first()
-                .map(|i| i.start)
-                .unwrap_or(0.0),
-            duration: transcript.transcript.iter().map(|i| i.duration).sum(),
-        }]
+/// Run the command, returning the process exit code
+async fn run(command: Command) -> Result<i32, TranscriptError> {
+    match command {
+        Command::Fetch(args) => run_fetch(args).await,
+        Command::List(args) => run_list(args).await,
+        Command::Playlist(args) => run_playlist(args).await,
+        Command::Channel(args) => run_channel(args).await,
+        Command::Search(args) => run_search(args).await.map(|_| EXIT_SUCCESS),
+        Command::Summarize(args) => run_summarize(args).await.map(|_| EXIT_SUCCESS),
+        Command::Convert(args) => run_convert(args).map(|_| EXIT_SUCCESS),
+        Command::Serve(args) => run_serve(args).await.map(|_| EXIT_SUCCESS),
+        Command::Cache(args) => run_cache(args).map(|_| EXIT_SUCCESS),
+        Command::Ask(args) => run_ask(args).await.map(|_| EXIT_SUCCESS),
+        Command::Completions(args) => run_completions(args).map(|_| EXIT_SUCCESS),
+        Command::Manpage(args) => run_manpage(args).map(|_| EXIT_SUCCESS),
+    }
+}
+
+fn run_completions(args: CompletionsArgs) -> Result<(), TranscriptError> {
+    // clap_complete panics on write errors, so a closed pipe is reported like any other
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut Args::command(), "ytt", &mut script);
+    io::stdout()
+        .write_all(&script)
+        .map_err(|e| TranscriptError::io("Failed to write completions", e))
+}
+
+fn run_manpage(args: ManpageArgs) -> Result<(), TranscriptError> {
+    let Some(out_dir) = args.out_dir else {
+        Man::new(Args::command()).render(&mut io::stdout())?;
+        return Ok(());
+    };
+
+    fs::create_dir_all(&out_dir).map_err(|e| {
+        TranscriptError::io(format!("Failed to create directory {}", out_dir.display()), e)
+    })?;
+    clap_mangen::generate_to(Args::command(), &out_dir).map_err(|e| {
+        TranscriptError::io(format!("Failed to write man pages to {}", out_dir.display()), e)
+    })?;
+    info!("Wrote man pages to {}", out_dir.display());
+    Ok(())
+}
+
+async fn run_fetch(args: FetchArgs) -> Result<i32, TranscriptError> {
+    let api = youtube_client(args.options.delay);
+    let chatgpt = fetch_chatgpt(&args.options)?;
+
+    // Handle batch input from a file or stdin
+    if let Some(input_path) = &args.input {
+        let (video_ids, unresolved) = input_video_ids(&api, input_path, args.max).await?;
+        return process_videos(&api, &args.options, chatgpt.as_ref(), &video_ids, &unresolved).await;
+    }
+
+    let video_id = YouTubeTranscript::extract_video_id(args.video.as_deref().unwrap_or_default())?;
+    let mut outputs = OutputState::new(&args.options)?;
+    let mut report = RunReport::new();
+    let started = Instant::now();
+    let result = if args.interactive {
+        fetch_interactively(&api, &args.options, chatgpt.as_ref(), &mut outputs, &video_id).await
+    } else {
+        process_single_video(&api, &args.options, chatgpt.as_ref(), &mut outputs, &video_id, None).await
+    };
+    report.record(&video_id, started, &result);
+    if let Some(chatgpt) = &chatgpt {
+        print_usage(chatgpt);
+    }
+    if let Some(path) = &args.options.report {
+        report.write(path, report.exit_code())?;
+    }
+    result.map(|_| EXIT_SUCCESS)
+}
+
+async fn run_playlist(args: PlaylistArgs) -> Result<i32, TranscriptError> {
+    let api = youtube_client(args.options.delay);
+    let chatgpt = fetch_chatgpt(&args.options)?;
+    let video_ids = playlist_video_ids(&api, &args.playlist, args.max).await?;
+    process_videos(&api, &args.options, chatgpt.as_ref(), &video_ids, &[]).await
+}
+
+async fn run_channel(args: ChannelArgs) -> Result<i32, TranscriptError> {
+    let api = youtube_client(args.options.delay);
+    let chatgpt = fetch_chatgpt(&args.options)?;
+    let channel_url = YouTubeTranscript::extract_channel_url(&args.channel)?;
+    info!("Fetching video IDs from channel: {}", channel_url);
+    let video_ids = api.get_channel_video_ids(&channel_url).await?;
+    info!("Found {} videos on channel", video_ids.len());
+    let video_ids = limit_videos(video_ids, args.max);
+    process_videos(&api, &args.options, chatgpt.as_ref(), &video_ids, &[]).await
+}
+
+async fn run_list(args: ListArgs) -> Result<i32, TranscriptError> {
+    let api = youtube_client(args.delay);
+    let mut writer = ListWriter::new(io::stdout(), args.format);
+
+    let (video_ids, unresolved) = if let Some(input_path) = &args.input {
+        input_video_ids(&api, input_path, args.max).await?
+    } else if args.playlist {
+        let video_ids = playlist_video_ids(&api, args.video.as_deref().unwrap_or_default(), args.max).await?;
+        (video_ids, Vec::new())
     } else {
-        transcript.transcript
+        let video_id = YouTubeTranscript::extract_video_id(args.video.as_deref().unwrap_or_default())?;
+        let transcript_list = api.list_transcripts(&video_id).await?;
+        writer.write(transcript_list, None)?;
+        writer.finish(false)?;
+        return Ok(EXIT_SUCCESS);
     };
 
-    // Determine output destination
-    // For playlists, if -o is a directory or -n is used, each video gets its own file
-    let output_dest = if let Some(ref output_path) = args.output {
-        let path = Path::new(output_path);
-        
-        // Check if the path is a directory:
-        // 1. If it exists and is a directory
-        // 2. If it ends with a path separator (directory-like)
-        let is_directory = if path.exists() {
-            path.is_dir()
-        } else {
-            // If path doesn't exist, check if it ends with a separator (directory-like)
-            let sep = std::path::MAIN_SEPARATOR;
-            output_path.ends_with(sep) || output_path.ends_with('/')
-        };
-        
-        if is_directory && args.name {
-            // Combine directory with title as filename
-            let title = transcript.title.as_ref()
-                .ok_or_else(|| TranscriptError::YouTubeDataUnparsable(
-                    "Failed to extract video title".to_string()
-                ))?;
-            let sanitized_title = sanitize_filename(title);
-            let extension = match args.format.to_lowercase().as_str() {
-                "json" => "json",
-                "srt" => "srt",
-                "markdown" | "md" => "md",
-                "text" | "txt" => "txt",
-                _ => "txt",
-            };
-            let filename = format!("{}.{}", sanitized_title, extension);
-            let combined_path = path.join(filename);
-            OutputDestination::File(combined_path.to_string_lossy().to_string())
-        } else if is_directory && video_index.is_some() {
-            // For playlist mode with directory output, use video_id as filename
-            let extension = match args.format.to_lowercase().as_str() {
-                "json" => "json",
-                "srt" => "srt",
-                "markdown" | "md" => "md",
-                "text" | "txt" => "txt",
-                _ => "txt",
+    let total = video_ids.len();
+    let mut failed = unresolved.len();
+    for (index, video_id) in video_ids.iter().enumerate() {
+        match api.list_transcripts(video_id).await {
+            Ok(transcript_list) => writer.write(transcript_list, Some((index + 1, total)))?,
+            Err(e) => {
+                error!("Error listing transcripts for video {}: {}", video_id, e.full_message());
+                failed += 1;
+            }
+        }
+    }
+    writer.finish(true)?;
+
+    Ok(match failed {
+        0 => EXIT_SUCCESS,
+        failed if failed == total + unresolved.len() => EXIT_TOTAL_FAILURE,
+        _ => EXIT_PARTIAL_FAILURE,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ListFormat {
+    /// Human-readable text
+    Text,
+    /// One JSON object per video, or an array of them when listing several videos
+    Json,
+    /// One row per track
+    Csv,
+    /// Aligned columns
+    Table,
+}
+
+impl ListFormat {
+    /// The list format matching the fetch format given with the flat `--list` invocation
+    fn from_fetch_format(format: &str) -> Self {
+        match format.to_lowercase().as_str() {
+            "json" => ListFormat::Json,
+            "csv" => ListFormat::Csv,
+            "table" => ListFormat::Table,
+            _ => ListFormat::Text,
+        }
+    }
+}
+
+/// Tracks written by `--all-languages`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TrackKinds {
+    /// Manually created and auto-generated tracks
+    All,
+    /// Only manually created tracks
+    Manual,
+    /// Only auto-generated tracks
+    Generated,
+}
+
+/// A CSV row of `ytt list --format csv`
+#[derive(Serialize)]
+struct ListRow<'a> {
+    video_id: &'a str,
+    language_code: &'a str,
+    language: &'a str,
+    kind: &'static str,
+    is_generated: bool,
+    is_translatable: bool,
+    has_base_url: bool,
+    /// Codes of the languages the track can be translated to, separated by `;`
+    translation_languages: String,
+}
+
+/// Writes the transcript lists of one or more videos in a `ListFormat`.
+/// JSON is written by `finish`, so a batch forms a single document.
+struct ListWriter<W: Write> {
+    out: W,
+    format: ListFormat,
+    csv_header_written: bool,
+    lists: Vec<TranscriptList>,
+}
+
+impl<W: Write> ListWriter<W> {
+    fn new(out: W, format: ListFormat) -> Self {
+        Self {
+            out,
+            format,
+            csv_header_written: false,
+            lists: Vec::new(),
+        }
+    }
+
+    /// Write one video's list. `position` is its (1-based index, total) when listing several videos.
+    fn write(&mut self, list: TranscriptList, position: Option<(usize, usize)>) -> Result<(), TranscriptError> {
+        match self.format {
+            ListFormat::Json => self.lists.push(list),
+            ListFormat::Csv => self.write_csv(&list)?,
+            ListFormat::Text | ListFormat::Table => {
+                if let Some((index, total)) = position {
+                    if index > 1 {
+                        writeln!(self.out)?;
+                    }
+                    write!(self.out, "[{}/{}] ", index, total)?;
+                }
+                writeln!(self.out, "Available transcripts for video: {}", list.video_id)?;
+                if self.format == ListFormat::Table {
+                    self.write_table(&list)?;
+                } else {
+                    self.write_text(&list)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write anything that is only complete once every video was listed.
+    /// `batch` selects a JSON array even if only one video was listed.
+    fn finish(mut self, batch: bool) -> Result<(), TranscriptError> {
+        if self.format == ListFormat::Json {
+            let json = match self.lists.as_slice() {
+                [list] if !batch => serde_json::to_string_pretty(list)?,
+                lists => serde_json::to_string_pretty(lists)?,
             };
-            let filename = format!("{}.{}", video_id, extension);
-            let combined_path = path.join(filename);
-            OutputDestination::File(combined_path.to_string_lossy().to_string())
-        } else {
-            // Use the path as-is (either it's a file path, or -n wasn't specified)
-            // For playlist mode, this would overwrite, so we should handle it differently
-            if video_index.is_some() {
-                // In playlist mode with a file path, append video_id
-                let path_buf = Path::new(output_path);
-                let stem = path_buf.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
-                let extension = path_buf.extension().and_then(|s| s.to_str()).unwrap_or("txt");
-                let parent = path_buf.parent().unwrap_or(Path::new("."));
-                let new_filename = format!("{}_{}.{}", stem, video_id, extension);
-                let combined_path = parent.join(new_filename);
-                OutputDestination::File(combined_path.to_string_lossy().to_string())
-            } else {
-                OutputDestination::File(output_path.clone())
-            }
-        }
-    } else if args.name {
-        // Use video title as basename in current directory
-        let title = transcript.title.as_ref()
-            .ok_or_else(|| TranscriptError::YouTubeDataUnparsable(
-                "Failed to extract video title".to_string()
-            ))?;
-        let sanitized_title = sanitize_filename(title);
-        let extension = match args.format.to_lowercase().as_str() {
-            "json" => "json",
-            "srt" => "srt",
-            "markdown" | "md" => "md",
-            "text" | "txt" => "txt",
-            _ => "txt",
+            writeln!(self.out, "{}", json)?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_text(&mut self, list: &TranscriptList) -> io::Result<()> {
+        let tracks = list.all_transcripts();
+        for (heading, is_generated) in [("Manually created", false), ("Auto-generated", true)] {
+            writeln!(self.out, "\n{}:", heading)?;
+            for track in tracks.iter().filter(|t| t.is_generated == is_generated) {
+                writeln!(self.out, "  {} ({})", track.language, track.language_code)?;
+            }
+        }
+        if !list.translation_languages.is_empty() {
+            writeln!(self.out, "\nTranslation languages:")?;
+            for lang in list.sorted_translation_languages() {
+                writeln!(self.out, "  {} ({})", lang.language, lang.language_code)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_table(&mut self, list: &TranscriptList) -> io::Result<()> {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        let rows: Vec<[&str; 5]> = list
+            .all_transcripts()
+            .into_iter()
+            .map(|track| {
+                [
+                    track.language_code.as_str(),
+                    track.language.as_str(),
+                    track.kind().as_str(),
+                    yes_no(track.is_translatable),
+                    yes_no(!track.base_url.is_empty()),
+                ]
+            })
+            .collect();
+        writeln!(self.out)?;
+        write_columns(&mut self.out, ["CODE", "LANGUAGE", "KIND", "TRANSLATABLE", "URL"], &rows)?;
+
+        if !list.translation_languages.is_empty() {
+            let rows: Vec<[&str; 2]> = list
+                .sorted_translation_languages()
+                .into_iter()
+                .map(|lang| [lang.language_code.as_str(), lang.language.as_str()])
+                .collect();
+            writeln!(self.out)?;
+            write_columns(&mut self.out, ["CODE", "TRANSLATION LANGUAGE"], &rows)?;
+        }
+        Ok(())
+    }
+
+    fn write_csv(&mut self, list: &TranscriptList) -> Result<(), TranscriptError> {
+        let mut csv = csv::WriterBuilder::new()
+            .has_headers(!self.csv_header_written)
+            .from_writer(&mut self.out);
+        let translation_languages = list
+            .sorted_translation_languages()
+            .into_iter()
+            .map(|lang| lang.language_code.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        for track in list.all_transcripts() {
+            csv.serialize(ListRow {
+                video_id: &list.video_id,
+                language_code: &track.language_code,
+                language: &track.language,
+                kind: track.kind().as_str(),
+                is_generated: track.is_generated,
+                is_translatable: track.is_translatable,
+                has_base_url: !track.base_url.is_empty(),
+                translation_languages: if track.is_translatable {
+                    translation_languages.clone()
+                } else {
+                    String::new()
+                },
+            })
+            .map_err(|e| TranscriptError::io("Failed to write CSV", e.into()))?;
+            self.csv_header_written = true;
+        }
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+/// Write rows as left-aligned columns separated by two spaces
+fn write_columns<const N: usize>(out: &mut impl Write, header: [&str; N], rows: &[[&str; N]]) -> io::Result<()> {
+    let mut widths = header.map(|h| h.chars().count());
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Video IDs of every entry of a batch input file (or stdin), and the
+/// entries that could not be resolved
+async fn input_video_ids(
+    api: &YouTubeTranscript,
+    input_path: &str,
+    max: Option<usize>,
+) -> Result<(Vec<String>, Vec<UnresolvedEntry>), TranscriptError> {
+    let entries = read_input_entries(input_path)?;
+    info!("Read {} entries from {}", entries.len(), input_name(input_path));
+    let (video_ids, unresolved) = resolve_input_entries(api, &entries, max).await;
+    info!("Found {} videos to process", video_ids.len());
+    Ok((video_ids, unresolved))
+}
+
+async fn playlist_video_ids(
+    api: &YouTubeTranscript,
+    url: &str,
+    max: Option<usize>,
+) -> Result<Vec<String>, TranscriptError> {
+    let playlist_id = YouTubeTranscript::extract_playlist_id(url)?;
+    info!("Fetching video IDs from playlist: {}", playlist_id);
+    let video_ids = api.get_playlist_video_ids(&playlist_id).await?;
+    info!("Found {} videos in playlist", video_ids.len());
+    Ok(limit_videos(video_ids, max))
+}
+
+/// Apply `--max` to the videos of a playlist or channel
+fn limit_videos(mut video_ids: Vec<String>, max: Option<usize>) -> Vec<String> {
+    if let Some(max) = max {
+        if max < video_ids.len() {
+            info!("Processing first {} videos (limited by --max)", max);
+            video_ids.truncate(max);
+        }
+    }
+    video_ids
+}
+
+/// Process several videos, reporting failures and moving on to the next video.
+/// Batch input entries that could not be resolved count as failed.
+async fn process_videos(
+    api: &YouTubeTranscript,
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    video_ids: &[String],
+    unresolved: &[UnresolvedEntry],
+) -> Result<i32, TranscriptError> {
+    let total = video_ids.len();
+    let mut total_usage = UsageSummary::default();
+    let mut outputs = OutputState::new(options)?;
+    let mut report = RunReport::new();
+    for entry in unresolved {
+        report.record_unresolved(entry);
+    }
+    let progress = batch_progress(total);
+    for (index, video_id) in video_ids.iter().enumerate() {
+        progress.set_message(video_id.clone());
+        info!("[{}/{}] Processing video: {}", index + 1, total, video_id);
+        let started = Instant::now();
+        let result =
+            process_single_video(api, options, chatgpt, &mut outputs, video_id, Some(index + 1)).await;
+        report.record(video_id, started, &result);
+        progress.inc(1);
+
+        if let Some(chatgpt) = chatgpt {
+            let usage = chatgpt.take_usage();
+            if !usage.is_empty() {
+                info!("ChatGPT usage for {}: {}", video_id, usage);
+            }
+            total_usage.add(&usage);
+        }
+
+        match result {
+            // Stop the whole batch rather than skipping ahead to cheaper videos
+            Err(e @ TranscriptError::BudgetExceeded(_)) => {
+                progress.finish_and_clear();
+                info!("Total ChatGPT usage: {}", total_usage);
+                if let Some(path) = &options.report {
+                    report.write(path, error_exit_code(&e))?;
+                }
+                return Err(e);
+            }
+            Err(e) => {
+                error!("Error processing video {}: {}", video_id, e.full_message());
+                // Continue with next video instead of failing completely
+                continue;
+            }
+            Ok(_) => {}
+        }
+    }
+
+    progress.finish_and_clear();
+    if chatgpt.is_some() {
+        info!("Total ChatGPT usage: {}", total_usage);
+    }
+
+    let (succeeded, skipped, failed) = report.counts();
+    info!(
+        "Done: {} succeeded, {} skipped, {} failed",
+        succeeded, skipped, failed
+    );
+
+    let code = report.exit_code();
+    if let Some(path) = &options.report {
+        report.write(path, code)?;
+    }
+    Ok(code)
+}
+
+/// How a video was handled, when it did not fail
+enum VideoOutcome {
+    /// The transcript was written, to `output` or to stdout if that is `None`
+    Written { track: Track, output: Option<String> },
+    /// Several tracks were written, with `--all-languages`
+    WrittenAll(Vec<WrittenTrack>),
+    Skipped { reason: String },
+}
+
+/// The transcript track that was fetched for a video
+#[derive(Clone, Serialize)]
+struct Track {
+    language: String,
+    language_code: String,
+    is_generated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translation_origin: Option<TranslationOrigin>,
+}
+
+/// One of the tracks written for a video with `--all-languages`
+#[derive(Clone, Serialize)]
+struct WrittenTrack {
+    #[serde(flatten)]
+    track: Track,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum VideoStatus {
+    Ok,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize)]
+struct VideoReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    video_id: Option<String>,
+    /// The batch input entry, if it could not be resolved to any videos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    status: VideoStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    track: Option<Track>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tracks: Vec<WrittenTrack>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorReport>,
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    /// Stable error code, see `TranscriptError::code`
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stage: Option<Stage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    retryable: bool,
+    #[serde(skip)]
+    exit_code: i32,
+}
+
+impl From<&TranscriptError> for ErrorReport {
+    fn from(error: &TranscriptError) -> Self {
+        Self {
+            code: error.code(),
+            message: error.full_message(),
+            stage: error.stage(),
+            status: error.status(),
+            retryable: error.is_retryable(),
+            exit_code: error_exit_code(error),
+        }
+    }
+}
+
+/// Per-video results of a run, written with `--report`
+struct RunReport {
+    started: Instant,
+    videos: Vec<VideoReport>,
+}
+
+impl RunReport {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            videos: Vec::new(),
+        }
+    }
+
+    fn record(
+        &mut self,
+        video_id: &str,
+        started: Instant,
+        result: &Result<VideoOutcome, TranscriptError>,
+    ) {
+        let mut report = VideoReport {
+            video_id: Some(video_id.to_string()),
+            entry: None,
+            status: VideoStatus::Ok,
+            track: None,
+            output: None,
+            tracks: Vec::new(),
+            reason: None,
+            error: None,
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+
+        match result {
+            Ok(VideoOutcome::Written { track, output }) => {
+                report.track = Some(track.clone());
+                report.output = output.clone();
+            }
+            Ok(VideoOutcome::WrittenAll(tracks)) => {
+                report.tracks = tracks.clone();
+            }
+            Ok(VideoOutcome::Skipped { reason }) => {
+                report.status = VideoStatus::Skipped;
+                report.reason = Some(reason.clone());
+            }
+            Err(e) => {
+                report.status = VideoStatus::Failed;
+                report.error = Some(ErrorReport::from(e));
+            }
+        }
+
+        self.videos.push(report);
+    }
+
+    /// Record a batch input entry that could not be resolved as a failure
+    fn record_unresolved(&mut self, unresolved: &UnresolvedEntry) {
+        self.videos.push(VideoReport {
+            video_id: None,
+            entry: Some(unresolved.entry.clone()),
+            status: VideoStatus::Failed,
+            track: None,
+            output: None,
+            tracks: Vec::new(),
+            reason: None,
+            error: Some(ErrorReport::from(&unresolved.error)),
+            duration_ms: 0,
+        });
+    }
+
+    /// Number of (succeeded, skipped, failed) videos
+    fn counts(&self) -> (usize, usize, usize) {
+        let count = |status: VideoStatus| self.videos.iter().filter(|v| v.status == status).count();
+        let skipped = count(VideoStatus::Skipped);
+        let failed = count(VideoStatus::Failed);
+        (self.videos.len() - skipped - failed, skipped, failed)
+    }
+
+    /// 0 if nothing failed, the error's own code if the only video failed,
+    /// otherwise the partial or total failure code
+    fn exit_code(&self) -> i32 {
+        let failures: Vec<&ErrorReport> = self.videos.iter().filter_map(|v| v.error.as_ref()).collect();
+        match failures.as_slice() {
+            [] => EXIT_SUCCESS,
+            [only] if self.videos.len() == 1 => only.exit_code,
+            _ if failures.len() == self.videos.len() => EXIT_TOTAL_FAILURE,
+            _ => EXIT_PARTIAL_FAILURE,
+        }
+    }
+
+    fn write(&self, path: &str, exit_code: i32) -> Result<(), TranscriptError> {
+        #[derive(Serialize)]
+        struct ReportFile<'a> {
+            exit_code: i32,
+            duration_ms: u64,
+            succeeded: usize,
+            skipped: usize,
+            failed: usize,
+            videos: &'a [VideoReport],
+        }
+
+        let (succeeded, skipped, failed) = self.counts();
+        let file = ReportFile {
+            exit_code,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            succeeded,
+            skipped,
+            failed,
+            videos: &self.videos,
         };
-        let output_path = format!("{}.{}", sanitized_title, extension);
-        OutputDestination::File(output_path)
-    } else if video_index.is_some() {
-        // Playlist mode without -o or -n: use video_id as filename
-        let extension = match args.format.to_lowercase().as_str() {
-            "json" => "json",
-            "srt" => "srt",
-            "markdown" | "md" => "md",
-            "text" | "txt" => "txt",
-            _ => "txt",
+        let json = serde_json::to_string_pretty(&file)?;
+        fs::write(path, json + "\n").map_err(|e| {
+            TranscriptError::io(format!("Failed to write report {}", path), e)
+        })
+    }
+}
+
+/// An entry of a batch input file
+#[derive(Debug, PartialEq)]
+enum InputEntry {
+    Video(String),
+    Playlist(String),
+    Channel(String),
+}
+
+fn classify_input_entry(entry: &str) -> Result<InputEntry, TranscriptError> {
+    // Watch URLs may carry a list= parameter, so videos are checked first
+    if let Ok(video_id) = YouTubeTranscript::extract_video_id(entry) {
+        return Ok(InputEntry::Video(video_id));
+    }
+    if let Ok(channel_url) = YouTubeTranscript::extract_channel_url(entry) {
+        return Ok(InputEntry::Channel(channel_url));
+    }
+    YouTubeTranscript::extract_playlist_id(entry)
+        .map(InputEntry::Playlist)
+        .map_err(|_| {
+            TranscriptError::InvalidVideoId(format!(
+                "{} (not a video, playlist or channel URL)",
+                entry
+            ))
+        })
+}
+
+/// A batch input entry that could not be resolved to any videos
+struct UnresolvedEntry {
+    entry: String,
+    error: TranscriptError,
+}
+
+/// Expand batch input entries into the video IDs to process, in input order,
+/// along with the entries that could not be resolved
+async fn resolve_input_entries(
+    api: &YouTubeTranscript,
+    entries: &[String],
+    max: Option<usize>,
+) -> (Vec<String>, Vec<UnresolvedEntry>) {
+    let mut video_ids: Vec<String> = Vec::new();
+    let mut unresolved = Vec::new();
+
+    for entry in entries {
+        let resolved = match classify_input_entry(entry) {
+            Ok(InputEntry::Video(video_id)) => Ok(vec![video_id]),
+            Ok(InputEntry::Playlist(playlist_id)) => {
+                info!("Fetching video IDs from playlist: {}", playlist_id);
+                api.get_playlist_video_ids(&playlist_id)
+                    .await
+                    .map(|ids| limit_videos(ids, max))
+            }
+            Ok(InputEntry::Channel(channel_url)) => {
+                info!("Fetching video IDs from channel: {}", channel_url);
+                api.get_channel_video_ids(&channel_url)
+                    .await
+                    .map(|ids| limit_videos(ids, max))
+            }
+            Err(e) => Err(e),
         };
-        let output_path = format!("{}.{}", video_id, extension);
-        OutputDestination::File(output_path)
+
+        match resolved {
+            Ok(ids) => {
+                for id in ids {
+                    if !video_ids.contains(&id) {
+                        video_ids.push(id);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to resolve {}: {}", entry, e.full_message());
+                unresolved.push(UnresolvedEntry {
+                    entry: entry.clone(),
+                    error: e,
+                });
+            }
+        }
+    }
+
+    (video_ids, unresolved)
+}
+
+fn input_name(path: &str) -> &str {
+    if path == "-" {
+        "stdin"
+    } else {
+        path
+    }
+}
+
+/// Read batch input entries from a file, or from stdin if `path` is `-`
+fn read_input_entries(path: &str) -> Result<Vec<String>, TranscriptError> {
+    let content = if path == "-" {
+        io::read_to_string(io::stdin())?
+    } else {
+        std::fs::read_to_string(path).map_err(|e| {
+            TranscriptError::io(format!("Failed to read input file {}", path), e)
+        })?
+    };
+    parse_input_entries(&content)
+}
+
+/// Parse batch input: either one entry per line, with `#` comments and blank
+/// lines ignored, or CSV whose header has a `url` column.
+fn parse_input_entries(content: &str) -> Result<Vec<String>, TranscriptError> {
+    let mut lines = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+    let is_csv = lines.next().is_some_and(|header| {
+        header
+            .split(',')
+            .any(|column| column.trim().trim_matches('"').eq_ignore_ascii_case("url"))
+    });
+
+    if !is_csv {
+        return Ok(content
+            .lines()
+            .map(|line| {
+                // Strip trailing comments; URLs themselves never contain " #"
+                let line = line.split(" #").next().unwrap_or_default();
+                line.trim().to_string()
+            })
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let url_column = reader
+        .headers()
+        .map_err(|e| TranscriptError::io("Failed to read CSV header", e.into()))?
+        .iter()
+        .position(|column| column.eq_ignore_ascii_case("url"))
+        .ok_or_else(|| TranscriptError::InvalidInput("CSV input has no url column".to_string()))?;
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record =
+            record.map_err(|e| TranscriptError::io("Failed to read CSV input", e.into()))?;
+        if let Some(url) = record.get(url_column).filter(|url| !url.is_empty()) {
+            entries.push(url.to_string());
+        }
+    }
+
+    Ok(entries)
+}
+
+fn create_chatgpt(
+    openai_key: Option<String>,
+    no_cache: bool,
+    max_cost: Option<f64>,
+) -> Result<ChatGPT, TranscriptError> {
+    let mut chatgpt = ChatGPT::new(openai_key)?;
+    if !no_cache {
+        if let Some(dir) = LlmCache::default_dir() {
+            chatgpt = chatgpt.with_cache(LlmCache::new(dir));
+        }
+    }
+    if let Some(max_cost) = max_cost {
+        chatgpt = chatgpt.with_max_cost(max_cost);
+    }
+    Ok(chatgpt)
+}
+
+/// The ChatGPT client for `--cleanup`, `--chapters` and `--llm-fallback`, if any of them is used
+fn fetch_chatgpt(options: &FetchOptions) -> Result<Option<ChatGPT>, TranscriptError> {
+    if options.cleanup || options.chapters || options.llm_fallback {
+        create_chatgpt(options.openai_key.clone(), options.no_cache, options.max_cost).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn print_usage(chatgpt: &ChatGPT) {
+    let usage = chatgpt.take_usage();
+    if !usage.is_empty() {
+        info!("ChatGPT usage: {}", usage);
+    }
+}
+
+async fn run_ask(args: AskArgs) -> Result<(), TranscriptError> {
+    let api = youtube_client(args.delay);
+    let video_id = YouTubeTranscript::extract_video_id(&args.video)?;
+
+    info!("Fetching transcript for video: {}", video_id);
+    let lang_codes: Option<Vec<&str>> = args
+        .languages
+        .as_ref()
+        .map(|v| v.iter().map(|s| s.as_str()).collect());
+    let transcript = api.fetch_transcript(&video_id, lang_codes).await?;
+
+    // Only the best matching parts of the transcript are sent to ChatGPT
+    let windows = search::relevant_windows(&transcript.transcript, &args.question, args.excerpts);
+    if windows.is_empty() {
+        warn!("No part of the transcript matches the question");
+        return Ok(());
+    }
+
+    info!("Asking ChatGPT using {} transcript excerpt(s)...", windows.len());
+    let chatgpt = create_chatgpt(args.openai_key, args.no_cache, None)?;
+    let answer = chatgpt.answer_question(&args.question, &windows).await?;
+    println!("{}", answer);
+    print_usage(&chatgpt);
+
+    let citations = search::verify_citations(&answer, &transcript.transcript);
+    for citation in citations.iter().filter(|c| !c.is_verified()) {
+        warn!(
+            "Cited timestamp [{}] does not match any line of the transcript",
+            citation.label
+        );
+    }
+
+    let verified: Vec<_> = citations.iter().filter(|c| c.is_verified()).collect();
+    if !verified.is_empty() {
+        println!("\nSources:");
+        for citation in verified {
+            println!(
+                "  [{}] {} (https://www.youtube.com/watch?v={}&t={}s)",
+                citation.label,
+                citation.text.as_deref().unwrap_or_default(),
+                video_id,
+                citation.seconds
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_search(args: SearchArgs) -> Result<(), TranscriptError> {
+    let api = youtube_client(args.delay);
+    let video_id = YouTubeTranscript::extract_video_id(&args.video)?;
+
+    info!("Fetching transcript for video: {}", video_id);
+    let lang_codes: Option<Vec<&str>> = args
+        .languages
+        .as_ref()
+        .map(|v| v.iter().map(|s| s.as_str()).collect());
+    let transcript = api.fetch_transcript(&video_id, lang_codes).await?;
+
+    let windows = search::relevant_windows(&transcript.transcript, &args.query, args.results);
+    if windows.is_empty() {
+        warn!("No part of the transcript matches the query");
+        return Ok(());
+    }
+
+    for (index, window) in windows.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!(
+            "{} - {} (https://www.youtube.com/watch?v={}&t={}s)",
+            chapters::format_timestamp(window.start),
+            chapters::format_timestamp(window.end),
+            video_id,
+            window.start as u64
+        );
+        println!("{}", search::format_window(window));
+    }
+
+    Ok(())
+}
+
+async fn run_summarize(args: SummarizeArgs) -> Result<(), TranscriptError> {
+    let api = youtube_client(args.delay);
+    let video_id = YouTubeTranscript::extract_video_id(&args.video)?;
+    let chatgpt = create_chatgpt(args.openai_key, args.no_cache, args.max_cost)?;
+
+    info!("Fetching transcript for video: {}", video_id);
+    let lang_codes: Option<Vec<&str>> = args
+        .languages
+        .as_ref()
+        .map(|v| v.iter().map(|s| s.as_str()).collect());
+    let transcript = api.fetch_transcript(&video_id, lang_codes).await?;
+
+    info!("Summarizing transcript with ChatGPT...");
+    let transcript_text = transcript
+        .transcript
+        .iter()
+        .map(|item| item.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let summary = chatgpt.summarize_transcript(&transcript_text).await?;
+    print_usage(&chatgpt);
+
+    let dest = match args.output {
+        Some(path) => OutputDestination::File(path),
+        None => OutputDestination::Stdout,
+    };
+    writeln!(dest.writer()?, "{}", summary)?;
+    Ok(())
+}
+
+fn run_convert(args: ConvertArgs) -> Result<(), TranscriptError> {
+    let content = if args.input == "-" {
+        io::read_to_string(io::stdin())?
     } else {
-        OutputDestination::Stdout
+        fs::read_to_string(&args.input).map_err(|e| {
+            TranscriptError::io(format!("Failed to read transcript {}", args.input), e)
+        })?
+    };
+    let items = parse_saved_transcript(&content)?;
+
+    let dest = match args.output {
+        Some(path) => OutputDestination::File(path),
+        None => OutputDestination::Stdout,
     };
+    output_items(&args.format, args.timestamps, &items, &dest, None, None)
+}
+
+/// Transcript items from a saved transcript: either the item array written
+/// by `-f json` or a full transcript object as returned by `ytt serve`
+fn parse_saved_transcript(content: &str) -> Result<Vec<TranscriptItem>, TranscriptError> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SavedTranscript {
+        Items(Vec<TranscriptItem>),
+        Response(TranscriptResponse),
+    }
+
+    match serde_json::from_str(content) {
+        Ok(SavedTranscript::Items(items)) => Ok(items),
+        Ok(SavedTranscript::Response(response)) => Ok(response.transcript),
+        Err(e) => Err(TranscriptError::json(None, "Expected a transcript written with -f json", e)),
+    }
+}
+
+fn run_cache(args: CacheArgs) -> Result<(), TranscriptError> {
+    let dir = LlmCache::default_dir()
+        .ok_or_else(|| TranscriptError::ConfigError("Could not determine the cache directory".to_string()))?;
+    let cache = LlmCache::new(dir);
+
+    match args.action {
+        CacheAction::Path => println!("{}", cache.dir().display()),
+        CacheAction::Stats => {
+            let stats = cache.stats()?;
+            println!("Location: {}", cache.dir().display());
+            println!("Entries:  {}", stats.entries);
+            println!("Size:     {:.1} KiB", stats.bytes as f64 / 1024.0);
+        }
+        CacheAction::Clear => {
+            let removed = cache.clear()?;
+            info!("Removed {} cached responses from {}", removed, cache.dir().display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Largest request head `ytt serve` reads before giving up on a request
+const MAX_REQUEST_HEAD: usize = 16 * 1024;
+
+/// How long `ytt serve` waits for a client to send its request head
+const REQUEST_HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections `ytt serve` handles at once; further ones wait to be accepted
+const MAX_CONNECTIONS: usize = 16;
+
+/// Serve transcripts over HTTP until the process is stopped.
+///
+/// Routes:
+/// - `GET /health`
+/// - `GET /transcripts/<video>?languages=en,de&translate=es&format=json|text`
+async fn run_serve(args: ServeArgs) -> Result<(), TranscriptError> {
+    let listener = TcpListener::bind(&args.bind)
+        .await
+        .map_err(|e| TranscriptError::io(format!("Failed to listen on {}", args.bind), e))?;
+    info!("Serving transcripts on http://{}", listener.local_addr()?);
+
+    let api = Arc::new(youtube_client(args.delay));
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    loop {
+        let permit = Arc::clone(&connections)
+            .acquire_owned()
+            .await
+            .expect("the connection semaphore is never closed");
+        let (stream, peer) = listener.accept().await?;
+        let api = Arc::clone(&api);
+        tokio::spawn(async move {
+            if let Err(e) = serve_connection(&api, stream).await {
+                debug!("Connection from {} failed: {}", peer, e);
+            }
+            drop(permit);
+        });
+    }
+}
+
+/// Answer a single request on `stream`. Connections are not kept alive.
+async fn serve_connection(api: &YouTubeTranscript, mut stream: TcpStream) -> io::Result<()> {
+    let head = match tokio::time::timeout(REQUEST_HEAD_TIMEOUT, read_request_head(&mut stream)).await {
+        Ok(Ok(Some(head))) => head,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => {
+            let response = HttpResponse::error(408, "request_timeout", "No request received in time");
+            return stream.write_all(&response.to_bytes()).await;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&head);
+    let request_line = head.lines().next().unwrap_or_default();
+    let response = handle_request(api, request_line).await;
+    info!("{} -> {}", request_line, response.status);
+
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read up to the end of the request head, `None` if the client went away
+/// or sent a head larger than [`MAX_REQUEST_HEAD`] (which is answered here)
+async fn read_request_head(stream: &mut TcpStream) -> io::Result<Option<Vec<u8>>> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_HEAD {
+            let response = HttpResponse::error(431, "request_too_large", "Request head too large");
+            stream.write_all(&response.to_bytes()).await?;
+            return Ok(None);
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        head.extend_from_slice(&chunk[..read]);
+    }
+    Ok(Some(head))
+}
+
+struct HttpResponse {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl HttpResponse {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string(body).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, code: &str, message: &str) -> Self {
+        Self::json(
+            status,
+            &serde_json::json!({ "error": { "code": code, "message": message } }),
+        )
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            431 => "Request Header Fields Too Large",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        };
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len(),
+            self.body
+        )
+        .into_bytes()
+    }
+}
+
+impl From<&TranscriptError> for HttpResponse {
+    fn from(error: &TranscriptError) -> Self {
+        Self::json(
+            http_status(error),
+            &serde_json::json!({ "error": ErrorReport::from(error) }),
+        )
+    }
+}
+
+/// HTTP status for a failed transcript request
+fn http_status(error: &TranscriptError) -> u16 {
+    match error_exit_code(error) {
+        EXIT_USAGE => 400,
+        EXIT_NO_TRANSCRIPT | EXIT_VIDEO_UNAVAILABLE => 404,
+        EXIT_BLOCKED => 503,
+        EXIT_YOUTUBE_ERROR => 502,
+        _ => 500,
+    }
+}
+
+async fn handle_request(api: &YouTubeTranscript, request_line: &str) -> HttpResponse {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return HttpResponse::error(400, "bad_request", "Malformed request line");
+    };
+    if method != "GET" {
+        return HttpResponse::error(405, "method_not_allowed", "Only GET is supported");
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path == "/health" {
+        return HttpResponse::json(200, &serde_json::json!({ "status": "ok" }));
+    }
+    let Some(video) = path.strip_prefix("/transcripts/").filter(|v| !v.is_empty() && !v.contains('/')) else {
+        return HttpResponse::error(404, "not_found", &format!("No route for {}", path));
+    };
+
+    let mut languages: Vec<String> = Vec::new();
+    let mut translate = None;
+    let mut format = "json".to_string();
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match key.as_ref() {
+            "languages" => languages.extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(String::from),
+            ),
+            "translate" => translate = Some(value.into_owned()),
+            "format" => format = value.into_owned(),
+            _ => {}
+        }
+    }
+    if !matches!(format.as_str(), "json" | "text") {
+        return HttpResponse::error(400, "bad_request", "format must be json or text");
+    }
+
+    // The segment may be a percent-encoded URL, e.g. https%3A%2F%2Fyoutu.be%2F...
+    let video = percent_encoding::percent_decode_str(video).decode_utf8_lossy();
+    let result = async {
+        let video_id = YouTubeTranscript::extract_video_id(&video)?;
+        let lang_codes: Vec<&str> = languages.iter().map(String::as_str).collect();
+        match &translate {
+            Some(target) => {
+                let source_langs = if lang_codes.is_empty() { vec!["en"] } else { lang_codes };
+                api.translate_transcript(&video_id, &source_langs, target).await
+            }
+            None => {
+                api.fetch_transcript(&video_id, Some(lang_codes).filter(|l| !l.is_empty()))
+                    .await
+            }
+        }
+    }
+    .await;
+
+    match result {
+        Ok(transcript) if format == "text" => HttpResponse {
+            status: 200,
+            content_type: "text/plain",
+            body: transcript
+                .transcript
+                .iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        },
+        Ok(transcript) => HttpResponse::json(200, &transcript),
+        Err(e) => {
+            warn!("Failed to serve transcript for {}: {}", video, e.full_message());
+            HttpResponse::from(&e)
+        }
+    }
+}
+
+/// A choice offered by `--interactive`
+#[derive(Debug)]
+enum TrackChoice<'a> {
+    Track(&'a TranscriptInfo),
+    /// `source` translated by YouTube to `language`
+    Translation {
+        source: &'a TranscriptInfo,
+        language: &'a TranslationLanguage,
+    },
+}
+
+/// Every track of `list`, followed by the translations of the first translatable track
+fn track_choices(list: &TranscriptList) -> Vec<TrackChoice<'_>> {
+    let tracks = list.all_transcripts();
+    let mut choices: Vec<TrackChoice> = tracks.iter().map(|track| TrackChoice::Track(track)).collect();
+    if let Some(source) = tracks.into_iter().find(|track| track.is_translatable) {
+        choices.extend(
+            list.sorted_translation_languages()
+                .into_iter()
+                .map(|language| TrackChoice::Translation { source, language }),
+        );
+    }
+    choices
+}
+
+fn write_track_menu(out: &mut impl Write, list: &TranscriptList, choices: &[TrackChoice]) -> io::Result<()> {
+    match &list.title {
+        Some(title) => writeln!(out, "Transcripts for {} ({}):", title, list.video_id)?,
+        None => writeln!(out, "Transcripts for {}:", list.video_id)?,
+    }
+
+    let width = choices.len().to_string().len();
+    let mut heading = None;
+    for (index, choice) in choices.iter().enumerate() {
+        let (section, label) = match choice {
+            TrackChoice::Track(track) if track.is_generated => {
+                ("Auto-generated".to_string(), format!("{} ({})", track.language, track.language_code))
+            }
+            TrackChoice::Track(track) => {
+                ("Manually created".to_string(), format!("{} ({})", track.language, track.language_code))
+            }
+            TrackChoice::Translation { source, language } => (
+                format!("Translated from {} ({})", source.language, source.language_code),
+                format!("{} ({})", language.language, language.language_code),
+            ),
+        };
+        if heading.as_ref() != Some(&section) {
+            writeln!(out, "\n{}:", section)?;
+            heading = Some(section);
+        }
+        writeln!(out, "  {:>width$}) {}", index + 1, label, width = width)?;
+    }
+    Ok(())
+}
+
+/// Ask for a menu number until a valid one is entered, returning its 0-based index
+fn prompt_choice(input: &mut impl BufRead, out: &mut impl Write, count: usize) -> Result<usize, TranscriptError> {
+    loop {
+        write!(out, "\nChoose a transcript [1-{}]: ", count)?;
+        out.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(TranscriptError::InvalidInput("No transcript selected".to_string()));
+        }
+        match line.trim().parse::<usize>() {
+            Ok(number) if (1..=count).contains(&number) => return Ok(number - 1),
+            _ => writeln!(out, "Please enter a number between 1 and {}", count)?,
+        }
+    }
+}
+
+/// List the tracks of a video, let the user pick one on the terminal and
+/// fetch it from the same list
+async fn fetch_interactively(
+    api: &YouTubeTranscript,
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    outputs: &mut OutputState,
+    video_id: &str,
+) -> Result<VideoOutcome, TranscriptError> {
+    let transcript_list = api.list_transcripts(video_id).await?;
+    let choices = track_choices(&transcript_list);
+    if choices.is_empty() {
+        return Err(TranscriptError::NoTranscriptFound(video_id.to_string(), Vec::new()));
+    }
+
+    // The menu goes to stderr so stdout only ever carries the transcript
+    let mut stderr = io::stderr();
+    write_track_menu(&mut stderr, &transcript_list, &choices)?;
+    let choice = prompt_choice(&mut io::stdin().lock(), &mut stderr, choices.len())?;
+
+    let transcript = match choices[choice] {
+        TrackChoice::Track(track) => transcript_list.fetch(api, track).await?,
+        TrackChoice::Translation { source, language } => {
+            let translation = source.translate(&language.language_code)?;
+            transcript_list.fetch(api, &translation).await?
+        }
+    };
+    deliver_transcript(options, chatgpt, outputs, transcript, None).await
+}
+
+async fn process_single_video(
+    api: &YouTubeTranscript,
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    outputs: &mut OutputState,
+    video_id: &str,
+    video_index: Option<usize>,
+) -> Result<VideoOutcome, TranscriptError> {
+    if let Some(kinds) = options.all_languages {
+        return process_all_languages(api, options, chatgpt, outputs, video_id, video_index, kinds).await;
+    }
+
+    if let Some(archive) = &outputs.archive {
+        let format = output_extension(&options.format);
+        // Translations are recorded in exactly the requested language
+        let match_region = options.match_region && options.translate.is_none();
+        if let Some(language) = archive.find(video_id, &requested_languages(options), format, match_region) {
+            let reason = format!("already in archive ({} {})", language, format);
+            info!("Skipping {}: {}", video_id, reason);
+            return Ok(VideoOutcome::Skipped { reason });
+        }
+    }
+
+    if video_index.is_none() {
+        info!("Fetching transcript for video: {}", video_id);
+    }
+
+    let transcript = if let Some(target_lang) = &options.translate {
+        let source_langs: Vec<&str> = options
+            .languages
+            .as_ref()
+            .map(|v| v.iter().map(|s| s.as_str()).collect())
+            .unwrap_or_else(|| vec!["en"]);
+        match chatgpt {
+            Some(chatgpt) if options.llm_fallback => {
+                let transcript = api
+                    .translate_transcript_with_fallback(video_id, &source_langs, target_lang, chatgpt)
+                    .await?;
+                if transcript.translation_origin == Some(TranslationOrigin::Llm) {
+                    warn!(
+                        "YouTube cannot translate this transcript to {}; translated with ChatGPT",
+                        target_lang
+                    );
+                }
+                transcript
+            }
+            _ => {
+                api.translate_transcript(video_id, &source_langs, target_lang)
+                    .await?
+            }
+        }
+    } else {
+        let lang_codes: Vec<&str> = options
+            .languages
+            .as_ref()
+            .map(|v| v.iter().map(|s| s.as_str()).collect())
+            .unwrap_or_else(|| vec!["en"]);
+        let transcript = api
+            .fetch_transcript_with_policy(video_id, &lang_codes, &language_policy(options))
+            .await?;
+        if transcript.translation_origin.is_some() {
+            info!(
+                "No transcript in {} for {}; translated to {} by YouTube",
+                lang_codes.join(", "),
+                video_id,
+                transcript.language_code
+            );
+        }
+        transcript
+    };
+
+    deliver_transcript(options, chatgpt, outputs, transcript, video_index).await
+}
+
+/// How to pick a track for `--languages`
+fn language_policy(options: &FetchOptions) -> LanguagePolicy {
+    let preference = if options.manual_first {
+        TrackPreference::ManualFirst
+    } else if options.prefer_generated {
+        TrackPreference::GeneratedPerLanguage
+    } else {
+        TrackPreference::ManualPerLanguage
+    };
+    LanguagePolicy {
+        match_region: options.match_region,
+        preference,
+        translate_fallback: options.translate_fallback,
+    }
+}
+
+/// Fetch and write every track `--all-languages` selects, listing the video once
+async fn process_all_languages(
+    api: &YouTubeTranscript,
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    outputs: &mut OutputState,
+    video_id: &str,
+    video_index: Option<usize>,
+    kinds: TrackKinds,
+) -> Result<VideoOutcome, TranscriptError> {
+    if video_index.is_none() {
+        info!("Fetching all transcripts for video: {}", video_id);
+    }
+
+    let transcript_list = api.list_transcripts(video_id).await?;
+    let filter = TranscriptFilter {
+        manually_created: kinds != TrackKinds::Generated,
+        generated: kinds != TrackKinds::Manual,
+        translations: options.translate.iter().cloned().collect(),
+    };
+    // A track that cannot be selected or fetched is reported and the others
+    // are still written; the video only fails if none of them could be
+    let mut failure = None;
+    let mut tracks = Vec::new();
+    for selected in transcript_list.select(&filter) {
+        match selected {
+            Ok(track) => tracks.push(track),
+            Err(e) => {
+                error!("Skipping a track of {}: {}", video_id, e.full_message());
+                failure = Some(e);
+            }
+        }
+    }
+    if tracks.is_empty() {
+        return Err(failure.unwrap_or_else(|| TranscriptError::NoTranscriptFound(video_id.to_string(), Vec::new())));
+    }
+
+    if let Some(archive) = &outputs.archive {
+        let format = output_extension(&options.format);
+        let total = tracks.len();
+        tracks.retain(|track| {
+            !archive.contains(video_id, &track_key(&track.language_code, track.is_generated), format)
+        });
+        if tracks.is_empty() {
+            let reason = format!("all {} tracks already in archive", total);
+            info!("Skipping {}: {}", video_id, reason);
+            return Ok(VideoOutcome::Skipped { reason });
+        }
+    }
+
+    let mut written = Vec::new();
+    let mut skipped = 0;
+    for track in &tracks {
+        let delivered = match transcript_list.fetch(api, track).await {
+            Ok(transcript) => deliver_transcript(options, chatgpt, outputs, transcript, video_index).await,
+            Err(e) => Err(e),
+        };
+        match delivered {
+            Ok(VideoOutcome::Written { track, output }) => written.push(WrittenTrack { track, output }),
+            Ok(_) => skipped += 1,
+            // Stop before spending more than the budget on the other tracks
+            Err(e @ TranscriptError::BudgetExceeded(_)) => return Err(e),
+            Err(e) => {
+                error!(
+                    "Failed to write the {} track of {}: {}",
+                    track_key(&track.language_code, track.is_generated),
+                    video_id,
+                    e.full_message()
+                );
+                failure = Some(e);
+            }
+        }
+    }
+
+    if written.is_empty() && skipped == 0 {
+        if let Some(e) = failure {
+            return Err(e);
+        }
+    }
+    if written.is_empty() {
+        return Ok(VideoOutcome::Skipped {
+            reason: "all output files already exist".to_string(),
+        });
+    }
+    Ok(VideoOutcome::WrittenAll(written))
+}
+
+/// How `--all-languages` names a track in file names and the archive: its
+/// language code, followed by `.auto` if it is auto-generated, so a language's
+/// manually created and auto-generated tracks are kept apart
+fn track_key(language_code: &str, is_generated: bool) -> String {
+    if is_generated {
+        format!("{}.auto", language_code)
+    } else {
+        language_code.to_string()
+    }
+}
+
+/// Write a fetched transcript to its output and record it in the archive
+async fn deliver_transcript(
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    outputs: &mut OutputState,
+    transcript: TranscriptResponse,
+    video_index: Option<usize>,
+) -> Result<VideoOutcome, TranscriptError> {
+    let video_id = transcript.video_id.clone();
+    let output_path = if options.all_languages.is_some() {
+        Some(outputs.language_path_for(options, &transcript, video_index)?)
+    } else {
+        outputs.path_for(options, &transcript, video_index)?
+    };
+    if let Some(path) = output_path.as_ref().filter(|path| options.skip_existing && path.exists()) {
+        let reason = format!("{} already exists", path.display());
+        info!("Skipping {}: {}", video_id, reason);
+        return Ok(VideoOutcome::Skipped { reason });
+    }
+    let output_dest = match output_path {
+        Some(path) => outputs.claim(path),
+        None => OutputDestination::Stdout,
+    };
+
+    let track = Track {
+        language: transcript.language.clone(),
+        language_code: transcript.language_code.clone(),
+        is_generated: transcript.is_generated,
+        translation_origin: transcript.translation_origin,
+    };
+    write_transcript(options, chatgpt, transcript, &output_dest, video_index).await?;
+
+    if let Some(archive) = outputs.archive.as_mut() {
+        let language = match options.all_languages {
+            Some(_) => track_key(&track.language_code, track.is_generated),
+            None => track.language_code.clone(),
+        };
+        archive.record(&video_id, &language, output_extension(&options.format))?;
+    }
+
+    let output = match output_dest {
+        OutputDestination::File(path) => Some(path),
+        OutputDestination::Stdout => None,
+    };
+    Ok(VideoOutcome::Written { track, output })
+}
+
+/// Produce the requested output for a fetched transcript, running ChatGPT
+/// cleanup or chapter generation first if requested
+async fn write_transcript(
+    options: &FetchOptions,
+    chatgpt: Option<&ChatGPT>,
+    transcript: TranscriptResponse,
+    output_dest: &OutputDestination,
+    video_index: Option<usize>,
+) -> Result<(), TranscriptError> {
+    let video_url = if options.url {
+        Some(format!("https://www.youtube.com/watch?v={}", transcript.video_id))
+    } else {
+        None
+    };
+    let video_title = if options.url {
+        transcript.title.as_deref()
+    } else {
+        None
+    };
+
+    // Chapters are generated from the timestamped transcript, so this has to
+    // happen before anything collapses the items
+    let chapters = match chatgpt {
+        Some(chatgpt) if options.chapters => {
+            if video_index.is_none() {
+                info!("Generating chapters with ChatGPT...");
+            }
+            Some(chatgpt.generate_chapters(&transcript.transcript).await?)
+        }
+        _ => None,
+    };
+
+    // Determine if we need markdown formatting from ChatGPT
+    let format_markdown = options.cleanup
+        && (options.format.to_lowercase() == "markdown" || options.format.to_lowercase() == "md");
+
+    // If cleanup is requested, send to ChatGPT first
+    let transcript_items = match chatgpt {
+        Some(chatgpt) if options.cleanup => {
+            if video_index.is_none() {
+                info!("Cleaning up transcript with ChatGPT...");
+            }
+            let transcript_text: String = transcript
+                .transcript
+                .iter()
+                .map(|item| item.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            // Plain text and Markdown are written while ChatGPT is still generating
+            let format = options.format.to_lowercase();
+            if !options.timestamps && matches!(format.as_str(), "text" | "txt" | "markdown" | "md") {
+                return stream_cleanup(
+                    chatgpt,
+                    &transcript_text,
+                    format_markdown,
+                    output_dest,
+                    video_url.as_deref(),
+                    video_title,
+                )
+                .await;
+            }
+
+            let cleaned_text = chatgpt
+                .cleanup_transcript(&transcript_text, format_markdown)
+                .await?;
+
+            // For cleanup, output the cleaned text directly as a single item
+            // This preserves the cleaned flow better than trying to split it back
+            vec![TranscriptItem {
+                text: cleaned_text,
+                start: transcript
+                    .transcript
+                    .first()
+                    .map(|i| i.start)
+                    .unwrap_or(0.0),
+                duration: transcript.transcript.iter().map(|i| i.duration).sum(),
+            }]
+        }
+        _ => transcript.transcript,
+    };
+
+    if let Some(chapters) = chapters {
+        match options.format.to_lowercase().as_str() {
+            "json" => output_chapters_json(&chapters, output_dest)?,
+            "markdown" | "md" => output_markdown_chapters(
+                &transcript_items,
+                &chapters,
+                output_dest,
+                options.timestamps,
+                video_url.as_deref(),
+                video_title,
+            )?,
+            _ => output_chapters_description(&chapters, output_dest)?,
+        }
+        return Ok(());
+    }
+
+    output_items(
+        &options.format,
+        options.timestamps,
+        &transcript_items,
+        output_dest,
+        video_url.as_deref(),
+        video_title,
+    )
+}
+
+/// Write transcript items in `format`, falling back to plain text for unknown formats
+fn output_items(
+    format: &str,
+    timestamps: bool,
+    items: &[TranscriptItem],
+    dest: &OutputDestination,
+    video_url: Option<&str>,
+    video_title: Option<&str>,
+) -> Result<(), TranscriptError> {
+    match format.to_lowercase().as_str() {
+        "json" => output_json(items, dest),
+        "srt" => output_srt(items, dest),
+        "text" | "txt" if timestamps => output_text(items, dest, video_url, video_title),
+        "text" | "txt" => output_text_only(items, dest, video_url, video_title),
+        "markdown" | "md" => output_markdown(items, dest, timestamps, video_url, video_title),
+        _ => {
+            warn!("Unknown format: '{}'. Using 'text' format.", format);
+            let formats: Vec<&str> = OUTPUT_FORMATS.iter().map(|(format, _)| *format).collect();
+            warn!("Supported formats: {}", formats.join(", "));
+            if timestamps {
+                output_text(items, dest, video_url, video_title)
+            } else {
+                output_text_only(items, dest, video_url, video_title)
+            }
+        }
+    }
+}
+
+/// Write the cleaned transcript to `dest` as ChatGPT generates it, with the
+/// same layout as `output_text_only` / `output_markdown` produce for cleanup.
+async fn stream_cleanup(
+    chatgpt: &ChatGPT,
+    transcript_text: &str,
+    markdown: bool,
+    dest: &OutputDestination,
+    video_url: Option<&str>,
+    video_title: Option<&str>,
+) -> Result<(), TranscriptError> {
+    let mut stream = chatgpt
+        .cleanup_transcript_stream(transcript_text, markdown)
+        .await?;
+    let mut writer = dest.writer()?;
+
+    if let (Some(url), Some(title)) = (video_url, video_title) {
+        if markdown {
+            writeln!(writer, "![{}]({})\n", title, url)?;
+        } else {
+            writeln!(writer, "{}: {}", title, url)?;
+            writeln!(writer)?;
+        }
+    }
+
+    let mut first_chunk = true;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        if first_chunk && markdown && !chunk.starts_with('#') {
+            writeln!(writer, "# Transcript\n")?;
+        }
+        first_chunk = false;
+        write!(writer, "{}", chunk)?;
+        writer.flush()?;
+    }
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+enum OutputDestination {
+    Stdout,
+    File(String),
+}
+
+/// Per-run output bookkeeping: where each transcript is written, the paths
+/// used so far (so videos in the same run never overwrite each other) and the
+/// download archive of videos processed by earlier runs.
+struct OutputState {
+    template: Option<OutputTemplate>,
+    used_paths: HashSet<PathBuf>,
+    archive: Option<DownloadArchive>,
+}
+
+impl OutputState {
+    fn new(options: &FetchOptions) -> Result<Self, TranscriptError> {
+        let template = options
+            .output_template
+            .as_deref()
+            .map(OutputTemplate::parse)
+            .transpose()?;
+        let archive = options.archive.as_deref().map(DownloadArchive::open).transpose()?;
+        if let Some(archive) = &archive {
+            info!(
+                "Using archive {} ({} entries)",
+                archive.path().display(),
+                archive.len()
+            );
+        }
+        Ok(Self {
+            template,
+            used_paths: HashSet::new(),
+            archive,
+        })
+    }
+
+    /// The file a transcript should be written to, or `None` for stdout
+    fn path_for(
+        &self,
+        options: &FetchOptions,
+        transcript: &TranscriptResponse,
+        video_index: Option<usize>,
+    ) -> Result<Option<PathBuf>, TranscriptError> {
+        let extension = output_extension(&options.format);
+
+        if let Some(template) = &self.template {
+            let fields = TemplateFields {
+                id: &transcript.video_id,
+                title: transcript.title.as_deref(),
+                channel: transcript.channel.as_deref(),
+                upload_date: transcript.upload_date.as_deref(),
+                lang: &transcript.language_code,
+                is_generated: transcript.is_generated,
+                playlist_index: video_index,
+                ext: extension,
+            };
+            // With a template, -o is the base directory the template is relative to
+            let path = match &options.output {
+                Some(base) => Path::new(base).join(template.render(&fields)),
+                None => template.render(&fields),
+            };
+            return Ok(Some(path));
+        }
+
+        let video_id = transcript.video_id.as_str();
+
+        // For playlists, if -o is a directory or -n is used, each video gets its own file
+        let path = if let Some(ref output_path) = options.output {
+            let path = Path::new(output_path);
+
+            // Check if the path is a directory:
+            // 1. If it exists and is a directory
+            // 2. If it ends with a path separator (directory-like)
+            let is_directory = if path.exists() {
+                path.is_dir()
+            } else {
+                // If path doesn't exist, check if it ends with a separator (directory-like)
+                let sep = std::path::MAIN_SEPARATOR;
+                output_path.ends_with(sep) || output_path.ends_with('/')
+            };
+
+            if is_directory && options.name {
+                // Combine directory with title as filename
+                path.join(format!("{}.{}", title_basename(transcript)?, extension))
+            } else if is_directory && video_index.is_some() {
+                // For playlist mode with directory output, use video_id as filename
+                path.join(format!("{}.{}", video_id, extension))
+            } else if video_index.is_some() {
+                // In playlist mode with a file path, append video_id
+                let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+                let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("txt");
+                let parent = path.parent().unwrap_or(Path::new("."));
+                parent.join(format!("{}_{}.{}", stem, video_id, extension))
+            } else {
+                // Use the path as-is (either it's a file path, or -n wasn't specified)
+                path.to_path_buf()
+            }
+        } else if options.name {
+            // Use video title as basename in current directory
+            PathBuf::from(format!("{}.{}", title_basename(transcript)?, extension))
+        } else if video_index.is_some() {
+            // Playlist mode without -o or -n: use video_id as filename
+            PathBuf::from(format!("{}.{}", video_id, extension))
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(path))
+    }
+
+    /// The file one of several tracks of a video is written to: the usual path
+    /// with the language code before the extension. Templates are used as they
+    /// are, since they can place `{lang}` themselves.
+    fn language_path_for(
+        &self,
+        options: &FetchOptions,
+        transcript: &TranscriptResponse,
+        video_index: Option<usize>,
+    ) -> Result<PathBuf, TranscriptError> {
+        let path = match self.path_for(options, transcript, video_index)? {
+            Some(path) if self.template.is_some() => return Ok(path),
+            Some(path) => path,
+            None => PathBuf::from(format!("{}.{}", transcript.video_id, output_extension(&options.format))),
+        };
+        Ok(with_language_code(&path, &track_key(&transcript.language_code, transcript.is_generated)))
+    }
+
+    /// Reserve `path` for this run, renaming it if another video already uses it
+    fn claim(&mut self, path: PathBuf) -> OutputDestination {
+        let path = unique_path(path, &mut self.used_paths);
+        OutputDestination::File(path.to_string_lossy().to_string())
+    }
+}
+
+/// `dir/name.ext` as `dir/name.<language_code>.ext`, see [`track_key`]
+fn with_language_code(path: &Path, language_code: &str) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    let file_name = match path.extension().and_then(|s| s.to_str()) {
+        Some(extension) => format!("{}.{}.{}", stem, language_code, extension),
+        None => format!("{}.{}", stem, language_code),
+    };
+    path.with_file_name(file_name)
+}
+
+/// Languages a transcript may be fetched in, for matching against the archive
+fn requested_languages(options: &FetchOptions) -> Vec<&str> {
+    match (&options.translate, &options.languages) {
+        (Some(target), _) => vec![target.as_str()],
+        (None, Some(languages)) => languages.iter().map(String::as_str).collect(),
+        (None, None) => vec!["en"],
+    }
+}
+
+/// File extension for an output format
+fn output_extension(format: &str) -> &'static str {
+    match format.to_lowercase().as_str() {
+        "json" => "json",
+        "srt" => "srt",
+        "markdown" | "md" => "md",
+        _ => "txt",
+    }
+}
+
+fn title_basename(transcript: &TranscriptResponse) -> Result<String, TranscriptError> {
+    let title = transcript.title.as_ref().ok_or_else(|| TranscriptError::YouTubeDataUnparsable {
+        id: transcript.video_id.clone(),
+        stage: Stage::Innertube,
+        reason: "No video title in the player response".to_string(),
+    })?;
+    Ok(sanitize_filename(title))
+}
+
+impl OutputDestination {
+    fn writer(&self) -> Result<Box<dyn Write>, TranscriptError> {
+        match self {
+            OutputDestination::Stdout => Ok(Box::new(io::stdout())),
+            OutputDestination::File(path) => {
+                if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent).map_err(|e| {
+                        TranscriptError::io(format!("Failed to create directory {}", parent.display()), e)
+                    })?;
+                }
+                let file = File::create(path).map_err(|e| {
+                    TranscriptError::io(format!("Failed to create file {}", path), e)
+                })?;
+                Ok(Box::new(file))
+            }
+        }
+    }
+}
+
+fn output_json(items: &[TranscriptItem], dest: &OutputDestination) -> Result<(), TranscriptError> {
+    let json = serde_json::to_string_pretty(items)?;
+    let mut writer = dest.writer()?;
+    writeln!(writer, "{}", json)?;
+    Ok(())
+}
+
+fn output_srt(items: &[TranscriptItem], dest: &OutputDestination) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+
+    for (index, item) in items.iter().enumerate() {
+        writeln!(writer, "{}", index + 1)?;
+
+        let start_time = format_srt_time(item.start);
+        let end_time = format_srt_time(item.start + item.duration);
+
+        writeln!(writer, "{} --> {}", start_time, end_time)?;
+        writeln!(writer, "{}", item.text)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn output_text(items: &[TranscriptItem], dest: &OutputDestination, video_url: Option<&str>, video_title: Option<&str>) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+
+    // Prefix with title and URL if provided
+    if let (Some(url), Some(title)) = (video_url, video_title) {
+        writeln!(writer, "{}: {}", title, url)?;
+        writeln!(writer)?;
+    }
+
+    for item in items {
+        writeln!(writer, "[{:.2}s] {}", item.start, item.text)?;
+    }
+
+    Ok(())
+}
+
+fn output_text_only(
+    items: &[TranscriptItem],
+    dest: &OutputDestination,
+    video_url: Option<&str>,
+    video_title: Option<&str>,
+) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+
+    // Prefix with title and URL if provided
+    if let (Some(url), Some(title)) = (video_url, video_title) {
+        writeln!(writer, "{}: {}", title, url)?;
+        writeln!(writer)?;
+    }
+
+    for item in items {
+        writeln!(writer, "{}", item.text)?;
+    }
+
+    Ok(())
+}
+
+fn output_markdown(
+    items: &[TranscriptItem],
+    dest: &OutputDestination,
+    timestamps: bool,
+    video_url: Option<&str>,
+    video_title: Option<&str>,
+) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+
+    // If URL and title are provided, prepend the markdown link
+    if let (Some(url), Some(title)) = (video_url, video_title) {
+        writeln!(writer, "![{}]({})\n", title, url)?;
+    }
+
+    // If there's only one item and it contains markdown (from ChatGPT cleanup),
+    // output it directly without adding extra formatting
+    if items.len() == 1
+        && (items[0].text.contains("**")
+            || items[0].text.contains("##")
+            || items[0].text.contains("*"))
+    {
+        // Already formatted by ChatGPT, just add heading if not present
+        if !items[0].text.trim_start().starts_with("#") {
+            writeln!(writer, "# Transcript\n")?;
+        }
+        writeln!(writer, "{}", items[0].text)?;
+    } else {
+        // Regular markdown output
+        writeln!(writer, "# Transcript\n")?;
+
+        for item in items {
+            if timestamps {
+                writeln!(writer, "**[{:.2}s]** {}", item.start, item.text)?;
+            } else {
+                writeln!(writer, "{}", item.text)?;
+            }
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn output_chapters_json(chapters: &[Chapter], dest: &OutputDestination) -> Result<(), TranscriptError> {
+    let json = serde_json::to_string_pretty(chapters)?;
+    let mut writer = dest.writer()?;
+    writeln!(writer, "{}", json)?;
+    Ok(())
+}
+
+fn output_chapters_description(chapters: &[Chapter], dest: &OutputDestination) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+    writeln!(writer, "{}", chapters::to_youtube_description(chapters))?;
+    Ok(())
+}
+
+fn output_markdown_chapters(
+    items: &[TranscriptItem],
+    chapters: &[Chapter],
+    dest: &OutputDestination,
+    timestamps: bool,
+    video_url: Option<&str>,
+    video_title: Option<&str>,
+) -> Result<(), TranscriptError> {
+    let mut writer = dest.writer()?;
+
+    if let (Some(url), Some(title)) = (video_url, video_title) {
+        writeln!(writer, "![{}]({})\n", title, url)?;
+    }
+
+    writeln!(writer, "# Transcript\n")?;
+
+    let mut next_chapter = chapters.iter().peekable();
+    for item in items {
+        // Start a new section for every chapter this item has reached
+        while let Some(chapter) = next_chapter.next_if(|c| c.start <= item.start) {
+            writeln!(
+                writer,
+                "## {} {}\n",
+                chapters::format_timestamp(chapter.start),
+                chapter.title
+            )?;
+        }
+
+        if timestamps {
+            writeln!(writer, "**[{:.2}s]** {}", item.start, item.text)?;
+        } else {
+            writeln!(writer, "{}", item.text)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn format_srt_time(seconds: f64) -> String {
+    let hours = (seconds / 3600.0) as u32;
+    let minutes = ((seconds % 3600.0) / 60.0) as u32;
+    let secs = seconds % 60.0;
+    let secs_int = secs as u32;
+    let millis = ((secs - secs_int as f64) * 1000.0) as u32;
+
+    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs_int, millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_format_srt_time() {
+        assert_eq!(format_srt_time(0.0), "00:00:00,000");
+        assert_eq!(format_srt_time(65.5), "00:01:05,500");
+        assert_eq!(format_srt_time(3661.123), "01:01:01,123");
+    }
+
+    #[test]
+    fn test_parse_input_entries_lines() {
+        let content = "# my videos\n\ndQw4w9WgXcQ\n  https://youtu.be/_NuH3D4SN-c  # talk\n@rustlang\n";
+        assert_eq!(
+            parse_input_entries(content).unwrap(),
+            vec!["dQw4w9WgXcQ", "https://youtu.be/_NuH3D4SN-c", "@rustlang"]
+        );
+    }
+
+    #[test]
+    fn test_parse_input_entries_csv() {
+        let content = "# export\ntitle,URL,notes\n\"Talk, part 1\",https://youtu.be/_NuH3D4SN-c,x\nEmpty,,\nIntro,dQw4w9WgXcQ\n";
+        assert_eq!(
+            parse_input_entries(content).unwrap(),
+            vec!["https://youtu.be/_NuH3D4SN-c", "dQw4w9WgXcQ"]
+        );
+    }
+
+    #[test]
+    fn test_classify_input_entry() {
+        assert_eq!(
+            classify_input_entry("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PL123").unwrap(),
+            InputEntry::Video("dQw4w9WgXcQ".to_string())
+        );
+        assert_eq!(
+            classify_input_entry("https://www.youtube.com/playlist?list=PL123").unwrap(),
+            InputEntry::Playlist("PL123".to_string())
+        );
+        assert_eq!(
+            classify_input_entry("https://www.youtube.com/@rustlang").unwrap(),
+            InputEntry::Channel("https://www.youtube.com/@rustlang".to_string())
+        );
+        assert!(classify_input_entry("not a url").is_err());
+    }
+
+    #[test]
+    fn test_limit_videos() {
+        let ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        assert_eq!(limit_videos(ids.clone(), Some(2)), vec!["a", "b"]);
+        assert_eq!(limit_videos(ids.clone(), Some(5)).len(), 3);
+        assert_eq!(limit_videos(ids, None).len(), 3);
+    }
+
+    fn parse_args(argv: &[&str]) -> (Args, ArgMatches) {
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        (Args::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    /// The command `argv` runs, with `settings` from the configuration applied
+    fn parse_command(argv: &[&str], settings: Settings) -> Command {
+        let (args, matches) = parse_args(argv);
+        let (mut command, command_matches) = args.into_command(&matches);
+        apply_settings(&mut command, command_matches, settings);
+        command
+    }
+
+    fn fetch_options(argv: &[&str], settings: Settings) -> FetchOptions {
+        match parse_command(argv, settings) {
+            Command::Fetch(fetch_args) => fetch_args.options,
+            _ => panic!("expected fetch command"),
+        }
+    }
+
+    fn research_settings() -> Settings {
+        Settings {
+            languages: Some(vec!["de".to_string()]),
+            format: Some("md".to_string()),
+            output: Some("notes".to_string()),
+            delay: Some(1000),
+            name: Some(true),
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_settings_fills_defaults() {
+        let (args, _) = parse_args(&["ytt", "abc", "--profile", "research"]);
+        assert_eq!(args.profile.as_deref(), Some("research"));
+
+        let options = fetch_options(&["ytt", "abc", "--profile", "research"], research_settings());
+        assert_eq!(options.languages, Some(vec!["de".to_string()]));
+        assert_eq!(options.format, "md");
+        assert_eq!(options.output.as_deref(), Some("notes"));
+        assert_eq!(options.delay, 1000);
+        assert!(options.name);
+        assert!(!options.url);
+    }
+
+    #[test]
+    fn test_apply_settings_command_line_wins() {
+        let argv = ["ytt", "abc", "-l", "fr,en", "-f", "text", "--delay", "500", "-o", "out.txt"];
+        let options = fetch_options(&argv, research_settings());
+
+        assert_eq!(options.languages, Some(vec!["fr".to_string(), "en".to_string()]));
+        assert_eq!(options.format, "text");
+        assert_eq!(options.output.as_deref(), Some("out.txt"));
+        assert_eq!(options.delay, 500);
+    }
+
+    #[test]
+    fn test_apply_settings_negated_flags() {
+        let settings = Settings {
+            timestamps: Some(true),
+            url: Some(true),
+            ..research_settings()
+        };
+        let options = fetch_options(&["ytt", "abc"], settings.clone());
+        assert!(options.name && options.url && options.timestamps);
+
+        let options = fetch_options(&["ytt", "abc", "--no-name", "--no-url", "--no-timestamps"], settings.clone());
+        assert!(!options.name && !options.url && !options.timestamps);
+
+        // The last of a flag and its negation wins
+        let options = fetch_options(&["ytt", "fetch", "abc", "--no-name", "-n", "--url", "--no-url"], settings.clone());
+        assert!(options.name);
+        assert!(!options.url);
+
+        let Command::Convert(convert_args) = parse_command(&["ytt", "convert", "t.json", "--no-timestamps"], settings) else {
+            panic!("expected convert command");
+        };
+        assert!(!convert_args.timestamps);
+    }
+
+    #[test]
+    fn test_apply_settings_subcommand() {
+        let options = fetch_options(&["ytt", "fetch", "abc", "-f", "json"], research_settings());
+        assert_eq!(options.format, "json");
+        assert_eq!(options.delay, 1000);
+
+        let Command::Playlist(playlist_args) =
+            parse_command(&["ytt", "playlist", "PL123", "--delay", "10"], research_settings())
+        else {
+            panic!("expected playlist command");
+        };
+        assert_eq!(playlist_args.options.delay, 10);
+        assert_eq!(playlist_args.options.format, "md");
+    }
+
+    #[test]
+    fn test_apply_settings_ask() {
+        let command = parse_command(&["ytt", "ask", "abc", "question?", "--profile", "research"], research_settings());
+
+        let Command::Ask(ask_args) = command else {
+            panic!("expected ask subcommand");
+        };
+        assert_eq!(ask_args.languages, Some(vec!["de".to_string()]));
+        assert_eq!(ask_args.delay, 1000);
+    }
+
+    #[test]
+    fn test_flat_invocation_maps_to_subcommands() {
+        let command = parse_command(&["ytt", "abc", "-m", "3"], Settings::default());
+        assert!(matches!(command, Command::Fetch(FetchArgs { video: Some(_), max: Some(3), .. })));
+
+        let command = parse_command(&["ytt", "--list", "abc"], Settings::default());
+        assert!(matches!(command, Command::List(ListArgs { playlist: false, .. })));
+
+        let command = parse_command(&["ytt", "-p", "PL123", "--list"], Settings::default());
+        assert!(matches!(command, Command::List(ListArgs { playlist: true, .. })));
+
+        let Command::Playlist(playlist_args) = parse_command(&["ytt", "-p", "PL123", "-f", "srt"], Settings::default())
+        else {
+            panic!("expected playlist command");
+        };
+        assert_eq!(playlist_args.playlist, "PL123");
+        assert_eq!(playlist_args.options.format, "srt");
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_completions_suggest_known_values() {
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Fish, &mut Args::command(), "ytt", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("srt\\t'SubRip subtitles'"));
+        assert!(script.contains("pt-BR\\t'Portuguese (Brazil)'"));
+
+        // Suggested values are not the only valid ones
+        let options = fetch_options(&["ytt", "abc", "-f", "xml", "-l", "tlh"], Settings::default());
+        assert_eq!(options.format, "xml");
+        assert_eq!(options.languages, Some(vec!["tlh".to_string()]));
+    }
 
-    let video_url = if args.url {
-        Some(format!("https://www.youtube.com/watch?v={}", video_id))
-    } else {
-        None
-    };
-    let video_title = if args.url {
-        transcript.title.as_ref().map(|s| s.as_str())
-    } else {
-        None
-    };
+    #[test]
+    fn test_manpage() {
+        let mut page = Vec::new();
+        Man::new(Args::command()).render(&mut page).unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.contains(".SH SUBCOMMANDS"));
+        assert!(page.contains("ytt\\-fetch"));
+    }
 
-    match args.format.to_lowercase().as_str() {
-        "json" => output_json(&transcript_items, &output_dest)?,
-        "srt" => output_srt(&transcript_items, &output_dest)?,
-        "text" | "txt" => {
-            if args.timestamps {
-                output_text(&transcript_items, &output_dest, video_url.as_deref(), video_title)?;
-            } else {
-                output_text_only(&transcript_items, &output_dest, video_url.as_deref(), video_title)?;
-            }
-        }
-        "markdown" | "md" => {
-            let video_title = if args.url {
-                transcript.title.as_ref().map(|s| s.as_str())
-            } else {
-                None
-            };
-            output_markdown(&transcript_items, &output_dest, args.timestamps, video_url.as_deref(), video_title)?;
+    #[test]
+    fn test_subcommands_parse() {
+        assert!(matches!(
+            parse_command(&["ytt", "channel", "@rustlang", "-m", "5"], Settings::default()),
+            Command::Channel(ChannelArgs { max: Some(5), .. })
+        ));
+        assert!(matches!(
+            parse_command(&["ytt", "search", "abc", "borrow checker", "-n", "2"], Settings::default()),
+            Command::Search(SearchArgs { results: 2, .. })
+        ));
+        assert!(matches!(
+            parse_command(&["ytt", "cache", "stats"], Settings::default()),
+            Command::Cache(CacheArgs { action: CacheAction::Stats })
+        ));
+        // Subcommands take no options of the flat invocation they are not defined with
+        assert!(Args::command().try_get_matches_from(["ytt", "list", "abc", "--cleanup"]).is_err());
+        assert!(Args::command().try_get_matches_from(["ytt", "fetch", "abc", "--list"]).is_err());
+    }
+
+    fn transcript(video_id: &str, title: &str) -> TranscriptResponse {
+        TranscriptResponse {
+            video_id: video_id.to_string(),
+            title: Some(title.to_string()),
+            channel: Some("Some Channel".to_string()),
+            upload_date: Some("2024-01-31".to_string()),
+            language: "English".to_string(),
+            language_code: "en".to_string(),
+            is_generated: true,
+            is_translatable: true,
+            translation_origin: None,
+            transcript: vec![],
         }
-        _ => {
-            eprintln!("Unknown format: '{}'. Using 'text' format.", args.format);
-            eprintln!("Supported formats: json, text, txt, srt, markdown, md");
-            if args.timestamps {
-                output_text(&transcript_items, &output_dest, video_url.as_deref(), video_title)?;
-            } else {
-                output_text_only(&transcript_items, &output_dest, video_url.as_deref(), video_title)?;
-            }
+    }
+
+    fn claimed_path(
+        outputs: &mut OutputState,
+        options: &FetchOptions,
+        transcript: &TranscriptResponse,
+        index: usize,
+    ) -> String {
+        let path = outputs.path_for(options, transcript, Some(index)).unwrap().unwrap();
+        match outputs.claim(path) {
+            OutputDestination::File(path) => path,
+            OutputDestination::Stdout => panic!("expected a file destination"),
         }
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_output_state_template() {
+        let options = fetch_options(
+            &[
+                "ytt",
+                "abc",
+                "-f",
+                "md",
+                "-o",
+                "out",
+                "--output-template",
+                "{channel}/{playlist_index:03}_{title}.{ext}",
+            ],
+            Settings::default(),
+        );
+        let mut outputs = OutputState::new(&options).unwrap();
+
+        assert_eq!(
+            claimed_path(&mut outputs, &options, &transcript("a", "First"), 2),
+            "out/Some_Channel/002_First.md"
+        );
+    }
 
-enum OutputDestination {
-    Stdout,
-    File(String),
-}
+    #[test]
+    fn test_output_state_avoids_collisions() {
+        let options = fetch_options(&["ytt", "abc", "-n"], Settings::default());
+        let mut outputs = OutputState::new(&options).unwrap();
 
-impl OutputDestination {
-    fn writer(&self) -> Result<Box<dyn Write>, TranscriptError> {
-        match self {
-            OutputDestination::Stdout => Ok(Box::new(io::stdout())),
-            OutputDestination::File(path) => {
-                let file = File::create(path).map_err(|e| {
-                    TranscriptError::IoError(format!("Failed to create file {}: {}", path, e))
-                })?;
-                Ok(Box::new(file))
-            }
-        }
+        assert_eq!(claimed_path(&mut outputs, &options, &transcript("a", "Same"), 1), "Same.txt");
+        assert_eq!(claimed_path(&mut outputs, &options, &transcript("b", "Same"), 2), "Same_2.txt");
     }
-}
 
-fn output_json(items: &[TranscriptItem], dest: &OutputDestination) -> Result<(), TranscriptError> {
-    let json = serde_json::to_string_pretty(items)?;
-    let mut writer = dest.writer()?;
-    writeln!(writer, "{}", json)?;
-    Ok(())
-}
+    #[test]
+    fn test_output_state_rejects_bad_template() {
+        let options = fetch_options(&["ytt", "abc", "--output-template", "{nope}"], Settings::default());
+        assert!(OutputState::new(&options).is_err());
+    }
 
-fn output_srt(items: &[TranscriptItem], dest: &OutputDestination) -> Result<(), TranscriptError> {
-    let mut writer = dest.writer()?;
+    #[test]
+    fn test_output_path_for_stdout() {
+        let options = fetch_options(&["ytt", "abc"], Settings::default());
+        let outputs = OutputState::new(&options).unwrap();
+        assert!(outputs.path_for(&options, &transcript("a", "Title"), None).unwrap().is_none());
+    }
 
-    for (index, item) in items.iter().enumerate() {
-        writeln!(writer, "{}", index + 1)?;
+    #[test]
+    fn test_output_language_paths() {
+        let mut german = transcript("a", "Title");
+        german.language_code = "de".to_string();
+        german.is_generated = false;
+        let language_path = |argv: &[&str], index: Option<usize>| {
+            let options = fetch_options(argv, Settings::default());
+            let outputs = OutputState::new(&options).unwrap();
+            outputs.language_path_for(&options, &german, index).unwrap()
+        };
 
-        let start_time = format_srt_time(item.start);
-        let end_time = format_srt_time(item.start + item.duration);
+        assert_eq!(language_path(&["ytt", "a", "--all-languages"], None), PathBuf::from("a.de.txt"));
+        assert_eq!(
+            language_path(&["ytt", "a", "--all-languages", "-f", "srt", "-o", "subs/out.srt"], None),
+            PathBuf::from("subs/out.de.srt")
+        );
+        assert_eq!(
+            language_path(&["ytt", "a", "--all-languages", "-n", "-f", "md"], Some(1)),
+            PathBuf::from("Title.de.md")
+        );
+        assert_eq!(
+            language_path(&["ytt", "a", "--all-languages", "--output-template", "{id}-{lang}.{ext}"], None),
+            PathBuf::from("a-de.txt")
+        );
+
+        german.is_generated = true;
+        let options = fetch_options(&["ytt", "a", "--all-languages"], Settings::default());
+        let outputs = OutputState::new(&options).unwrap();
+        assert_eq!(
+            outputs.language_path_for(&options, &german, None).unwrap(),
+            PathBuf::from("a.de.auto.txt")
+        );
+    }
 
-        writeln!(writer, "{} --> {}", start_time, end_time)?;
-        writeln!(writer, "{}", item.text)?;
-        writeln!(writer)?;
+    #[test]
+    fn test_language_policy() {
+        let options = fetch_options(&["ytt", "abc"], Settings::default());
+        assert_eq!(language_policy(&options), LanguagePolicy::default());
+
+        let options = fetch_options(
+            &["ytt", "abc", "--match-region", "--prefer-generated", "--translate-fallback"],
+            Settings::default(),
+        );
+        assert_eq!(
+            language_policy(&options),
+            LanguagePolicy {
+                match_region: true,
+                preference: TrackPreference::GeneratedPerLanguage,
+                translate_fallback: true,
+            }
+        );
+
+        let options = fetch_options(&["ytt", "abc", "--manual-first"], Settings::default());
+        assert_eq!(language_policy(&options).preference, TrackPreference::ManualFirst);
+
+        for argv in [
+            &["ytt", "abc", "--manual-first", "--prefer-generated"][..],
+            &["ytt", "abc", "--translate-fallback", "-t", "de"],
+        ] {
+            assert!(Args::command().try_get_matches_from(argv).is_err(), "{:?}", argv);
+        }
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_all_languages_flag() {
+        let options = fetch_options(&["ytt", "--all-languages", "abc"], Settings::default());
+        assert_eq!(options.all_languages, Some(TrackKinds::All));
+        let options = fetch_options(&["ytt", "abc", "--all-languages=manual", "-t", "de"], Settings::default());
+        assert_eq!(options.all_languages, Some(TrackKinds::Manual));
+        assert!(fetch_options(&["ytt", "abc"], Settings::default()).all_languages.is_none());
+
+        assert!(Args::command()
+            .try_get_matches_from(["ytt", "abc", "--all-languages", "--interactive"])
+            .is_err());
+    }
 
-fn output_text(items: &[TranscriptItem], dest: &OutputDestination, video_url: Option<&str>, video_title: Option<&str>) -> Result<(), TranscriptError> {
-    let mut writer = dest.writer()?;
+    #[test]
+    fn test_track_key() {
+        let mut list = transcript_list("abc");
+        let mut generated_en = list.manually_created["en"].clone();
+        generated_en.is_generated = true;
+        list.generated.insert("en".to_string(), generated_en);
+
+        // Both English tracks are written, under different names
+        let keys: Vec<String> = list
+            .select(&TranscriptFilter::default())
+            .into_iter()
+            .map(|track| track.unwrap())
+            .map(|track| track_key(&track.language_code, track.is_generated))
+            .collect();
+        assert_eq!(keys, vec!["en", "de.auto", "en.auto", "fr.auto"]);
+    }
 
-    // Prefix with title and URL if provided
-    if let (Some(url), Some(title)) = (video_url, video_title) {
-        writeln!(writer, "{}: {}", title, url)?;
-        writeln!(writer)?;
+    #[test]
+    fn test_requested_languages() {
+        let options = fetch_options(&["ytt", "abc"], Settings::default());
+        assert_eq!(requested_languages(&options), vec!["en"]);
+        let options = fetch_options(&["ytt", "abc", "-l", "de,fr"], Settings::default());
+        assert_eq!(requested_languages(&options), vec!["de", "fr"]);
+        let options = fetch_options(&["ytt", "abc", "-l", "de", "-t", "es"], Settings::default());
+        assert_eq!(requested_languages(&options), vec!["es"]);
     }
 
-    for item in items {
-        writeln!(writer, "[{:.2}s] {}", item.start, item.text)?;
+    #[tokio::test]
+    async fn test_archive_matches_like_the_language_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("archive.txt");
+        fs::write(&archive_path, "abcdefghijk en-US txt\n_NuH3D4SN-c he txt\n").unwrap();
+        let archive = archive_path.to_str().unwrap();
+        // Nothing is recorded, so any request would fail
+        let api = YouTubeTranscript::with_transport(Arc::new(ytt::transport::ReplayTransport::new(vec![])), 0);
+        let process = |argv: &[&str], video_id: &'static str| {
+            let options = fetch_options(argv, Settings::default());
+            let api = &api;
+            async move {
+                let mut outputs = OutputState::new(&options).unwrap();
+                process_single_video(api, &options, None, &mut outputs, video_id, None).await
+            }
+        };
+
+        let outcome = process(&["ytt", "abcdefghijk", "-l", "en", "--match-region", "--archive", archive], "abcdefghijk");
+        assert!(matches!(outcome.await, Ok(VideoOutcome::Skipped { reason }) if reason.contains("en-US")));
+        let outcome = process(&["ytt", "_NuH3D4SN-c", "-l", "iw", "--archive", archive], "_NuH3D4SN-c");
+        assert!(matches!(outcome.await, Ok(VideoOutcome::Skipped { .. })));
+        let outcome = process(&["ytt", "abcdefghijk", "-l", "en", "--archive", archive], "abcdefghijk");
+        assert!(outcome.await.is_err());
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_output_destination_creates_directories() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("a").join("b").join("out.txt");
+        let dest = OutputDestination::File(file_path.to_string_lossy().to_string());
+        assert!(dest.writer().is_ok());
+        assert!(file_path.exists());
+    }
 
-fn output_text_only(
-    items: &[TranscriptItem],
-    dest: &OutputDestination,
-    video_url: Option<&str>,
-    video_title: Option<&str>,
-) -> Result<(), TranscriptError> {
-    let mut writer = dest.writer()?;
+    fn written() -> Result<VideoOutcome, TranscriptError> {
+        Ok(VideoOutcome::Written {
+            track: Track {
+                language: "English".to_string(),
+                language_code: "en".to_string(),
+                is_generated: false,
+                translation_origin: None,
+            },
+            output: Some("a.txt".to_string()),
+        })
+    }
 
-    // Prefix with title and URL if provided
-    if let (Some(url), Some(title)) = (video_url, video_title) {
-        writeln!(writer, "{}: {}", title, url)?;
-        writeln!(writer)?;
+    fn blocked() -> Result<VideoOutcome, TranscriptError> {
+        Err(TranscriptError::IpBlocked("b".to_string()))
     }
 
-    for item in items {
-        writeln!(writer, "{}", item.text)?;
+    #[test]
+    fn test_run_report_exit_codes() {
+        let mut report = RunReport::new();
+        assert_eq!(report.exit_code(), EXIT_SUCCESS);
+
+        report.record("a", Instant::now(), &written());
+        report.record("c", Instant::now(), &Ok(VideoOutcome::Skipped { reason: "archived".to_string() }));
+        assert_eq!(report.exit_code(), EXIT_SUCCESS);
+
+        report.record("b", Instant::now(), &blocked());
+        assert_eq!(report.exit_code(), EXIT_PARTIAL_FAILURE);
+        assert_eq!(report.counts(), (1, 1, 1));
+
+        let mut single = RunReport::new();
+        single.record("b", Instant::now(), &blocked());
+        assert_eq!(single.exit_code(), EXIT_BLOCKED);
+
+        single.record("b", Instant::now(), &blocked());
+        assert_eq!(single.exit_code(), EXIT_TOTAL_FAILURE);
+
+        // Nothing was processed because the only entry could not be resolved
+        let mut unresolved = RunReport::new();
+        unresolved.record_unresolved(&UnresolvedEntry {
+            entry: "not a url".to_string(),
+            error: TranscriptError::InvalidVideoId("not a url".to_string()),
+        });
+        assert_eq!(unresolved.exit_code(), EXIT_USAGE);
+
+        unresolved.record("b", Instant::now(), &blocked());
+        assert_eq!(unresolved.exit_code(), EXIT_TOTAL_FAILURE);
+        unresolved.record("a", Instant::now(), &written());
+        assert_eq!(unresolved.exit_code(), EXIT_PARTIAL_FAILURE);
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_run_report_write() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("report.json");
+
+        let mut report = RunReport::new();
+        report.record("a", Instant::now(), &written());
+        report.record("b", Instant::now(), &blocked());
+        report.record_unresolved(&UnresolvedEntry {
+            entry: "not a url".to_string(),
+            error: TranscriptError::InvalidVideoId("not a url".to_string()),
+        });
+        report.write(path.to_str().unwrap(), report.exit_code()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["exit_code"], EXIT_PARTIAL_FAILURE);
+        assert_eq!(json["failed"], 2);
+        assert_eq!(json["videos"][0]["status"], "ok");
+        assert_eq!(json["videos"][0]["track"]["language_code"], "en");
+        assert_eq!(json["videos"][1]["status"], "failed");
+        assert_eq!(json["videos"][1]["error"]["code"], "ip_blocked");
+        assert_eq!(json["videos"][2]["entry"], "not a url");
+        assert!(json["videos"][2].get("video_id").is_none());
+        assert_eq!(json["videos"][2]["error"]["code"], "invalid_video_id");
+    }
 
-fn output_markdown(
-    items: &[TranscriptItem],
-    dest: &OutputDestination,
-    timestamps: bool,
-    video_url: Option<&str>,
-    video_title: Option<&str>,
-) -> Result<(), TranscriptError> {
-    let mut writer = dest.writer()?;
+    #[test]
+    fn test_log_level() {
+        assert_eq!(log_level(0, 0), LevelFilter::INFO);
+        assert_eq!(log_level(0, 1), LevelFilter::WARN);
+        assert_eq!(log_level(0, 2), LevelFilter::ERROR);
+        assert_eq!(log_level(1, 0), LevelFilter::DEBUG);
+        assert_eq!(log_level(3, 0), LevelFilter::TRACE);
+    }
 
-    // If URL and title are provided, prepend the markdown link
-    if let (Some(url), Some(title)) = (video_url, video_title) {
-        writeln!(writer, "![{}]({})\n", title, url)?;
+    #[test]
+    fn test_verbose_conflicts_with_quiet() {
+        assert!(Args::command().try_get_matches_from(["ytt", "abc", "-v", "-q"]).is_err());
+        let (args, _) = parse_args(&["ytt", "abc", "-vv", "--log-format", "json"]);
+        assert_eq!(args.verbose, 2);
+        assert_eq!(args.log_format, LogFormat::Json);
     }
 
-    // If there's only one item and it contains markdown (from ChatGPT cleanup),
-    // output it directly without adding extra formatting
-    if items.len() == 1
-        && (items[0].text.contains("**")
-            || items[0].text.contains("##")
-            || items[0].text.contains("*"))
-    {
-        // Already formatted by ChatGPT, just add heading if not present
-        if !items[0].text.trim_start().starts_with("#") {
-            writeln!(writer, "# Transcript\n")?;
+    #[test]
+    fn test_error_exit_code() {
+        assert_eq!(error_exit_code(&TranscriptError::InvalidVideoId("x".to_string())), EXIT_USAGE);
+        assert_eq!(
+            error_exit_code(&TranscriptError::NoTranscriptFound("x".to_string(), vec![])),
+            EXIT_NO_TRANSCRIPT
+        );
+        assert_eq!(error_exit_code(&TranscriptError::BudgetExceeded("x".to_string())), EXIT_LLM_ERROR);
+    }
+
+    fn transcript_list(video_id: &str) -> TranscriptList {
+        let track = |code: &str, language: &str, is_generated: bool| TranscriptInfo {
+            language_code: code.to_string(),
+            language: language.to_string(),
+            is_generated,
+            is_translatable: !is_generated,
+            base_url: format!("https://example.com/{}", code),
+            translation_languages: vec![],
+            translated_from: None,
+        };
+        TranscriptList {
+            video_id: video_id.to_string(),
+            title: None,
+            channel: None,
+            upload_date: None,
+            manually_created: [("en".to_string(), track("en", "English", false))].into_iter().collect(),
+            generated: [
+                ("fr".to_string(), track("fr", "French (auto-generated)", true)),
+                ("de".to_string(), track("de", "German (auto-generated)", true)),
+            ]
+            .into_iter()
+            .collect(),
+            translation_languages: vec![
+                TranslationLanguage {
+                    language: "Spanish".to_string(),
+                    language_code: "es".to_string(),
+                },
+                TranslationLanguage {
+                    language: "Afrikaans".to_string(),
+                    language_code: "af".to_string(),
+                },
+            ],
         }
-        writeln!(writer, "{}", items[0].text)?;
-    } else {
-        // Regular markdown output
-        writeln!(writer, "# Transcript\n")?;
+    }
 
-        for item in items {
-            if timestamps {
-                writeln!(writer, "**[{:.2}s]** {}", item.start, item.text)?;
-            } else {
-                writeln!(writer, "{}", item.text)?;
-            }
-            writeln!(writer)?;
+    fn list_output(format: ListFormat, video_ids: &[&str], batch: bool) -> String {
+        let mut out = Vec::new();
+        let mut writer = ListWriter::new(&mut out, format);
+        for (index, video_id) in video_ids.iter().enumerate() {
+            let position = batch.then_some((index + 1, video_ids.len()));
+            writer.write(transcript_list(video_id), position).unwrap();
         }
+        writer.finish(batch).unwrap();
+        String::from_utf8(out).unwrap()
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_list_csv() {
+        let csv = list_output(ListFormat::Csv, &["a", "b"], true);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "video_id,language_code,language,kind,is_generated,is_translatable,has_base_url,translation_languages"
+        );
+        assert_eq!(lines[1], "a,en,English,manual,false,true,true,af;es");
+        assert_eq!(lines[2], "a,de,German (auto-generated),generated,true,false,true,");
+        assert_eq!(lines.len(), 7);
+        assert!(lines[4].starts_with("b,en,"));
+    }
 
-fn format_srt_time(seconds: f64) -> String {
-    let hours = (seconds / 3600.0) as u32;
-    let minutes = ((seconds % 3600.0) / 60.0) as u32;
-    let secs = seconds % 60.0;
-    let secs_int = secs as u32;
-    let millis = ((secs - secs_int as f64) * 1000.0) as u32;
+    #[test]
+    fn test_list_table() {
+        let table = list_output(ListFormat::Table, &["a"], false);
+        assert_eq!(
+            table,
+            "Available transcripts for video: a\n\n\
+            CODE  LANGUAGE                 KIND       TRANSLATABLE  URL\n\
+            en    English                  manual     yes           yes\n\
+            de    German (auto-generated)  generated  no            yes\n\
+            fr    French (auto-generated)  generated  no            yes\n\n\
+            CODE  TRANSLATION LANGUAGE\n\
+            af    Afrikaans\n\
+            es    Spanish\n"
+        );
+    }
 
-    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, secs_int, millis)
-}
+    #[test]
+    fn test_list_text_is_sorted() {
+        let text = list_output(ListFormat::Text, &["a"], false);
+        let german = text.find("German").unwrap();
+        assert!(german < text.find("French").unwrap());
+        assert!(text.find("Afrikaans").unwrap() < text.find("Spanish").unwrap());
+    }
 
-fn sanitize_filename(title: &str) -> String {
-    // Replace invalid filesystem characters with underscores
-    let sanitized: String = title
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.' {
-                c
-            } else {
-                '_'
-            }
-        })
-        .collect();
+    #[test]
+    fn test_list_json() {
+        let single: serde_json::Value = serde_json::from_str(&list_output(ListFormat::Json, &["a"], false)).unwrap();
+        assert_eq!(single["video_id"], "a");
+        assert_eq!(single["transcripts"][1]["language_code"], "de");
 
-    // Trim whitespace and replace multiple spaces/underscores with single underscore
-    let sanitized = sanitized.trim();
-    let sanitized = sanitized
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join("_");
-    let sanitized = sanitized
-        .chars()
-        .fold(String::new(), |mut acc, c| {
-            if c == '_' {
-                if !acc.ends_with('_') {
-                    acc.push(c);
-                }
-            } else {
-                acc.push(c);
-            }
-            acc
-        });
+        let batch: serde_json::Value = serde_json::from_str(&list_output(ListFormat::Json, &["a"], true)).unwrap();
+        assert_eq!(batch.as_array().unwrap().len(), 1);
+    }
 
-    // Limit length to 200 characters (reasonable for most filesystems)
-    let sanitized = if sanitized.len() > 200 {
-        &sanitized[..200]
-    } else {
-        &sanitized
-    };
+    #[test]
+    fn test_flat_list_format() {
+        let Command::List(list_args) = parse_command(&["ytt", "abc", "--list", "-f", "csv"], Settings::default()) else {
+            panic!("expected list command");
+        };
+        assert_eq!(list_args.format, ListFormat::Csv);
+        assert_eq!(ListFormat::from_fetch_format("md"), ListFormat::Text);
+    }
 
-    sanitized.trim_end_matches('_').to_string()
-}
+    #[test]
+    fn test_parse_saved_transcript() {
+        let items = r#"[{"text": "Hello", "start": 0.0, "duration": 1.0}]"#;
+        assert_eq!(parse_saved_transcript(items).unwrap()[0].text, "Hello");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::TempDir;
+        let response = serde_json::to_string(&TranscriptResponse {
+            transcript: vec![TranscriptItem {
+                text: "World".to_string(),
+                start: 1.0,
+                duration: 1.0,
+            }],
+            ..transcript("a", "Title")
+        })
+        .unwrap();
+        assert_eq!(parse_saved_transcript(&response).unwrap()[0].text, "World");
+
+        assert!(matches!(
+            parse_saved_transcript(r#"[{"start": 0.0, "title": "Intro"}]"#),
+            Err(TranscriptError::JsonParseError { source: Some(_), .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handle_request_routes() {
+        let api = YouTubeTranscript::with_delay(0);
+        assert_eq!(handle_request(&api, "GET /health HTTP/1.1").await.status, 200);
+        assert_eq!(handle_request(&api, "POST /health HTTP/1.1").await.status, 405);
+        assert_eq!(handle_request(&api, "GET /nope HTTP/1.1").await.status, 404);
+        assert_eq!(handle_request(&api, "GET /transcripts/abc?format=xml HTTP/1.1").await.status, 400);
+
+        let response = handle_request(&api, "GET /transcripts/bad%20id HTTP/1.1").await;
+        assert_eq!(response.status, 400);
+        let json: serde_json::Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(json["error"]["code"], "invalid_video_id");
+
+        // An encoded URL is decoded to its video ID, which is then requested
+        let transport = Arc::new(ytt::transport::ReplayTransport::new(Vec::new()));
+        let api = YouTubeTranscript::with_transport(transport.clone(), 0);
+        let request_line = "GET /transcripts/https%3A%2F%2Fyoutu.be%2FdQw4w9WgXcQ HTTP/1.1";
+        let response = handle_request(&api, request_line).await;
+        assert_eq!(response.status, 502);
+        assert!(transport.requests()[0].url.contains("v=dQw4w9WgXcQ"));
+    }
 
     #[test]
-    fn test_format_srt_time() {
-        assert_eq!(format_srt_time(0.0), "00:00:00,000");
-        assert_eq!(format_srt_time(65.5), "00:01:05,500");
-        assert_eq!(format_srt_time(3661.123), "01:01:01,123");
+    fn test_http_status() {
+        assert_eq!(http_status(&TranscriptError::InvalidVideoId("x".to_string())), 400);
+        assert_eq!(http_status(&TranscriptError::TranscriptsDisabled("x".to_string())), 404);
+        assert_eq!(http_status(&TranscriptError::IpBlocked("x".to_string())), 503);
+        assert_eq!(http_status(&TranscriptError::http(None, "x")), 502);
+        assert_eq!(http_status(&TranscriptError::io("x", io::ErrorKind::Other.into())), 500);
     }
 
     #[test]
@@ -644,6 +3766,58 @@ mod tests {
         assert!(content.contains("Hello world"));
     }
 
+    #[test]
+    fn test_output_markdown_chapters() {
+        let items = vec![
+            TranscriptItem {
+                text: "Welcome".to_string(),
+                start: 0.0,
+                duration: 2.0,
+            },
+            TranscriptItem {
+                text: "Let's get started".to_string(),
+                start: 65.0,
+                duration: 2.0,
+            },
+        ];
+        let chapters = vec![
+            Chapter {
+                start: 0.0,
+                title: "Intro".to_string(),
+            },
+            Chapter {
+                start: 65.0,
+                title: "Setup".to_string(),
+            },
+        ];
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("test.md");
+        let dest = OutputDestination::File(file_path.to_string_lossy().to_string());
+
+        assert!(output_markdown_chapters(&items, &chapters, &dest, false, None, None).is_ok());
+        let content = fs::read_to_string(&file_path).unwrap();
+        assert_eq!(
+            content,
+            "# Transcript\n\n## 00:00 Intro\n\nWelcome\n\n## 01:05 Setup\n\nLet's get started\n\n"
+        );
+    }
+
+    #[test]
+    fn test_output_chapters_description() {
+        let chapters = vec![Chapter {
+            start: 0.0,
+            title: "Intro".to_string(),
+        }];
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("chapters.txt");
+        let dest = OutputDestination::File(file_path.to_string_lossy().to_string());
+
+        assert!(output_chapters_description(&chapters, &dest).is_ok());
+        assert_eq!(fs::read_to_string(&file_path).unwrap(), "00:00 Intro\n");
+    }
+
     #[test]
     fn test_output_markdown_with_chatgpt_formatting() {
         let items = vec![TranscriptItem {
@@ -661,4 +3835,109 @@ mod tests {
         // Should detect ChatGPT formatting and not add extra heading
         assert!(content.contains("## Section"));
     }
+
+    #[test]
+    fn test_track_menu() {
+        let list = transcript_list("abc");
+        let choices = track_choices(&list);
+        let labels: Vec<String> = choices
+            .iter()
+            .map(|choice| match choice {
+                TrackChoice::Track(track) => track.language_code.clone(),
+                TrackChoice::Translation { source, language } => {
+                    format!("{}>{}", source.language_code, language.language_code)
+                }
+            })
+            .collect();
+        assert_eq!(labels, ["en", "de", "fr", "en>af", "en>es"]);
+
+        let mut menu = Vec::new();
+        write_track_menu(&mut menu, &list, &choices).unwrap();
+        let menu = String::from_utf8(menu).unwrap();
+        assert!(menu.contains("Manually created:\n  1) English (en)\n"));
+        assert!(menu.contains("Auto-generated:\n  2) German (auto-generated) (de)\n"));
+        assert!(menu.contains("Translated from English (en):\n  4) Afrikaans (af)\n  5) Spanish (es)\n"));
+    }
+
+    #[test]
+    fn test_prompt_choice() {
+        let mut out = Vec::new();
+        let choice = prompt_choice(&mut io::Cursor::new("x\n9\n 2 \n"), &mut out, 5).unwrap();
+        assert_eq!(choice, 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap().matches("Please enter a number between 1 and 5").count(),
+            2
+        );
+
+        let error = prompt_choice(&mut io::Cursor::new("0\n"), &mut Vec::new(), 5).unwrap_err();
+        assert!(matches!(error, TranscriptError::InvalidInput(_)));
+    }
+
+    #[test]
+    fn test_interactive_flag() {
+        let command = parse_command(&["ytt", "--interactive", "abc"], Settings::default());
+        assert!(matches!(command, Command::Fetch(FetchArgs { interactive: true, .. })));
+
+        let command = parse_command(&["ytt", "fetch", "abc", "--interactive", "-f", "srt"], Settings::default());
+        assert!(matches!(command, Command::Fetch(FetchArgs { interactive: true, .. })));
+
+        for argv in [
+            &["ytt", "--interactive", "--list", "abc"][..],
+            &["ytt", "--interactive", "-p", "PL123"],
+        ] {
+            assert!(Args::command().try_get_matches_from(argv).is_err(), "{:?}", argv);
+        }
+
+        let interactive_check = |command: clap::Command, argv: &[&str]| {
+            let matches = command.try_get_matches_from(argv).unwrap();
+            let args = Args::from_arg_matches(&matches).unwrap();
+            let (command, command_matches) = args.into_command(&matches);
+            check_interactive(&command, command_matches)
+        };
+        let error = interactive_check(Args::command(), &["ytt", "fetch", "abc", "--interactive", "-l", "de"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+        assert!(interactive_check(Args::command(), &["ytt", "--interactive", "abc", "--languages", "de"]).is_err());
+        assert!(interactive_check(Args::command(), &["ytt", "fetch", "abc", "-l", "de"]).is_ok());
+
+        // Languages from the environment don't get in the way of the menu. The
+        // variable is renamed so tests running in parallel don't see it.
+        std::env::set_var("YTT_TEST_INTERACTIVE_LANGUAGES", "en");
+        let from_env = |arg: clap::Arg| arg.env("YTT_TEST_INTERACTIVE_LANGUAGES");
+        let command = Args::command()
+            .mut_arg("languages", from_env)
+            .mut_subcommand("fetch", |fetch| fetch.mut_arg("languages", from_env));
+        assert!(interactive_check(command.clone(), &["ytt", "fetch", "abc", "--interactive"]).is_ok());
+        assert!(interactive_check(command.clone(), &["ytt", "--interactive", "abc"]).is_ok());
+        assert!(interactive_check(command, &["ytt", "fetch", "abc", "--interactive", "-l", "de"]).is_err());
+        std::env::remove_var("YTT_TEST_INTERACTIVE_LANGUAGES");
+    }
+
+    #[test]
+    fn test_client_settings() {
+        let client_args = |argv: &[&str]| Args::try_parse_from(argv).unwrap().client;
+        let settings = Settings {
+            clients: Some(vec!["tv".to_string()]),
+            youtube_url: Some("http://localhost:8080".to_string()),
+            po_token_command: Some("pot-helper {video_id}".to_string()),
+            ..Settings::default()
+        };
+
+        let args = client_args(&["ytt", "fetch", "abc", "--client", "ios,web@2.1", "--po-token", "tok"]);
+        let client_settings = ClientSettings::new(args, &settings).unwrap();
+        assert_eq!(client_settings.clients, vec![ClientProfile::ios(), ClientProfile::web().with_version("2.1")]);
+        assert_eq!(client_settings.youtube_url.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(client_settings.po_token, Some(PoToken::new("tok")));
+        assert!(client_settings.po_token_command.is_none());
+
+        let client_settings = ClientSettings::new(client_args(&["ytt", "abc"]), &settings).unwrap();
+        assert_eq!(client_settings.clients, vec![ClientProfile::tv()]);
+        assert!(client_settings.po_token_command.is_some());
+
+        let err = ClientSettings::new(client_args(&["ytt", "abc", "--client", "nokia"]), &settings).unwrap_err();
+        assert_eq!(error_exit_code(&err), EXIT_USAGE);
+        // Visitor data belongs to a token, not to a helper command
+        let args = client_args(&["ytt", "abc", "--visitor-data", "Cgt4"]);
+        assert!(ClientSettings::new(args, &settings).is_err());
+        assert!(Args::try_parse_from(["ytt", "abc", "--po-token", "a", "--po-token-command", "b"]).is_err());
+    }
 }
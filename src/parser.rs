@@ -1,5 +1,6 @@
 use quick_xml::events::Event;
 use quick_xml::Reader;
+use std::io::BufRead;
 use std::str;
 
 pub struct TranscriptParser {
@@ -14,184 +15,206 @@ impl TranscriptParser {
     }
 
     pub fn parse(&self, xml: &str) -> Result<Vec<crate::TranscriptItem>, String> {
-        let mut reader = Reader::from_str(xml);
+        self.items(xml.as_bytes()).collect()
+    }
+
+    /// Parse the transcript in `xml` one element at a time, as the returned
+    /// iterator is advanced. It ends after the first error.
+    pub fn items<R: BufRead>(&self, xml: R) -> TranscriptItems<R> {
+        TranscriptItems {
+            reader: Reader::from_reader(xml),
+            buf: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+pub struct TranscriptItems<R> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    done: bool,
+}
 
-        let mut items = Vec::new();
-        let mut buf = Vec::new();
+impl<R: BufRead> Iterator for TranscriptItems<R> {
+    type Item = Result<crate::TranscriptItem, String>;
 
-        loop {
-            match reader.read_event_into(&mut buf) {
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            let item = match self.reader.read_event_into(&mut self.buf) {
                 Ok(Event::Start(e)) => match e.name().as_ref() {
-                    b"text" => {
-                        if let Some(item) = self.parse_text_element(&mut reader, &e)? {
-                            items.push(item);
-                        }
-                    }
-                    b"p" => {
-                        if let Some(item) = self.parse_p_element(&mut reader, &e)? {
-                            items.push(item);
-                        }
-                    }
-                    _ => {}
+                    b"text" => parse_text_element(&mut self.reader, &e),
+                    b"p" => parse_p_element(&mut self.reader, &e),
+                    _ => Ok(None),
                 },
-                Ok(Event::Eof) => break,
-                Err(e) => return Err(format!("XML parse error: {}", e)),
-                _ => {}
+                Ok(Event::Eof) => {
+                    self.done = true;
+                    Ok(None)
+                }
+                Err(e) => Err(format!("XML parse error: {}", e)),
+                _ => Ok(None),
+            };
+            match item {
+                Ok(Some(item)) => return Some(Ok(item)),
+                Ok(None) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
             }
-            buf.clear();
         }
-
-        Ok(items)
+        None
     }
+}
 
-    fn parse_text_element(
-        &self,
-        reader: &mut Reader<&[u8]>,
-        e: &quick_xml::events::BytesStart,
-    ) -> Result<Option<crate::TranscriptItem>, String> {
-        let start = e
-            .attributes()
-            .find(|a| {
-                a.as_ref()
-                    .map(|attr| attr.key.as_ref() == b"start")
-                    .unwrap_or(false)
-            })
-            .and_then(|a| {
-                a.ok()
-                    .and_then(|attr| str::from_utf8(&attr.value).ok().map(|s| s.to_string()))
-                    .and_then(|s| s.parse::<f64>().ok())
-            })
-            .unwrap_or(0.0);
-
-        let duration = e
-            .attributes()
-            .find(|a| {
-                a.as_ref()
-                    .map(|attr| attr.key.as_ref() == b"dur")
-                    .unwrap_or(false)
-            })
-            .and_then(|a| {
-                a.ok()
-                    .and_then(|attr| str::from_utf8(&attr.value).ok().map(|s| s.to_string()))
-                    .and_then(|s| s.parse::<f64>().ok())
-            })
-            .unwrap_or(0.0);
-
-        let mut text = String::new();
-        let mut buf = Vec::new();
-
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Text(e)) => {
-                    let decoded = html_escape::decode_html_entities(
-                        e.decode()
-                            .map_err(|e| format!("Failed to decode: {}", e))?
-                            .as_ref(),
-                    );
-                    text.push_str(&decoded);
-                }
-                Ok(Event::End(e)) if e.name().as_ref() == b"text" => break,
-                Ok(Event::Eof) => return Err("Unexpected EOF in text element".to_string()),
-                Err(e) => return Err(format!("XML parse error: {}", e)),
-                _ => {}
+fn parse_text_element<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &quick_xml::events::BytesStart,
+) -> Result<Option<crate::TranscriptItem>, String> {
+    let start = e
+        .attributes()
+        .find(|a| {
+            a.as_ref()
+                .map(|attr| attr.key.as_ref() == b"start")
+                .unwrap_or(false)
+        })
+        .and_then(|a| {
+            a.ok()
+                .and_then(|attr| str::from_utf8(&attr.value).ok().map(|s| s.to_string()))
+                .and_then(|s| s.parse::<f64>().ok())
+        })
+        .unwrap_or(0.0);
+
+    let duration = e
+        .attributes()
+        .find(|a| {
+            a.as_ref()
+                .map(|attr| attr.key.as_ref() == b"dur")
+                .unwrap_or(false)
+        })
+        .and_then(|a| {
+            a.ok()
+                .and_then(|attr| str::from_utf8(&attr.value).ok().map(|s| s.to_string()))
+                .and_then(|s| s.parse::<f64>().ok())
+        })
+        .unwrap_or(0.0);
+
+    let mut text = String::new();
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Text(e)) => {
+                text.push_str(&e.decode().map_err(|e| format!("Failed to decode: {}", e))?);
             }
-            buf.clear();
-        }
-
-        if text.trim().is_empty() {
-            return Ok(None);
+            // quick-xml reports entity references on their own; keep them
+            // escaped so they are decoded together with the text below
+            Ok(Event::GeneralRef(e)) => {
+                let name = e.decode().map_err(|e| format!("Failed to decode: {}", e))?;
+                text.push_str(&format!("&{};", name));
+            }
+            Ok(Event::End(e)) if e.name().as_ref() == b"text" => break,
+            Ok(Event::Eof) => return Err("Unexpected EOF in text element".to_string()),
+            Err(e) => return Err(format!("XML parse error: {}", e)),
+            _ => {}
         }
+        buf.clear();
+    }
 
-        Ok(Some(crate::TranscriptItem {
-            text: text.trim().to_string(),
-            start,
-            duration,
-        }))
+    // The XML escaping is undone first, then the HTML escaping YouTube
+    // applies to caption text (`&amp;#39;` is an apostrophe)
+    let text = html_escape::decode_html_entities(&html_escape::decode_html_entities(&text));
+    if text.trim().is_empty() {
+        return Ok(None);
     }
 
-    fn parse_p_element(
-        &self,
-        reader: &mut Reader<&[u8]>,
-        e: &quick_xml::events::BytesStart,
-    ) -> Result<Option<crate::TranscriptItem>, String> {
-        let start = e
-            .attributes()
-            .find(|a| {
-                a.as_ref()
-                    .map(|attr| attr.key.as_ref() == b"t")
-                    .unwrap_or(false)
-            })
-            .and_then(|a| {
-                a.ok().and_then(|attr| {
-                    str::from_utf8(&attr.value)
-                        .ok()
-                        .map(|s| s.to_string())
-                        .and_then(|s| s.parse::<f64>().ok())
-                        .map(|s| s / 1000.0) // Convert from milliseconds
-                })
-            })
-            .unwrap_or(0.0);
-
-        let duration = e
-            .attributes()
-            .find(|a| {
-                a.as_ref()
-                    .map(|attr| attr.key.as_ref() == b"d")
-                    .unwrap_or(false)
+    Ok(Some(crate::TranscriptItem {
+        text: text.trim().to_string(),
+        start,
+        duration,
+    }))
+}
+
+fn parse_p_element<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &quick_xml::events::BytesStart,
+) -> Result<Option<crate::TranscriptItem>, String> {
+    let start = e
+        .attributes()
+        .find(|a| {
+            a.as_ref()
+                .map(|attr| attr.key.as_ref() == b"t")
+                .unwrap_or(false)
+        })
+        .and_then(|a| {
+            a.ok().and_then(|attr| {
+                str::from_utf8(&attr.value)
+                    .ok()
+                    .map(|s| s.to_string())
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .map(|s| s / 1000.0) // Convert from milliseconds
             })
-            .and_then(|a| {
-                a.ok().and_then(|attr| {
-                    str::from_utf8(&attr.value)
-                        .ok()
-                        .map(|s| s.to_string())
-                        .and_then(|s| s.parse::<f64>().ok())
-                        .map(|s| s / 1000.0) // Convert from milliseconds
-                })
+        })
+        .unwrap_or(0.0);
+
+    let duration = e
+        .attributes()
+        .find(|a| {
+            a.as_ref()
+                .map(|attr| attr.key.as_ref() == b"d")
+                .unwrap_or(false)
+        })
+        .and_then(|a| {
+            a.ok().and_then(|attr| {
+                str::from_utf8(&attr.value)
+                    .ok()
+                    .map(|s| s.to_string())
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .map(|s| s / 1000.0) // Convert from milliseconds
             })
-            .unwrap_or(0.0);
-
-        let mut text = String::new();
-        let mut buf = Vec::new();
-
-        loop {
-            match reader.read_event_into(&mut buf) {
-                Ok(Event::Text(e)) => {
-                    let decoded = html_escape::decode_html_entities(
-                        e.decode()
-                            .map_err(|e| format!("Failed to decode: {}", e))?
-                            .as_ref(),
-                    );
-                    text.push_str(&decoded);
-                }
-                Ok(Event::Start(e)) => {
-                    // Handle nested tags like <s>, <br/>, etc.
-                    match e.name().as_ref() {
-                        b"s" | b"br" => {
-                            if !text.ends_with(' ') {
-                                text.push(' ');
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                Ok(Event::End(e)) if e.name().as_ref() == b"p" => break,
-                Ok(Event::Eof) => return Err("Unexpected EOF in p element".to_string()),
-                Err(e) => return Err(format!("XML parse error: {}", e)),
-                _ => {}
-            }
-            buf.clear();
-        }
+        })
+        .unwrap_or(0.0);
 
-        if text.trim().is_empty() {
-            return Ok(None);
+    let mut text = String::new();
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Text(e)) => {
+                text.push_str(&e.decode().map_err(|e| format!("Failed to decode: {}", e))?);
+            }
+            // quick-xml reports entity references on their own; keep them
+            // escaped so they are decoded together with the text below
+            Ok(Event::GeneralRef(e)) => {
+                let name = e.decode().map_err(|e| format!("Failed to decode: {}", e))?;
+                text.push_str(&format!("&{};", name));
+            }
+            // Handle nested tags like <s>, <br/>, etc.
+            Ok(Event::Start(e))
+                if matches!(e.name().as_ref(), b"s" | b"br") && !text.ends_with(' ') =>
+            {
+                text.push(' ');
+            }
+            Ok(Event::End(e)) if e.name().as_ref() == b"p" => break,
+            Ok(Event::Eof) => return Err("Unexpected EOF in p element".to_string()),
+            Err(e) => return Err(format!("XML parse error: {}", e)),
+            _ => {}
         }
+        buf.clear();
+    }
 
-        Ok(Some(crate::TranscriptItem {
-            text: text.trim().to_string(),
-            start,
-            duration,
-        }))
+    // The XML escaping is undone first, then the HTML escaping YouTube
+    // applies to caption text (`&amp;#39;` is an apostrophe)
+    let text = html_escape::decode_html_entities(&html_escape::decode_html_entities(&text));
+    if text.trim().is_empty() {
+        return Ok(None);
     }
+
+    Ok(Some(crate::TranscriptItem {
+        text: text.trim().to_string(),
+        start,
+        duration,
+    }))
 }
 
 mod html_escape {
@@ -202,15 +225,26 @@ mod html_escape {
         while let Some(ch) = chars.next() {
             if ch == '&' {
                 let mut entity = String::new();
+                let mut terminated = false;
                 while let Some(&next) = chars.peek() {
                     if next == ';' {
                         chars.next();
+                        terminated = true;
+                        break;
+                    }
+                    if !(next.is_ascii_alphanumeric() || next == '#') {
                         break;
                     }
                     entity.push(chars.next().unwrap());
                 }
 
-                result.push_str(&decode_entity(&entity));
+                if terminated {
+                    result.push_str(&decode_entity(&entity));
+                } else {
+                    // A bare ampersand, not an entity
+                    result.push('&');
+                    result.push_str(&entity);
+                }
             } else {
                 result.push(ch);
             }
@@ -314,6 +348,27 @@ mod tests {
         assert_eq!(items[1].start, 2.5);
     }
 
+    #[test]
+    fn test_parse_items_incrementally() {
+        let xml = r#"<transcript><text start="0" dur="1">One</text><text start="1" dur="1">Two</text><text"#;
+        let mut items = TranscriptParser::new(false).items(xml.as_bytes());
+        assert_eq!(items.next().unwrap().unwrap().text, "One");
+        assert_eq!(items.next().unwrap().unwrap().text, "Two");
+        assert!(items.next().unwrap().is_err());
+        assert!(items.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_escaped_entities() {
+        let xml = r#"<transcript><text start="0" dur="1">We&amp;#39;re &amp;amp; &lt;b&gt;</text></transcript>"#;
+        let items = TranscriptParser::new(false).parse(xml).unwrap();
+        assert_eq!(items[0].text, "We're & <b>");
+
+        let xml = r#"<timedtext><body><p t="0" d="1000">Rock &amp; roll<s>now</s></p></body></timedtext>"#;
+        let items = TranscriptParser::new(false).parse(xml).unwrap();
+        assert_eq!(items[0].text, "Rock & roll now");
+    }
+
     #[test]
     fn test_parse_p_format() {
         let xml = r#"<transcript>
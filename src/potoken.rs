@@ -0,0 +1,163 @@
+//! Proof-of-origin (PO) tokens for videos whose caption tracks YouTube only
+//! serves to clients that prove they are a real browser or app.
+//!
+//! `ytt` cannot mint tokens itself. A [`PoTokenProvider`] supplies them, either
+//! a fixed [`PoToken`] obtained elsewhere or a [`PoTokenCommand`] that asks a
+//! local helper program for one per video. The token and visitor data are sent
+//! with InnerTube player requests and appended to caption track requests.
+
+use crate::error::{Result, TranscriptError};
+use futures_channel::oneshot;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use std::process::Command;
+use std::thread;
+
+/// A PO token together with the visitor data it was generated for
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PoToken {
+    #[serde(alias = "poToken", alias = "po_token")]
+    pub token: String,
+    #[serde(default, alias = "visitorData")]
+    pub visitor_data: Option<String>,
+}
+
+impl PoToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            visitor_data: None,
+        }
+    }
+
+    pub fn with_visitor_data(mut self, visitor_data: impl Into<String>) -> Self {
+        self.visitor_data = Some(visitor_data.into());
+        self
+    }
+}
+
+/// Supplies PO tokens, see the [module documentation](self)
+pub trait PoTokenProvider: Send + Sync {
+    fn po_token(&self, video_id: &str) -> BoxFuture<'_, Result<PoToken>>;
+}
+
+/// A fixed token is used for every video
+impl PoTokenProvider for PoToken {
+    fn po_token(&self, _video_id: &str) -> BoxFuture<'_, Result<PoToken>> {
+        Box::pin(std::future::ready(Ok(self.clone())))
+    }
+}
+
+/// Gets tokens from a local helper program.
+///
+/// `{video_id}` in the arguments is replaced with the video's ID. The program
+/// prints either the bare token or a JSON object with `poToken` and optionally
+/// `visitorData`. Each run waits on its own thread, so a slow helper doesn't
+/// hold up the async runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoTokenCommand {
+    program: String,
+    args: Vec<String>,
+}
+
+impl PoTokenCommand {
+    pub fn new(program: impl Into<String>, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// A command line like `pot-helper --video {video_id}`, split at whitespace
+    pub fn parse(command_line: &str) -> Result<Self> {
+        let mut words = command_line.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| TranscriptError::ConfigError("Empty PO token command".to_string()))?;
+        Ok(Self::new(program, words))
+    }
+
+    fn run(&self, video_id: &str) -> Result<PoToken> {
+        let failed = |reason: String| TranscriptError::PoTokenProviderFailed {
+            message: format!("{}: {}", self.program, reason),
+            source: None,
+        };
+        let output = Command::new(&self.program)
+            .args(self.args.iter().map(|arg| arg.replace("{video_id}", video_id)))
+            .output()
+            .map_err(|e| TranscriptError::PoTokenProviderFailed {
+                message: format!("Failed to run {}", self.program),
+                source: Some(e.into()),
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(failed(format!("{} {}", output.status, stderr.trim())));
+        }
+        parse_output(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| failed("printed no token".to_string()))
+    }
+}
+
+impl PoTokenProvider for PoTokenCommand {
+    fn po_token(&self, video_id: &str) -> BoxFuture<'_, Result<PoToken>> {
+        let video_id = video_id.to_string();
+        Box::pin(async move {
+            let (sender, receiver) = oneshot::channel();
+            let command = self.clone();
+            thread::spawn(move || {
+                let _ = sender.send(command.run(&video_id));
+            });
+            receiver.await.unwrap_or_else(|_| {
+                Err(TranscriptError::PoTokenProviderFailed {
+                    message: format!("{}: helper thread panicked", self.program),
+                    source: None,
+                })
+            })
+        })
+    }
+}
+
+/// A helper's output: a JSON object or the bare token
+fn parse_output(output: &str) -> Option<PoToken> {
+    let output = output.trim();
+    if output.starts_with('{') {
+        return serde_json::from_str(output).ok();
+    }
+    (!output.is_empty()).then(|| PoToken::new(output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output() {
+        assert_eq!(parse_output("abc123\n"), Some(PoToken::new("abc123")));
+        assert_eq!(
+            parse_output(r#"{"poToken": "abc", "visitorData": "Cgt4"}"#),
+            Some(PoToken::new("abc").with_visitor_data("Cgt4"))
+        );
+        assert_eq!(parse_output("  "), None);
+        assert_eq!(parse_output("{not json"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_po_token_command() {
+        let command = PoTokenCommand::parse("echo token-for-{video_id}").unwrap();
+        let token = command.po_token("dQw4w9WgXcQ").await.unwrap();
+        assert_eq!(token.token, "token-for-dQw4w9WgXcQ");
+
+        let command = PoTokenCommand::parse("false").unwrap();
+        assert!(matches!(
+            command.po_token("dQw4w9WgXcQ").await,
+            Err(TranscriptError::PoTokenProviderFailed { source: None, .. })
+        ));
+
+        let command = PoTokenCommand::parse("ytt-no-such-helper {video_id}").unwrap();
+        assert!(matches!(
+            command.po_token("dQw4w9WgXcQ").await,
+            Err(TranscriptError::PoTokenProviderFailed { source: Some(_), .. })
+        ));
+        assert!(PoTokenCommand::parse("  ").is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use crate::chapters::{format_timestamp, parse_timestamp};
+use crate::TranscriptItem;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// Length of a transcript window in seconds
+const WINDOW_SECONDS: f64 = 60.0;
+/// Distance between the starts of consecutive (overlapping) windows
+const WINDOW_STRIDE_SECONDS: f64 = 30.0;
+
+// BM25 tuning parameters
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "but", "by", "did", "do", "does", "for",
+    "from", "had", "has", "have", "he", "her", "his", "how", "i", "if", "in", "is", "it", "its",
+    "me", "my", "of", "on", "or", "our", "she", "so", "that", "the", "their", "them", "they",
+    "this", "to", "was", "we", "were", "what", "when", "where", "which", "who", "why", "will",
+    "with", "you", "your",
+];
+
+/// A contiguous run of transcript items that was ranked against a query.
+#[derive(Debug, Clone)]
+pub struct TranscriptWindow {
+    pub start: f64,
+    pub end: f64,
+    pub score: f64,
+    pub items: Vec<TranscriptItem>,
+}
+
+/// A `[mm:ss]` reference found in an LLM answer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Citation {
+    /// The timestamp as written in the answer, without brackets
+    pub label: String,
+    pub seconds: f64,
+    /// Text of the transcript item the citation points at, if it matches one
+    pub text: Option<String>,
+}
+
+impl Citation {
+    pub fn is_verified(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+/// Find the parts of a transcript most relevant to `query`.
+///
+/// The transcript is split into overlapping windows which are ranked locally
+/// with BM25. At most `max_windows` windows with a non-zero score are
+/// returned, merged where they overlap and ordered chronologically.
+pub fn relevant_windows(
+    items: &[TranscriptItem],
+    query: &str,
+    max_windows: usize,
+) -> Vec<TranscriptWindow> {
+    let query_terms: HashSet<String> = tokenize(query).into_iter().collect();
+    if query_terms.is_empty() || max_windows == 0 {
+        return Vec::new();
+    }
+
+    let ranges = build_windows(items);
+    let documents: Vec<Vec<String>> = ranges
+        .iter()
+        .map(|range| {
+            items[range.clone()]
+                .iter()
+                .flat_map(|item| tokenize(&item.text))
+                .collect()
+        })
+        .collect();
+
+    let scores = bm25_scores(&documents, &query_terms);
+
+    let mut ranked: Vec<(Range<usize>, f64)> = ranges
+        .into_iter()
+        .zip(scores)
+        .filter(|(_, score)| *score > 0.0)
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked.truncate(max_windows);
+    ranked.sort_by_key(|(range, _)| range.start);
+
+    // Merge overlapping windows so no caption is sent twice
+    let mut merged: Vec<(Range<usize>, f64)> = Vec::new();
+    for (range, score) in ranked {
+        match merged.last_mut() {
+            Some((last, last_score)) if range.start < last.end => {
+                last.end = last.end.max(range.end);
+                *last_score = last_score.max(score);
+            }
+            _ => merged.push((range, score)),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(range, score)| {
+            let window_items = items[range].to_vec();
+            let start = window_items.first().map(|i| i.start).unwrap_or(0.0);
+            let end = window_items
+                .last()
+                .map(|i| i.start + i.duration)
+                .unwrap_or(start);
+            TranscriptWindow {
+                start,
+                end,
+                score,
+                items: window_items,
+            }
+        })
+        .collect()
+}
+
+/// Extract every `[mm:ss]` / `[h:mm:ss]` citation from `answer` and check it
+/// against the transcript it was supposedly taken from.
+///
+/// A citation is verified when a transcript item starts within that second or
+/// is still being spoken at that time.
+pub fn verify_citations(answer: &str, items: &[TranscriptItem]) -> Vec<Citation> {
+    let re = Regex::new(r"\[(\d{1,2}(?::\d{2}){1,2})\]").expect("valid citation regex");
+
+    let mut citations: Vec<Citation> = Vec::new();
+    for cap in re.captures_iter(answer) {
+        let label = cap[1].to_string();
+        if citations.iter().any(|c| c.label == label) {
+            continue;
+        }
+        let Some(seconds) = parse_timestamp(&label) else {
+            continue;
+        };
+
+        let text = items
+            .iter()
+            .find(|item| item.start.floor() == seconds)
+            .or_else(|| {
+                items
+                    .iter()
+                    .find(|item| item.start <= seconds && seconds < item.start + item.duration)
+            })
+            .map(|item| item.text.clone());
+
+        citations.push(Citation {
+            label,
+            seconds,
+            text,
+        });
+    }
+
+    citations
+}
+
+/// Render a window as `[mm:ss] text` lines, the form LLM prompts cite from.
+pub fn format_window(window: &TranscriptWindow) -> String {
+    window
+        .items
+        .iter()
+        .map(|item| format!("[{}] {}", format_timestamp(item.start), item.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn build_windows(items: &[TranscriptItem]) -> Vec<Range<usize>> {
+    let mut windows = Vec::new();
+    let mut begin = 0;
+
+    while begin < items.len() {
+        let window_start = items[begin].start;
+        let end = items[begin..]
+            .iter()
+            .position(|item| item.start >= window_start + WINDOW_SECONDS)
+            .map(|offset| begin + offset)
+            .unwrap_or(items.len())
+            .max(begin + 1);
+        windows.push(begin..end);
+
+        if end == items.len() {
+            break;
+        }
+
+        begin = items[begin + 1..]
+            .iter()
+            .position(|item| item.start >= window_start + WINDOW_STRIDE_SECONDS)
+            .map(|offset| begin + 1 + offset)
+            .unwrap_or(end);
+    }
+
+    windows
+}
+
+fn bm25_scores(documents: &[Vec<String>], query_terms: &HashSet<String>) -> Vec<f64> {
+    let doc_count = documents.len() as f64;
+    let avg_len = documents.iter().map(|d| d.len()).sum::<usize>() as f64 / doc_count.max(1.0);
+
+    let document_frequency: HashMap<&str, usize> = query_terms
+        .iter()
+        .map(|term| {
+            let df = documents.iter().filter(|d| d.contains(term)).count();
+            (term.as_str(), df)
+        })
+        .collect();
+
+    documents
+        .iter()
+        .map(|document| {
+            let len = document.len() as f64;
+            query_terms
+                .iter()
+                .map(|term| {
+                    let tf = document.iter().filter(|t| *t == term).count() as f64;
+                    if tf == 0.0 {
+                        return 0.0;
+                    }
+                    let df = document_frequency[term.as_str()] as f64;
+                    let idf = ((doc_count - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    idf * tf * (BM25_K1 + 1.0)
+                        / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * len / avg_len.max(1.0)))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|t| t.to_lowercase())
+        .filter(|t| t.chars().count() > 1 && !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(start: f64, text: &str) -> TranscriptItem {
+        TranscriptItem {
+            text: text.to_string(),
+            start,
+            duration: 4.0,
+        }
+    }
+
+    fn sample() -> Vec<TranscriptItem> {
+        vec![
+            item(0.0, "Welcome to the show"),
+            item(20.0, "Today we talk about gardening"),
+            item(45.0, "Tomatoes need lots of sun"),
+            item(90.0, "Now let's discuss pricing"),
+            item(100.0, "The pricing starts at ten dollars"),
+            item(150.0, "Thanks for watching"),
+        ]
+    }
+
+    #[test]
+    fn test_tokenize_drops_stopwords() {
+        assert_eq!(tokenize("What did they say about Pricing?"), vec!["say", "pricing"]);
+    }
+
+    #[test]
+    fn test_build_windows_overlap() {
+        let windows = build_windows(&sample());
+        assert_eq!(windows[0], 0..3);
+        assert_eq!(windows[1], 2..5);
+        assert_eq!(windows.last().unwrap().end, 6);
+    }
+
+    #[test]
+    fn test_relevant_windows_finds_pricing() {
+        let windows = relevant_windows(&sample(), "what did they say about pricing?", 1);
+        assert_eq!(windows.len(), 1);
+        assert!(windows[0].items.iter().any(|i| i.text.contains("ten dollars")));
+        assert!(windows[0].items.iter().all(|i| !i.text.contains("Welcome")));
+    }
+
+    #[test]
+    fn test_relevant_windows_no_match() {
+        assert!(relevant_windows(&sample(), "quantum physics", 3).is_empty());
+        assert!(relevant_windows(&sample(), "the", 3).is_empty());
+    }
+
+    #[test]
+    fn test_relevant_windows_merges_overlaps() {
+        let windows = relevant_windows(&sample(), "pricing tomatoes sun", 10);
+        for pair in windows.windows(2) {
+            assert!(pair[0].items.last().unwrap().start < pair[1].items[0].start);
+        }
+    }
+
+    #[test]
+    fn test_verify_citations() {
+        let answer = "Pricing starts at ten dollars [01:40], see also [01:32] and [09:59]. Again [01:40].";
+        let citations = verify_citations(answer, &sample());
+
+        assert_eq!(citations.len(), 3);
+        assert_eq!(citations[0].label, "01:40");
+        assert_eq!(citations[0].text.as_deref(), Some("The pricing starts at ten dollars"));
+        // Inside an item that started at 01:30
+        assert!(citations[1].is_verified());
+        assert!(!citations[2].is_verified());
+    }
+
+    #[test]
+    fn test_format_window() {
+        let window = TranscriptWindow {
+            start: 90.0,
+            end: 104.0,
+            score: 1.0,
+            items: sample()[3..5].to_vec(),
+        };
+        assert_eq!(
+            format_window(&window),
+            "[01:30] Now let's discuss pricing\n[01:40] The pricing starts at ten dollars"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use crate::error::{Result, TranscriptError};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Fields available in output templates, e.g. `{channel}/{upload_date}_{title}_{id}.{ext}`
+pub const TEMPLATE_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "channel",
+    "upload_date",
+    "lang",
+    "generated",
+    "playlist_index",
+    "ext",
+];
+
+/// Placeholder written for fields the video has no value for
+const MISSING_VALUE: &str = "NA";
+
+/// Values substituted into an [`OutputTemplate`] for one video.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateFields<'a> {
+    pub id: &'a str,
+    pub title: Option<&'a str>,
+    pub channel: Option<&'a str>,
+    pub upload_date: Option<&'a str>,
+    pub lang: &'a str,
+    pub is_generated: bool,
+    /// 1-based position of the video in the playlist or batch
+    pub playlist_index: Option<usize>,
+    pub ext: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field { name: String, width: Option<usize> },
+}
+
+/// A parsed `--output-template`.
+///
+/// Fields are written as `{name}`, numeric fields accept a zero-padded width
+/// (`{playlist_index:03}`) and literal braces are escaped as `{{` and `}}`.
+/// Every substituted value is sanitized, so a `/` in a title can never create
+/// a directory; only `/` in the template itself does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    segments: Vec<Segment>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |reason: String| {
+            TranscriptError::InvalidOutputTemplate(format!("{}: {}", template, reason))
+        };
+
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => return Err(invalid("unclosed '{'".to_string())),
+                        }
+                    }
+
+                    let (name, width) = match spec.split_once(':') {
+                        Some((name, format)) => {
+                            let width = format
+                                .strip_prefix('0')
+                                .unwrap_or(format)
+                                .parse::<usize>()
+                                .map_err(|_| invalid(format!("invalid width '{}' for {{{}}}", format, name)))?;
+                            (name, Some(width))
+                        }
+                        None => (spec.as_str(), None),
+                    };
+
+                    if !TEMPLATE_FIELDS.contains(&name) {
+                        return Err(invalid(format!(
+                            "unknown field {{{}}} (available: {})",
+                            name,
+                            TEMPLATE_FIELDS.join(", ")
+                        )));
+                    }
+                    if width.is_some() && name != "playlist_index" {
+                        return Err(invalid(format!("{{{}}} does not take a width", name)));
+                    }
+
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Field {
+                        name: name.to_string(),
+                        width,
+                    });
+                }
+                '}' => return Err(invalid("unmatched '}'".to_string())),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Render the template into a relative (or absolute, if the template is) path.
+    pub fn render(&self, fields: &TemplateFields) -> PathBuf {
+        let rendered: String = self
+            .segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Field { name, width } => field_value(fields, name, *width),
+            })
+            .collect();
+        PathBuf::from(rendered)
+    }
+}
+
+fn field_value(fields: &TemplateFields, name: &str, width: Option<usize>) -> String {
+    let value = match name {
+        "id" => Some(fields.id.to_string()),
+        "title" => fields.title.map(str::to_string),
+        "channel" => fields.channel.map(str::to_string),
+        "upload_date" => fields.upload_date.map(str::to_string),
+        "lang" => Some(fields.lang.to_string()),
+        "generated" => Some(if fields.is_generated { "generated" } else { "manual" }.to_string()),
+        "playlist_index" => fields
+            .playlist_index
+            .map(|index| format!("{:0width$}", index, width = width.unwrap_or(0))),
+        "ext" => Some(fields.ext.to_string()),
+        _ => None,
+    };
+
+    value
+        .map(|value| sanitize_filename(&value))
+        // "." and ".." would escape the directory the template describes
+        .filter(|value| !value.is_empty() && value.chars().any(|c| c != '.'))
+        .unwrap_or_else(|| MISSING_VALUE.to_string())
+}
+
+/// Return `path`, or a numbered variant of it (`name_2.txt`, `name_3.txt`, ...)
+/// if it was already used during this run, and remember the result.
+pub fn unique_path(path: PathBuf, used: &mut HashSet<PathBuf>) -> PathBuf {
+    if used.insert(path.clone()) {
+        return path;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+    let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+    (2..)
+        .map(|n| {
+            let filename = match &extension {
+                Some(ext) => format!("{}_{}.{}", stem, n, ext),
+                None => format!("{}_{}", stem, n),
+            };
+            parent.join(filename)
+        })
+        .find(|candidate| used.insert(candidate.clone()))
+        .expect("unbounded range always yields a free path")
+}
+
+/// Turn arbitrary text (usually a video title) into a safe file name component.
+pub fn sanitize_filename(title: &str) -> String {
+    // Replace invalid filesystem characters with underscores
+    let sanitized: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    // Trim whitespace and replace multiple spaces/underscores with single underscore
+    let sanitized = sanitized.trim();
+    let sanitized = sanitized
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+    let sanitized = sanitized
+        .chars()
+        .fold(String::new(), |mut acc, c| {
+            if c == '_' {
+                if !acc.ends_with('_') {
+                    acc.push(c);
+                }
+            } else {
+                acc.push(c);
+            }
+            acc
+        });
+
+    // Limit length to 200 bytes (reasonable for most filesystems)
+    let mut end = sanitized.len().min(200);
+    while !sanitized.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    sanitized[..end].trim_end_matches('_').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> TemplateFields<'static> {
+        TemplateFields {
+            id: "dQw4w9WgXcQ",
+            title: Some("Never Gonna Give You Up"),
+            channel: Some("Rick Astley"),
+            upload_date: Some("2009-10-24"),
+            lang: "en",
+            is_generated: false,
+            playlist_index: Some(7),
+            ext: "txt",
+        }
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = OutputTemplate::parse("{channel}/{upload_date}_{title}_{id}.{ext}").unwrap();
+        assert_eq!(
+            template.render(&fields()),
+            PathBuf::from("Rick_Astley/2009-10-24_Never_Gonna_Give_You_Up_dQw4w9WgXcQ.txt")
+        );
+    }
+
+    #[test]
+    fn test_render_padded_index_and_flags() {
+        let template = OutputTemplate::parse("{playlist_index:03}-{lang}-{generated}.{ext}").unwrap();
+        assert_eq!(template.render(&fields()), PathBuf::from("007-en-manual.txt"));
+
+        let generated = TemplateFields {
+            is_generated: true,
+            playlist_index: None,
+            ..fields()
+        };
+        assert_eq!(template.render(&generated), PathBuf::from("NA-en-generated.txt"));
+    }
+
+    #[test]
+    fn test_render_sanitizes_values() {
+        let template = OutputTemplate::parse("{channel}/{title}").unwrap();
+        let tricky = TemplateFields {
+            channel: Some(".."),
+            title: Some("AC/DC: Live"),
+            ..fields()
+        };
+        assert_eq!(template.render(&tricky), PathBuf::from("NA/AC_DC_Live"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(OutputTemplate::parse("{nope}.txt").is_err());
+        assert!(OutputTemplate::parse("{title").is_err());
+        assert!(OutputTemplate::parse("title}").is_err());
+        assert!(OutputTemplate::parse("{title:03}").is_err());
+        assert!(OutputTemplate::parse("{playlist_index:x}").is_err());
+        assert_eq!(
+            OutputTemplate::parse("{{literal}}").unwrap().render(&fields()),
+            PathBuf::from("{literal}")
+        );
+    }
+
+    #[test]
+    fn test_unique_path() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_path(PathBuf::from("out/a.txt"), &mut used), PathBuf::from("out/a.txt"));
+        assert_eq!(unique_path(PathBuf::from("out/a.txt"), &mut used), PathBuf::from("out/a_2.txt"));
+        assert_eq!(unique_path(PathBuf::from("out/a.txt"), &mut used), PathBuf::from("out/a_3.txt"));
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("Hello, World!"), "Hello_World");
+        assert_eq!(sanitize_filename("  spaced   out  "), "spaced_out");
+        let long = "é".repeat(150);
+        assert!(sanitize_filename(&long).len() <= 200);
+    }
+}
@@ -0,0 +1,399 @@
+//! The HTTP layer of [`YouTubeTranscript`](crate::YouTubeTranscript).
+//!
+//! Every request the client makes goes through an [`HttpTransport`], so it can
+//! run on another HTTP stack, or offline against recorded responses with
+//! [`ReplayTransport`]. [`RecordingTransport`] captures the responses of a real
+//! run in the format [`ReplayTransport`] reads.
+
+use crate::error::{Result, TranscriptError};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn get(url: impl Into<String>) -> Self {
+        Self {
+            method: Method::Get,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn post_json(url: impl Into<String>, body: &serde_json::Value) -> Self {
+        Self {
+            method: Method::Post,
+            url: url.into(),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: Some(body.to_string().into_bytes()),
+        }
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body as text, replacing invalid UTF-8
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// Sends the client's HTTP requests.
+///
+/// Implementations keep their own cookie store: cookies set by responses and
+/// by [`HttpTransport::add_cookie`] are sent with later requests to the same site.
+/// Non-2xx responses are returned as responses; errors are only for requests
+/// that got no response at all, reported as [`TranscriptError::HttpError`].
+pub trait HttpTransport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+
+    /// Store a cookie given in `Set-Cookie` syntax (`NAME=value; Domain=...`) as if `url` had set it
+    fn add_cookie(&self, url: &str, cookie: &str);
+}
+
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        (**self).send(request)
+    }
+
+    fn add_cookie(&self, url: &str, cookie: &str) {
+        (**self).add_cookie(url, cookie)
+    }
+}
+
+/// The default transport, a [`reqwest::Client`] with a cookie store
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    cookies: Arc<reqwest::cookie::Jar>,
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReqwestTransport {
+    pub fn new() -> Self {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::ACCEPT_LANGUAGE,
+            reqwest::header::HeaderValue::from_static("en-US"),
+        );
+        let cookies = Arc::new(reqwest::cookie::Jar::default());
+
+        Self {
+            client: reqwest::Client::builder()
+                .cookie_provider(cookies.clone())
+                .default_headers(headers)
+                .build()
+                .expect("Failed to create HTTP client"),
+            cookies,
+        }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut builder = match request.method {
+                Method::Get => self.client.get(&request.url),
+                Method::Post => self.client.post(&request.url),
+            };
+            for (name, value) in &request.headers {
+                builder = builder.header(name, value);
+            }
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+
+            let response = builder
+                .send()
+                .await
+                .map_err(|e| TranscriptError::http_transport(&request.url, e))?;
+            let status = response.status().as_u16();
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| TranscriptError::http_transport(&request.url, e))?;
+            Ok(HttpResponse {
+                status,
+                body: body.to_vec(),
+            })
+        })
+    }
+
+    fn add_cookie(&self, url: &str, cookie: &str) {
+        if let Ok(url) = url.parse::<reqwest::Url>() {
+            self.cookies.add_cookie_str(cookie, &url);
+        }
+    }
+}
+
+/// A request and the response it got, as stored in a fixture file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: Method,
+    pub url: String,
+    pub status: u16,
+    /// The response body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// File holding the response body, relative to the fixture file; used instead of `body`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_file: Option<String>,
+}
+
+/// Serves recorded responses instead of going to the network.
+///
+/// A fixture file is a JSON array of [`RecordedExchange`]s. Requests are
+/// matched on method and exact URL; when several exchanges match, they are
+/// served in order and the last one is repeated once all were used. A request
+/// without a recorded response fails with [`TranscriptError::HttpError`].
+#[derive(Debug, Default)]
+pub struct ReplayTransport {
+    exchanges: Vec<(RecordedExchange, Vec<u8>)>,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    served: Vec<bool>,
+    requests: Vec<HttpRequest>,
+    cookies: Vec<String>,
+}
+
+impl ReplayTransport {
+    /// Serve `exchanges`, which must carry their bodies inline
+    pub fn new(exchanges: Vec<RecordedExchange>) -> Self {
+        let exchanges: Vec<(RecordedExchange, Vec<u8>)> = exchanges
+            .into_iter()
+            .map(|exchange| {
+                let body = exchange.body.clone().unwrap_or_default().into_bytes();
+                (exchange, body)
+            })
+            .collect();
+        Self {
+            state: Mutex::new(ReplayState {
+                served: vec![false; exchanges.len()],
+                ..ReplayState::default()
+            }),
+            exchanges,
+        }
+    }
+
+    /// Load a fixture file and the body files it refers to
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let read_error = |file: &Path, e: std::io::Error| {
+            TranscriptError::io(format!("Failed to read fixture {}", file.display()), e)
+        };
+        let content = fs::read_to_string(path).map_err(|e| read_error(path, e))?;
+        let recorded: Vec<RecordedExchange> = serde_json::from_str(&content)?;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut transport = Self::new(Vec::new());
+        for exchange in recorded {
+            let body = match &exchange.body_file {
+                Some(file) => {
+                    let file = dir.join(file);
+                    fs::read(&file).map_err(|e| read_error(&file, e))?
+                }
+                None => exchange.body.clone().unwrap_or_default().into_bytes(),
+            };
+            transport.exchanges.push((exchange, body));
+        }
+        transport.state.get_mut().unwrap().served = vec![false; transport.exchanges.len()];
+        Ok(transport)
+    }
+
+    /// Every request sent so far, in order
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Cookies added with [`HttpTransport::add_cookie`], in order
+    pub fn cookies(&self) -> Vec<String> {
+        self.state.lock().unwrap().cookies.clone()
+    }
+}
+
+impl HttpTransport for ReplayTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        let mut state = self.state.lock().unwrap();
+        let matching: Vec<usize> = self
+            .exchanges
+            .iter()
+            .enumerate()
+            .filter(|(_, (exchange, _))| exchange.method == request.method && exchange.url == request.url)
+            .map(|(index, _)| index)
+            .collect();
+        let chosen = matching
+            .iter()
+            .copied()
+            .find(|&index| !state.served[index])
+            .or_else(|| matching.last().copied());
+
+        let result = match chosen {
+            Some(index) => {
+                state.served[index] = true;
+                let (exchange, body) = &self.exchanges[index];
+                Ok(HttpResponse {
+                    status: exchange.status,
+                    body: body.clone(),
+                })
+            }
+            None => Err(TranscriptError::http(
+                None,
+                format!("No recorded response for {:?} {}", request.method, request.url),
+            )),
+        };
+        state.requests.push(request);
+        Box::pin(std::future::ready(result))
+    }
+
+    fn add_cookie(&self, _url: &str, cookie: &str) {
+        self.state.lock().unwrap().cookies.push(cookie.to_string());
+    }
+}
+
+/// Passes requests on to another transport and keeps the exchanges, to be
+/// saved as a fixture file for [`ReplayTransport`]
+pub struct RecordingTransport<T> {
+    inner: T,
+    exchanges: Mutex<Vec<RecordedExchange>>,
+}
+
+impl<T: HttpTransport> RecordingTransport<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn exchanges(&self) -> Vec<RecordedExchange> {
+        self.exchanges.lock().unwrap().clone()
+    }
+
+    /// Write the exchanges recorded so far as a fixture file, with inline bodies
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.exchanges())?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+impl<T: HttpTransport> HttpTransport for RecordingTransport<T> {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let (method, url) = (request.method, request.url.clone());
+            let response = self.inner.send(request).await?;
+            self.exchanges.lock().unwrap().push(RecordedExchange {
+                method,
+                url,
+                status: response.status,
+                body: Some(response.text()),
+                body_file: None,
+            });
+            Ok(response)
+        })
+    }
+
+    fn add_cookie(&self, url: &str, cookie: &str) {
+        self.inner.add_cookie(url, cookie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn exchange(url: &str, status: u16, body: &str) -> RecordedExchange {
+        RecordedExchange {
+            method: Method::Get,
+            url: url.to_string(),
+            status,
+            body: Some(body.to_string()),
+            body_file: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_serves_in_order_then_repeats() {
+        let transport = ReplayTransport::new(vec![
+            exchange("https://example.com/a", 429, "slow down"),
+            exchange("https://example.com/a", 200, "ok"),
+        ]);
+
+        for expected in ["slow down", "ok", "ok"] {
+            let response = transport.send(HttpRequest::get("https://example.com/a")).await.unwrap();
+            assert_eq!(response.text(), expected);
+        }
+        assert!(matches!(
+            transport.send(HttpRequest::get("https://example.com/b")).await,
+            Err(TranscriptError::HttpError { .. })
+        ));
+        let post = HttpRequest::post_json("https://example.com/a", &serde_json::json!({}));
+        assert!(transport.send(post).await.is_err());
+        assert_eq!(transport.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_record_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("page.html"), "<html>page</html>").unwrap();
+        let fixture = temp_dir.path().join("fixtures.json");
+        fs::write(
+            &fixture,
+            r#"[{"method": "GET", "url": "https://example.com/page", "status": 200, "body_file": "page.html"}]"#,
+        )
+        .unwrap();
+
+        let recorder = RecordingTransport::new(ReplayTransport::load(&fixture).unwrap());
+        let response = recorder.send(HttpRequest::get("https://example.com/page")).await.unwrap();
+        assert_eq!(response.text(), "<html>page</html>");
+
+        let recorded = temp_dir.path().join("recorded.json");
+        recorder.save(&recorded).unwrap();
+        let replay = ReplayTransport::load(&recorded).unwrap();
+        let response = replay.send(HttpRequest::get("https://example.com/page")).await.unwrap();
+        assert_eq!(response.text(), "<html>page</html>");
+    }
+}
@@ -0,0 +1,260 @@
+//! A local stand-in for the parts of YouTube the client talks to, serving the
+//! recorded pages in `tests/fixtures`.
+//!
+//! The video ID picks the scenario:
+//!
+//! | Video ID      | Behaviour |
+//! |---------------|-----------|
+//! | `consent0000` | Consent interstitial until the `CONSENT` cookie is sent |
+//! | `consentFail` | Consent interstitial on every request |
+//! | `ratelimited` | HTTP 429 for the watch page |
+//! | `recaptcha00` | reCAPTCHA page instead of the watch page |
+//! | `botcheck000` | Player response asking to sign in to confirm you are not a bot |
+//! | `agerestrict` | Age-restricted player response |
+//! | `unavailable` | Player response for a removed video |
+//! | `unplayable0` | Player response for an unplayable video |
+//! | `nocaptions0` | Player response without captions |
+//! | `potoken0000` | Caption tracks that require a PO token (a `pot` parameter) |
+//! | `androidBlkd` | Bot check for the `ANDROID` client only |
+//! | `potokenWeb0` | Caption tracks that require a PO token unless the client is `WEB` |
+//! | anything else | A video with a manual English and a generated German track |
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use ytt::YouTubeTranscript;
+
+pub struct MockYouTube {
+    pub base_url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockYouTube {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_base_url = base_url.clone();
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let base_url = server_base_url.clone();
+                let requests = server_requests.clone();
+                tokio::spawn(async move { handle_connection(stream, &base_url, &requests).await });
+            }
+        });
+
+        Self { base_url, requests }
+    }
+
+    /// A client for this server that does not wait between requests
+    pub fn client(&self) -> YouTubeTranscript {
+        YouTubeTranscript::with_delay(0).with_base_url(&self.base_url)
+    }
+
+    /// `METHOD /path?query` of every request so far
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn has_cookie(&self, name: &str) -> bool {
+        self.headers.get("cookie").is_some_and(|cookies| {
+            cookies
+                .split(';')
+                .any(|cookie| cookie.trim().starts_with(&format!("{}=", name)))
+        })
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+        }
+    }
+
+    fn status(status: u16) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: String::new(),
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, base_url: &str, requests: &Mutex<Vec<String>>) {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let Ok(read) = stream.read(&mut chunk).await else {
+            return;
+        };
+        if read == 0 {
+            return;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    let mut body = buffer.split_off(head_end + 4);
+    while body.len() < content_length {
+        match stream.read(&mut chunk).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => body.extend_from_slice(&chunk[..read]),
+        }
+    }
+
+    requests.lock().unwrap().push(format!("{} {}", method, target));
+    let (path, query) = target.split_once('?').unwrap_or((target.as_str(), ""));
+    let request = Request {
+        method,
+        path: path.to_string(),
+        query: query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        headers,
+        body,
+    };
+
+    let response = route(&request, base_url);
+    let reply = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    );
+    let _ = stream.write_all(reply.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// A fixture with `{video_id}`, `{title}` and `{base_url}` filled in
+fn render(name: &str, video_id: &str, base_url: &str) -> String {
+    fixture(name)
+        .replace("{video_id}", video_id)
+        .replace("{title}", &format!("Mock video {}", video_id))
+        .replace("{base_url}", base_url)
+}
+
+fn route(request: &Request, base_url: &str) -> Response {
+    let param = |name: &str| request.query.get(name).map(String::as_str).unwrap_or_default();
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/watch") => watch_page(request, param("v"), base_url),
+        ("POST", "/youtubei/v1/player") if param("key") == "mock-api-key" => {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let client = body["context"]["client"]["clientName"].as_str().unwrap_or_default();
+            match body["videoId"].as_str() {
+                Some(video_id) => player_response(video_id, client, base_url),
+                None => Response::status(400),
+            }
+        }
+        ("GET", "/api/timedtext") if param("exp") == "xpe" && param("pot").is_empty() => Response::status(403),
+        ("GET", "/api/timedtext") => {
+            let name = match (param("lang"), param("tlang")) {
+                ("en", "") => "timedtext_en.xml",
+                ("de", "") => "timedtext_de.xml",
+                ("en", "fr") => "timedtext_en_fr.xml",
+                _ => return Response::status(404),
+            };
+            Response::ok("text/xml", fixture(name))
+        }
+        ("GET", "/playlist") if param("list") == "PLmock" => {
+            Response::ok("text/html", fixture("playlist.html"))
+        }
+        ("GET", "/@mock/videos") => Response::ok("text/html", fixture("playlist.html")),
+        _ => Response::status(404),
+    }
+}
+
+fn player_response(video_id: &str, client: &str, base_url: &str) -> Response {
+    let mut player: serde_json::Value = serde_json::from_str(&render("player.json", video_id, base_url)).unwrap();
+    let playability = |status: &str, reason: &str| serde_json::json!({ "status": status, "reason": reason });
+
+    match video_id {
+        "botcheck000" | "androidBlkd" if video_id == "botcheck000" || client == "ANDROID" => {
+            player["playabilityStatus"] = playability("LOGIN_REQUIRED", "Sign in to confirm you're not a bot")
+        }
+        "agerestrict" => {
+            player["playabilityStatus"] = playability(
+                "LOGIN_REQUIRED",
+                "This video may be inappropriate for some users.",
+            )
+        }
+        "unavailable" => player["playabilityStatus"] = playability("ERROR", "This video is unavailable"),
+        "unplayable0" => {
+            player["playabilityStatus"] = playability("UNPLAYABLE", "The uploader has not made this video available in your country")
+        }
+        "nocaptions0" => {
+            player.as_object_mut().unwrap().remove("captions");
+        }
+        "potoken0000" | "potokenWeb0" if video_id == "potoken0000" || client != "WEB" => {
+            let tracks = player["captions"]["playerCaptionsTracklistRenderer"]["captionTracks"]
+                .as_array_mut()
+                .unwrap();
+            for track in tracks {
+                let url = format!("{}&exp=xpe", track["baseUrl"].as_str().unwrap());
+                track["baseUrl"] = url.into();
+            }
+        }
+        _ => {}
+    }
+    Response::ok("application/json", player.to_string())
+}
+
+fn watch_page(request: &Request, video_id: &str, base_url: &str) -> Response {
+    match video_id {
+        "ratelimited" => Response::status(429),
+        "recaptcha00" => Response::ok("text/html", fixture("recaptcha.html")),
+        "consentFail" => Response::ok("text/html", render("consent.html", video_id, base_url)),
+        "consent0000" if !request.has_cookie("CONSENT") => {
+            Response::ok("text/html", render("consent.html", video_id, base_url))
+        }
+        _ => Response::ok("text/html", render("watch.html", video_id, base_url)),
+    }
+}
@@ -0,0 +1,269 @@
+//! End-to-end tests of the client against a local mock of YouTube
+
+mod common;
+
+use common::MockYouTube;
+use futures_util::{StreamExt, TryStreamExt};
+use ytt::innertube::ClientProfile;
+use ytt::languages::LanguagePolicy;
+use ytt::potoken::PoToken;
+use ytt::{Stage, TranscriptError, TranscriptFilter, TranslationOrigin};
+
+#[tokio::test]
+async fn test_fetch_transcript() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let transcript = api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+    assert_eq!(transcript.title.as_deref(), Some("Mock video dQw4w9WgXcQ"));
+    assert_eq!(transcript.channel.as_deref(), Some("Mock Channel"));
+    assert_eq!(transcript.upload_date.as_deref(), Some("2009-10-24"));
+    assert_eq!(transcript.language_code, "en");
+    assert!(!transcript.is_generated);
+    assert_eq!(transcript.transcript.len(), 2);
+    assert_eq!(transcript.transcript[0].text, "We're no strangers to love");
+    assert_eq!(transcript.transcript[1].text, "You know the rules & so do I");
+    assert_eq!(transcript.transcript[1].start, 2.6);
+
+    assert_eq!(
+        server.requests(),
+        [
+            "GET /watch?v=dQw4w9WgXcQ",
+            "POST /youtubei/v1/player?key=mock-api-key",
+            "GET /api/timedtext?v=dQw4w9WgXcQ&lang=en",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_list_and_fetch_generated_srv3_track() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let list = api.list_transcripts("dQw4w9WgXcQ").await.unwrap();
+    assert_eq!(list.manually_created.len(), 1);
+    let german = list.find_generated(&["de"]).unwrap();
+    // Relative caption URLs are resolved against the base URL
+    assert!(german.base_url.starts_with(&server.base_url));
+
+    let transcript = list.fetch(&api, german).await.unwrap();
+    assert!(transcript.is_generated);
+    assert_eq!(transcript.transcript[0].text, "Wir sind keine Fremden");
+    assert_eq!(transcript.transcript[0].duration, 2.1);
+}
+
+#[tokio::test]
+async fn test_translate_transcript() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let transcript = api.translate_transcript("dQw4w9WgXcQ", &["en"], "fr").await.unwrap();
+    assert_eq!(transcript.language, "French");
+    assert_eq!(transcript.translation_origin, Some(TranslationOrigin::YouTube));
+    assert_eq!(transcript.transcript[0].text, "Nous ne sommes pas des inconnus");
+
+    assert!(matches!(
+        api.translate_transcript("dQw4w9WgXcQ", &["en"], "ja").await,
+        Err(TranscriptError::TranslationLanguageNotAvailable(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_fetch_all_and_policy_from_one_listing() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let filter = TranscriptFilter {
+        translations: vec!["fr".to_string()],
+        ..TranscriptFilter::default()
+    };
+    let transcripts = api.fetch_all_transcripts("dQw4w9WgXcQ", &filter).await.unwrap();
+    let codes: Vec<&str> = transcripts
+        .iter()
+        .map(|t| t.as_ref().unwrap().language_code.as_str())
+        .collect();
+    assert_eq!(codes, ["en", "de", "fr"]);
+    let watch_requests = server.requests().iter().filter(|r| r.starts_with("GET /watch")).count();
+    assert_eq!(watch_requests, 1);
+
+    let policy = LanguagePolicy {
+        match_region: true,
+        ..LanguagePolicy::default()
+    };
+    let transcript = api
+        .fetch_transcript_with_policy("dQw4w9WgXcQ", &["de-AT"], &policy)
+        .await
+        .unwrap();
+    assert_eq!(transcript.language_code, "de");
+}
+
+#[tokio::test]
+async fn test_consent_interstitial() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let transcript = api.fetch_transcript("consent0000", None).await.unwrap();
+    assert_eq!(transcript.transcript.len(), 2);
+    let watch_requests = server.requests().iter().filter(|r| r.starts_with("GET /watch")).count();
+    assert_eq!(watch_requests, 2);
+
+    assert!(matches!(
+        api.fetch_transcript("consentFail", None).await,
+        Err(TranscriptError::FailedToCreateConsentCookie(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_blocked_requests() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    assert!(matches!(
+        api.fetch_transcript("ratelimited", None).await,
+        Err(TranscriptError::IpBlocked(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("recaptcha00", None).await,
+        Err(TranscriptError::IpBlocked(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("botcheck000", None).await,
+        Err(TranscriptError::RequestBlocked(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_playability_errors() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    assert!(matches!(
+        api.fetch_transcript("agerestrict", None).await,
+        Err(TranscriptError::AgeRestricted(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("unavailable", None).await,
+        Err(TranscriptError::VideoUnavailable(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("unplayable0", None).await,
+        Err(TranscriptError::VideoUnplayable(_, reason)) if reason.contains("your country")
+    ));
+    assert!(matches!(
+        api.fetch_transcript("nocaptions0", None).await,
+        Err(TranscriptError::TranscriptsDisabled(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("potoken0000", None).await,
+        Err(TranscriptError::PoTokenRequired(_))
+    ));
+    assert!(matches!(
+        api.fetch_transcript("dQw4w9WgXcQ", Some(vec!["ja"])).await,
+        Err(TranscriptError::NoTranscriptFound(_, _))
+    ));
+}
+
+#[tokio::test]
+async fn test_client_fallback() {
+    let server = MockYouTube::start().await;
+    let player_requests = || {
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.starts_with("POST /youtubei/v1/player"))
+            .count()
+    };
+
+    // The default client alone gives up
+    assert!(matches!(
+        server.client().fetch_transcript("androidBlkd", None).await,
+        Err(TranscriptError::RequestBlocked(_))
+    ));
+    assert_eq!(player_requests(), 1);
+
+    let api = server
+        .client()
+        .with_clients([ClientProfile::android(), ClientProfile::ios(), ClientProfile::web()]);
+    let transcript = api.fetch_transcript("androidBlkd", None).await.unwrap();
+    assert_eq!(transcript.language_code, "en");
+    assert_eq!(player_requests(), 3);
+
+    // IOS needs a PO token for every track, WEB does not
+    let transcript = api.fetch_transcript("potokenWeb0", None).await.unwrap();
+    assert!(!transcript.transcript.is_empty());
+    assert_eq!(player_requests(), 6);
+
+    // Without a client left to try, the tracks are still listed
+    let transcript_list = api.list_transcripts("potoken0000").await.unwrap();
+    assert!(transcript_list.manually_created["en"].requires_po_token());
+}
+
+#[tokio::test]
+async fn test_po_token() {
+    let server = MockYouTube::start().await;
+    let api = server.client().with_po_token_provider(PoToken::new("mock/token"));
+
+    let transcript = api.fetch_transcript("potoken0000", None).await.unwrap();
+    assert!(!transcript.transcript.is_empty());
+    assert!(server
+        .requests()
+        .iter()
+        .any(|r| r.starts_with("GET /api/timedtext") && r.ends_with("&exp=xpe&pot=mock%2Ftoken")));
+
+    // With a token, the first client's tracks are good enough: the player
+    // request is only repeated with the token, no other client is tried
+    let api = server
+        .client()
+        .with_clients([ClientProfile::ios(), ClientProfile::web()])
+        .with_po_token_provider(PoToken::new("mock-token"));
+    let before = server.requests().len();
+    api.fetch_transcript("potokenWeb0", None).await.unwrap();
+    assert_eq!(server.requests().len() - before, 4);
+
+    // Unprotected tracks need neither a token nor a second player request
+    let before = server.requests().len();
+    api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap();
+    assert_eq!(server.requests().len() - before, 3);
+}
+
+#[tokio::test]
+async fn test_playlist_and_channel_pages() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let expected = ["dQw4w9WgXcQ", "consent0000", "nocaptions0"];
+    assert_eq!(api.get_playlist_video_ids("PLmock").await.unwrap(), expected);
+    let channel = format!("{}/@mock", server.base_url);
+    assert_eq!(api.get_channel_video_ids(&channel).await.unwrap(), expected);
+
+    let err = api.get_playlist_video_ids("PLmissing").await.unwrap_err();
+    assert!(matches!(
+        err,
+        TranscriptError::HttpError { status: Some(404), stage: Some(Stage::Playlist), .. }
+    ));
+    assert!(err.is_permanent());
+}
+
+#[tokio::test]
+async fn test_streams() {
+    let server = MockYouTube::start().await;
+    let api = server.client();
+
+    let expected = api.fetch_transcript("dQw4w9WgXcQ", None).await.unwrap().transcript;
+    let streamed: Vec<_> = api.fetch_transcript_stream("dQw4w9WgXcQ", None).try_collect().await.unwrap();
+    assert_eq!(
+        streamed.iter().map(|item| &item.text).collect::<Vec<_>>(),
+        expected.iter().map(|item| &item.text).collect::<Vec<_>>()
+    );
+
+    let failed: Vec<_> = api.fetch_transcript_stream("nocaptions0", None).collect().await;
+    assert!(matches!(failed[..], [Err(TranscriptError::TranscriptsDisabled(_))]));
+
+    let results: Vec<_> = api.fetch_playlist_stream("PLmock", &["en"]).await.unwrap().collect().await;
+    let ids: Vec<_> = results.iter().map(|(video_id, _)| video_id.as_str()).collect();
+    assert_eq!(ids, ["dQw4w9WgXcQ", "consent0000", "nocaptions0"]);
+    assert!(results[0].1.is_ok() && results[1].1.is_ok());
+    assert!(matches!(results[2].1, Err(TranscriptError::TranscriptsDisabled(_))));
+
+    assert!(api.fetch_playlist_stream("PLmissing", &["en"]).await.is_err());
+}